@@ -0,0 +1,422 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+use zstd::stream::{decode_all, encode_all, Decoder};
+
+use super::backend::BackendKind;
+use super::content_store::{self, ContentStore};
+use super::journal;
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, CheckpointPaths, SessionTimeline, TimelineNode, SESSION_STATE_FILE};
+
+/// Identifies a file as a Claudia timeline archive
+const ARCHIVE_FORMAT: &str = "claudia-timeline";
+
+/// Archive directory holding content blobs, as laid out by the original per-session pools
+const CONTENT_POOL_PREFIX: &str = "files/content_pool/";
+
+/// Current archive format version
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Largest archive accepted on import, both as a file and once decompressed
+pub const MAX_ARCHIVE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Portable archive of a session's `.timelines/<session_id>` tree
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimelineArchive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    /// Project the timeline was exported from
+    pub project_id: String,
+    /// Session the timeline was exported from
+    pub session_id: String,
+    /// Every file of the timeline tree
+    pub entries: Vec<ArchiveEntry>,
+}
+
+/// A single file within a timeline archive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Path relative to the session's timeline directory, using `/` separators
+    pub path: String,
+    /// SHA-256 of the file's bytes
+    pub sha256: String,
+    /// Base64-encoded file bytes
+    pub data: String,
+}
+
+/// Summary of an exported or imported timeline archive
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSummary {
+    pub project_id: String,
+    pub session_id: String,
+    /// Number of checkpoints in the timeline
+    pub checkpoints: usize,
+    /// Number of files packed or unpacked
+    pub files: usize,
+    /// Total size of the files in bytes
+    pub total_bytes: u64,
+}
+
+impl CheckpointStorage {
+    /// Pack a session's timeline into a zstd-compressed archive
+    pub fn export_timeline_archive(
+        &self,
+        project_id: &str,
+        session_id: &str,
+    ) -> Result<(Vec<u8>, ArchiveSummary)> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let base_dir = paths
+            .timeline_file
+            .parent()
+            .context("Invalid timeline path")?
+            .to_path_buf();
+
+        if !paths.timeline_file.exists() {
+            anyhow::bail!("No timeline found for session {}", session_id);
+        }
+        if self.backend_kind() != BackendKind::File {
+            anyhow::bail!("Archives can only be exported from file checkpoint storage");
+        }
+        let timeline = self.load_timeline(&paths.timeline_file)?;
+        // Opening the store first moves any old per-session pool out of the way
+        let store = ContentStore::open(&paths.content_store_dir)?;
+
+        let mut entries = Vec::new();
+        let mut total_bytes = 0u64;
+        for entry in WalkDir::new(&base_dir).sort_by_file_name() {
+            let entry = entry.context("Failed to walk timeline directory")?;
+            if !entry.file_type().is_file()
+                || matches!(
+                    entry.file_name().to_str(),
+                    Some(journal::LOCK_FILE | journal::JOURNAL_FILE | SESSION_STATE_FILE)
+                )
+            {
+                continue;
+            }
+
+            let rel = entry
+                .path()
+                .strip_prefix(&base_dir)
+                .context("Timeline file outside timeline directory")?;
+            let data = fs::read(entry.path())
+                .with_context(|| format!("Failed to read {}", rel.display()))?;
+            total_bytes += data.len() as u64;
+
+            entries.push(ArchiveEntry {
+                path: rel
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                sha256: Self::calculate_file_hash(&data),
+                data: STANDARD.encode(&data),
+            });
+        }
+
+        // Content lives in the project-wide store; pack what this session references
+        let mut hashes = BTreeSet::new();
+        let refs_dir = paths.files_dir.join("refs");
+        if refs_dir.exists() {
+            for checkpoint_entry in fs::read_dir(&refs_dir)? {
+                hashes.extend(content_store::referenced_hashes(&checkpoint_entry?.path()));
+            }
+        }
+        for hash in hashes {
+            let Some(data) = store.get_compressed(&hash)? else {
+                log::warn!("Content file missing for hash: {}", hash);
+                continue;
+            };
+            total_bytes += data.len() as u64;
+
+            entries.push(ArchiveEntry {
+                path: format!("{}{}", CONTENT_POOL_PREFIX, hash),
+                sha256: Self::calculate_file_hash(&data),
+                data: STANDARD.encode(&data),
+            });
+        }
+
+        let summary = ArchiveSummary {
+            project_id: project_id.to_string(),
+            session_id: session_id.to_string(),
+            checkpoints: timeline.total_checkpoints,
+            files: entries.len(),
+            total_bytes,
+        };
+
+        let archive = TimelineArchive {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            project_id: project_id.to_string(),
+            session_id: session_id.to_string(),
+            entries,
+        };
+
+        let json = serde_json::to_vec(&archive).context("Failed to serialize archive")?;
+        let compressed = encode_all(&json[..], 19).context("Failed to compress archive")?;
+
+        Ok((compressed, summary))
+    }
+
+    /// Unpack a timeline archive into a project, optionally under a new session ID.
+    ///
+    /// Every entry is checked against its SHA-256 hash, and content pool blobs
+    /// against the content hash they are stored under, before anything is written.
+    /// Fails if the target session already has checkpoints.
+    pub fn import_timeline_archive(
+        &self,
+        archive_bytes: &[u8],
+        project_id: &str,
+        session_id: Option<&str>,
+    ) -> Result<ArchiveSummary> {
+        if self.backend_kind() != BackendKind::File {
+            anyhow::bail!("Archives can only be imported into file checkpoint storage");
+        }
+        if archive_bytes.len() as u64 > MAX_ARCHIVE_SIZE {
+            anyhow::bail!("Archive is larger than {} bytes", MAX_ARCHIVE_SIZE);
+        }
+
+        // Stop decompressing past the cap rather than trusting the archive's size
+        let mut json = Vec::new();
+        Decoder::new(archive_bytes)
+            .context("Failed to decompress archive")?
+            .take(MAX_ARCHIVE_SIZE + 1)
+            .read_to_end(&mut json)
+            .context("Failed to decompress archive")?;
+        if json.len() as u64 > MAX_ARCHIVE_SIZE {
+            anyhow::bail!(
+                "Archive decompresses to more than {} bytes",
+                MAX_ARCHIVE_SIZE
+            );
+        }
+        let archive: TimelineArchive =
+            serde_json::from_slice(&json).context("Failed to parse archive")?;
+
+        if archive.format != ARCHIVE_FORMAT {
+            anyhow::bail!("Not a timeline archive: {}", archive.format);
+        }
+        if archive.version != ARCHIVE_VERSION {
+            anyhow::bail!(
+                "Unsupported archive version: {}. This version of the app only supports version {}.",
+                archive.version,
+                ARCHIVE_VERSION
+            );
+        }
+
+        let session_id = session_id.unwrap_or(&archive.session_id).to_string();
+        validate_session_id(&session_id)?;
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, &session_id);
+        let base_dir = paths
+            .timeline_file
+            .parent()
+            .context("Invalid timeline path")?
+            .to_path_buf();
+
+        if paths.timeline_file.exists() {
+            let existing = self.load_timeline(&paths.timeline_file)?;
+            if existing.root_node.is_some() {
+                anyhow::bail!("Session {} already has checkpoints", session_id);
+            }
+        }
+
+        // Decode and verify everything up front so a bad archive leaves no trace
+        let mut files = Vec::with_capacity(archive.entries.len());
+        let mut blobs = Vec::new();
+        let mut total_bytes = 0u64;
+        for entry in &archive.entries {
+            let rel = safe_relative_path(&entry.path)?;
+            let data = STANDARD
+                .decode(&entry.data)
+                .with_context(|| format!("Invalid data for {}", entry.path))?;
+
+            if Self::calculate_file_hash(&data) != entry.sha256 {
+                anyhow::bail!("Hash mismatch for {}", entry.path);
+            }
+            total_bytes += data.len() as u64;
+
+            if let Some(content_hash) = entry.path.strip_prefix(CONTENT_POOL_PREFIX) {
+                let content = decode_all(&data[..])
+                    .with_context(|| format!("Failed to decompress {}", entry.path))?;
+                if Self::calculate_file_hash(&content) != content_hash {
+                    anyhow::bail!("Content hash mismatch for {}", entry.path);
+                }
+                blobs.push((content_hash.to_string(), data));
+            } else {
+                files.push((rel, data));
+            }
+        }
+        let file_count = files.len() + blobs.len();
+
+        // Unpack into a staging directory, then move it into place
+        let timelines_dir = base_dir.parent().context("Invalid timeline path")?;
+        fs::create_dir_all(timelines_dir).context("Failed to create timelines directory")?;
+        let staging_dir = timelines_dir.join(format!(".import-{}", uuid::Uuid::new_v4()));
+
+        let unpack = || -> Result<SessionTimeline> {
+            for (rel, data) in &files {
+                let target = staging_dir.join(rel);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, data)
+                    .with_context(|| format!("Failed to write {}", rel.display()))?;
+            }
+
+            let timeline_file = staging_dir.join("timeline.json");
+            let mut timeline = self.load_timeline(&timeline_file)?;
+            timeline.session_id = session_id.clone();
+            if let Some(root) = &mut timeline.root_node {
+                reassign_node(root, project_id, &session_id);
+            }
+            self.save_timeline(&timeline_file, &timeline)?;
+
+            // Keep per-checkpoint metadata consistent with the new location
+            let checkpoints_dir = staging_dir.join("checkpoints");
+            if checkpoints_dir.exists() {
+                for entry in fs::read_dir(&checkpoints_dir)? {
+                    let metadata_file = entry?.path().join("metadata.json");
+                    if !metadata_file.exists() {
+                        continue;
+                    }
+                    let mut checkpoint: Checkpoint =
+                        serde_json::from_str(&fs::read_to_string(&metadata_file)?)
+                            .context("Failed to parse checkpoint metadata")?;
+                    checkpoint.project_id = project_id.to_string();
+                    checkpoint.session_id = session_id.clone();
+                    fs::write(&metadata_file, serde_json::to_string_pretty(&checkpoint)?)?;
+                }
+            }
+
+            Ok(timeline)
+        };
+
+        let timeline = match unpack() {
+            Ok(timeline) => timeline,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging_dir);
+                return Err(e);
+            }
+        };
+
+        if base_dir.exists() {
+            fs::remove_dir_all(&base_dir).context("Failed to replace empty timeline")?;
+        }
+        fs::rename(&staging_dir, &base_dir).context("Failed to move imported timeline")?;
+
+        // Add the content to the project's store, then count the new references
+        let store = ContentStore::open(&paths.content_store_dir)?;
+        for (hash, data) in &blobs {
+            store.insert_compressed(hash, data)?;
+        }
+        store.collect_garbage()?;
+
+        Ok(ArchiveSummary {
+            project_id: project_id.to_string(),
+            session_id,
+            checkpoints: timeline.total_checkpoints,
+            files: file_count,
+            total_bytes,
+        })
+    }
+}
+
+/// Validate an archive entry path, rejecting anything that could escape the timeline directory
+fn safe_relative_path(path: &str) -> Result<PathBuf> {
+    let rel = Path::new(path);
+    if path.is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        anyhow::bail!("Invalid path in archive: {}", path);
+    }
+    Ok(rel.to_path_buf())
+}
+
+/// Check that a session ID is a plain identifier, as it becomes a directory name
+fn validate_session_id(session_id: &str) -> Result<()> {
+    let is_plain = !session_id.is_empty()
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_plain {
+        anyhow::bail!("Invalid session ID: {:?}", session_id);
+    }
+    Ok(())
+}
+
+/// Point every checkpoint in the tree at a new project and session
+fn reassign_node(node: &mut TimelineNode, project_id: &str, session_id: &str) {
+    node.checkpoint.project_id = project_id.to_string();
+    node.checkpoint.session_id = session_id.to_string();
+    for child in &mut node.children {
+        reassign_node(child, project_id, session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::manager::CheckpointManager;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_archive_round_trip_into_another_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let claude_dir = temp_dir.path().join("claude");
+        let project_path = temp_dir.path().join("project");
+        fs::create_dir_all(&project_path).unwrap();
+        fs::write(project_path.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let manager = CheckpointManager::new(
+            "source".to_string(),
+            "session".to_string(),
+            project_path,
+            claude_dir.clone(),
+        )
+        .await
+        .unwrap();
+        let created = manager.create_checkpoint(None, None).await.unwrap();
+
+        let storage = CheckpointStorage::new(claude_dir.clone());
+        let (archive, exported) = storage
+            .export_timeline_archive("source", "session")
+            .unwrap();
+        let imported = storage
+            .import_timeline_archive(&archive, "target", Some("copy"))
+            .unwrap();
+
+        assert_eq!(imported.checkpoints, 1);
+        assert_eq!(imported.files, exported.files);
+
+        let (checkpoint, snapshots, _) = storage
+            .load_checkpoint("target", "copy", &created.checkpoint.id)
+            .unwrap();
+        assert_eq!(checkpoint.project_id, "target");
+        assert_eq!(checkpoint.session_id, "copy");
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].content, b"fn main() {}\n");
+
+        // A second import into the same session is refused
+        assert!(storage
+            .import_timeline_archive(&archive, "target", Some("copy"))
+            .is_err());
+
+        // Session IDs that are not plain identifiers could escape `.timelines`
+        for session_id in ["../escape", "a/b", ""] {
+            assert!(storage
+                .import_timeline_archive(&archive, "target", Some(session_id))
+                .is_err());
+        }
+        assert!(!claude_dir
+            .join("projects")
+            .join("target")
+            .join("escape")
+            .exists());
+    }
+}
@@ -0,0 +1,297 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zstd::stream::decode_all;
+
+use super::content_store::ContentStore;
+use super::git_backend::GitBackend;
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, CheckpointPaths, FileSnapshot, TimelineIssueKind};
+
+/// Per-project backend selection, stored under the project's `.timelines`
+const BACKEND_CONFIG_FILE: &str = "backend.json";
+
+/// Where the content of checkpoint file snapshots is kept.
+///
+/// The timeline, checkpoint metadata, messages and file references always
+/// live under `.timelines`; a backend only stores the bytes of snapshotted
+/// files.
+pub trait StorageBackend: Send + Sync {
+    /// Which kind of backend this is
+    fn kind(&self) -> BackendKind;
+
+    /// Store the content of a checkpoint's snapshots. Returns the snapshots
+    /// that could not be stored; an error means nothing was stored.
+    fn save_contents(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint: &Checkpoint,
+        snapshots: &[&FileSnapshot],
+    ) -> Result<Vec<(PathBuf, anyhow::Error)>>;
+
+    /// Fill in the content of a checkpoint's snapshots. Content that cannot
+    /// be found is left empty.
+    fn load_contents(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        snapshots: &mut [FileSnapshot],
+    ) -> Result<()>;
+
+    /// Check that a snapshot's content exists and matches its hash
+    fn check_content(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        file_path: &Path,
+        hash: &str,
+    ) -> Option<TimelineIssueKind>;
+
+    /// Check backend-specific state of a checkpoint, returning a description of any problem
+    fn check_checkpoint(&self, _paths: &CheckpointPaths, _checkpoint_id: &str) -> Option<String> {
+        None
+    }
+
+    /// Give a checkpoint a hold on content it took over from a removed ancestor
+    fn retain_contents(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        hashes: &[String],
+    ) -> Result<()>;
+
+    /// Drop a removed checkpoint's hold on its content
+    fn release_checkpoint(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        hashes: &[String],
+    ) -> Result<()>;
+
+    /// Delete content no checkpoint of the project refers to, returning how much was deleted
+    fn collect_garbage(&self, content_store_dir: &Path) -> Result<usize>;
+}
+
+/// Available storage backends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// zstd-compressed blobs in the project's content store
+    #[default]
+    File,
+    /// Git objects, one commit per checkpoint on a hidden ref
+    Git,
+}
+
+/// Storage backend selected for a project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendConfig {
+    pub backend: BackendKind,
+    /// Repository holding the checkpoint commits, for the git backend
+    pub repo_path: Option<PathBuf>,
+}
+
+/// Load a project's backend selection, falling back to the file backend
+pub fn load_config(claude_dir: &Path, project_id: &str) -> BackendConfig {
+    let config_file = config_path(claude_dir, project_id);
+    if !config_file.exists() {
+        return BackendConfig::default();
+    }
+
+    match fs::read_to_string(&config_file)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| serde_json::from_str(&json).map_err(anyhow::Error::from))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!(
+                "Failed to read {:?}, using file storage: {}",
+                config_file,
+                e
+            );
+            BackendConfig::default()
+        }
+    }
+}
+
+/// Save a project's backend selection
+pub fn save_config(claude_dir: &Path, project_id: &str, config: &BackendConfig) -> Result<()> {
+    let config_file = config_path(claude_dir, project_id);
+    if let Some(parent) = config_file.parent() {
+        fs::create_dir_all(parent).context("Failed to create timelines directory")?;
+    }
+    let json =
+        serde_json::to_string_pretty(config).context("Failed to serialize backend config")?;
+    fs::write(&config_file, json).context("Failed to write backend config")
+}
+
+/// Open the backend described by `config`. For the git backend the
+/// configured repository is used, falling back to `project_path`.
+pub fn open_backend(
+    config: &BackendConfig,
+    project_path: Option<&Path>,
+    compression_level: i32,
+) -> Result<Arc<dyn StorageBackend>> {
+    match config.backend {
+        BackendKind::File => Ok(Arc::new(FileBackend::new(compression_level))),
+        BackendKind::Git => {
+            let repo_path = config
+                .repo_path
+                .as_deref()
+                .or(project_path)
+                .context("No repository configured for git checkpoint storage")?;
+            Ok(Arc::new(GitBackend::open(repo_path)?))
+        }
+    }
+}
+
+/// Select the storage backend for a project, returning the saved configuration.
+///
+/// Fails if any session of the project already has checkpoints, since their
+/// content would be left behind in the old backend, or if the git backend is
+/// selected and `project_path` is not a git repository.
+pub fn select_backend(
+    claude_dir: &Path,
+    project_id: &str,
+    kind: BackendKind,
+    project_path: &Path,
+) -> Result<BackendConfig> {
+    let timelines_dir = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(".timelines");
+    if let Ok(entries) = fs::read_dir(&timelines_dir) {
+        let storage = CheckpointStorage::new(claude_dir.to_path_buf());
+        for entry in entries.flatten() {
+            let timeline_file = entry.path().join("timeline.json");
+            if !timeline_file.exists() {
+                continue;
+            }
+            let timeline = storage.load_timeline(&timeline_file)?;
+            if timeline.root_node.is_some() {
+                anyhow::bail!(
+                    "Session {} already has checkpoints; the storage backend can only be changed before the first checkpoint",
+                    timeline.session_id
+                );
+            }
+        }
+    }
+
+    let config = match kind {
+        BackendKind::File => BackendConfig::default(),
+        BackendKind::Git => {
+            GitBackend::open(project_path)?;
+            BackendConfig {
+                backend: kind,
+                repo_path: Some(project_path.to_path_buf()),
+            }
+        }
+    };
+    save_config(claude_dir, project_id, &config)?;
+    Ok(config)
+}
+
+fn config_path(claude_dir: &Path, project_id: &str) -> PathBuf {
+    claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(".timelines")
+        .join(BACKEND_CONFIG_FILE)
+}
+
+/// The default backend, keeping content in the project's content store
+pub struct FileBackend {
+    compression_level: i32,
+}
+
+impl FileBackend {
+    pub fn new(compression_level: i32) -> Self {
+        Self { compression_level }
+    }
+}
+
+impl StorageBackend for FileBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::File
+    }
+
+    fn save_contents(
+        &self,
+        paths: &CheckpointPaths,
+        _checkpoint: &Checkpoint,
+        snapshots: &[&FileSnapshot],
+    ) -> Result<Vec<(PathBuf, anyhow::Error)>> {
+        let store = ContentStore::open(&paths.content_store_dir)?;
+        let mut failures = Vec::new();
+
+        for snapshot in snapshots.iter().filter(|s| !s.hash.is_empty()) {
+            if let Err(e) = store.put(&snapshot.hash, &snapshot.content, self.compression_level) {
+                failures.push((snapshot.file_path.clone(), e));
+            }
+        }
+
+        store.flush()?;
+        Ok(failures)
+    }
+
+    fn load_contents(
+        &self,
+        paths: &CheckpointPaths,
+        _checkpoint_id: &str,
+        snapshots: &mut [FileSnapshot],
+    ) -> Result<()> {
+        let store = ContentStore::open(&paths.content_store_dir)?;
+
+        for snapshot in snapshots.iter_mut().filter(|s| !s.hash.is_empty()) {
+            match store.get(&snapshot.hash)? {
+                Some(content) => snapshot.content = content,
+                None => log::warn!("Content file missing for hash: {}", snapshot.hash),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_content(
+        &self,
+        paths: &CheckpointPaths,
+        _checkpoint_id: &str,
+        _file_path: &Path,
+        hash: &str,
+    ) -> Option<TimelineIssueKind> {
+        let store = ContentStore::open(&paths.content_store_dir).ok()?;
+        let Ok(Some(compressed)) = store.get_compressed(hash) else {
+            return Some(TimelineIssueKind::MissingContent);
+        };
+        match decode_all(&compressed[..]) {
+            Ok(content) if CheckpointStorage::calculate_file_hash(&content) == hash => None,
+            _ => Some(TimelineIssueKind::CorruptContent),
+        }
+    }
+
+    fn retain_contents(
+        &self,
+        paths: &CheckpointPaths,
+        _checkpoint_id: &str,
+        hashes: &[String],
+    ) -> Result<()> {
+        ContentStore::open(&paths.content_store_dir)?.retain(hashes.iter().map(String::as_str))
+    }
+
+    fn release_checkpoint(
+        &self,
+        paths: &CheckpointPaths,
+        _checkpoint_id: &str,
+        hashes: &[String],
+    ) -> Result<()> {
+        ContentStore::open(&paths.content_store_dir)?.release(hashes.iter().map(String::as_str))?;
+        Ok(())
+    }
+
+    fn collect_garbage(&self, content_store_dir: &Path) -> Result<usize> {
+        ContentStore::open(content_store_dir)?.collect_garbage()
+    }
+}
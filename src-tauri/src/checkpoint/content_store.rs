@@ -0,0 +1,413 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use zstd::stream::{decode_all, encode_all};
+
+/// Directory under a project's `.timelines` holding the shared content store
+pub const CONTENT_STORE_DIR: &str = ".content_pool";
+
+/// Reference counts of the store's blobs, relative to the store root
+const REFCOUNT_FILE: &str = "refcounts.json";
+
+/// Stores opened by this process, keyed by store root. Every storage instance
+/// and manager of a project goes through the same store, so reference counts
+/// are never updated concurrently from stale copies.
+static STORES: OnceLock<Mutex<HashMap<PathBuf, Arc<ContentStore>>>> = OnceLock::new();
+
+/// Content-addressed blob store shared by all sessions of a project.
+///
+/// Blobs are zstd-compressed and stored under their SHA-256 hash. Each blob
+/// has a reference count of the file references pointing at it across the
+/// project's sessions, and is deleted once that count drops to zero.
+pub struct ContentStore {
+    root: PathBuf,
+    refcounts: Mutex<RefCounts>,
+}
+
+#[derive(Default)]
+struct RefCounts {
+    counts: HashMap<String, u64>,
+    dirty: bool,
+}
+
+/// Result of moving per-session content pools into the project store
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    /// Sessions whose content pool was moved
+    pub sessions_migrated: usize,
+    /// Blobs moved into the project store
+    pub blobs_moved: usize,
+    /// Blobs dropped because the project store already had them
+    pub duplicates_removed: usize,
+    /// Disk space reclaimed by dropping duplicates, in bytes
+    pub bytes_freed: u64,
+}
+
+impl ContentStore {
+    /// Get the store rooted at `root`, opening it on first use.
+    ///
+    /// Opening a store migrates any per-session content pools left by earlier
+    /// versions and rebuilds the reference counts if they are missing.
+    pub fn open(root: &Path) -> Result<Arc<Self>> {
+        Self::open_with_report(root).map(|(store, _)| store)
+    }
+
+    /// Like `open`, also returning what was migrated. The report is empty if
+    /// the store was already open.
+    pub fn open_with_report(root: &Path) -> Result<(Arc<Self>, MigrationReport)> {
+        let mut stores = STORES
+            .get_or_init(Default::default)
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Content store registry is poisoned"))?;
+        if let Some(store) = stores.get(root) {
+            return Ok((store.clone(), MigrationReport::default()));
+        }
+
+        let refcount_file = root.join(REFCOUNT_FILE);
+        let counts = if refcount_file.exists() {
+            let json =
+                fs::read_to_string(&refcount_file).context("Failed to read reference counts")?;
+            serde_json::from_str(&json).context("Failed to parse reference counts")?
+        } else {
+            HashMap::new()
+        };
+
+        let store = Arc::new(Self {
+            root: root.to_path_buf(),
+            refcounts: Mutex::new(RefCounts {
+                counts,
+                dirty: false,
+            }),
+        });
+
+        let mut report = MigrationReport::default();
+        if !refcount_file.exists() || !store.legacy_pools().is_empty() {
+            report = store.migrate_session_pools()?;
+            if report.sessions_migrated > 0 {
+                log::info!(
+                    "Migrated {} session content pools into {:?}, freed {} bytes",
+                    report.sessions_migrated,
+                    root,
+                    report.bytes_freed
+                );
+            }
+        }
+
+        stores.insert(root.to_path_buf(), store.clone());
+        Ok((store, report))
+    }
+
+    /// Path of a blob within the store
+    pub fn blob_path(&self, hash: &str) -> PathBuf {
+        blob_path(&self.root, hash)
+    }
+
+    /// Store content under its hash and take a reference to it.
+    ///
+    /// The content is only written if the store does not already have it.
+    /// Reference counts are persisted on the next `flush`.
+    pub fn put(&self, hash: &str, content: &[u8], compression_level: i32) -> Result<()> {
+        let mut refcounts = self.lock()?;
+
+        let blob_file = self.blob_path(hash);
+        if !blob_file.exists() {
+            let compressed = encode_all(content, compression_level)
+                .context("Failed to compress file content")?;
+            write_blob(&blob_file, &compressed)?;
+        }
+
+        *refcounts.counts.entry(hash.to_string()).or_insert(0) += 1;
+        refcounts.dirty = true;
+        Ok(())
+    }
+
+    /// Store an already compressed blob without taking a reference
+    pub fn insert_compressed(&self, hash: &str, compressed: &[u8]) -> Result<()> {
+        let _refcounts = self.lock()?;
+        let blob_file = self.blob_path(hash);
+        if !blob_file.exists() {
+            write_blob(&blob_file, compressed)?;
+        }
+        Ok(())
+    }
+
+    /// Read and decompress a blob, or `None` if the store does not have it
+    pub fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        match self.get_compressed(hash)? {
+            Some(compressed) => Ok(Some(
+                decode_all(&compressed[..]).context("Failed to decompress file content")?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Read a blob as stored, or `None` if the store does not have it
+    pub fn get_compressed(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        let blob_file = self.blob_path(hash);
+        if !blob_file.exists() {
+            return Ok(None);
+        }
+        fs::read(&blob_file)
+            .map(Some)
+            .context("Failed to read file content from pool")
+    }
+
+    /// Take another reference to blobs the store already has
+    pub fn retain<'a>(&self, hashes: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let mut refcounts = self.lock()?;
+        for hash in hashes {
+            *refcounts.counts.entry(hash.to_string()).or_insert(0) += 1;
+            refcounts.dirty = true;
+        }
+        self.save(&mut refcounts)
+    }
+
+    /// Drop one reference to each hash, deleting blobs that are no longer referenced.
+    /// Returns the number of blobs deleted.
+    pub fn release<'a>(&self, hashes: impl IntoIterator<Item = &'a str>) -> Result<usize> {
+        let mut refcounts = self.lock()?;
+        let mut removed = 0;
+
+        for hash in hashes {
+            let Some(count) = refcounts.counts.get_mut(hash) else {
+                continue;
+            };
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                refcounts.counts.remove(hash);
+                if fs::remove_file(self.blob_path(hash)).is_ok() {
+                    removed += 1;
+                }
+            }
+            refcounts.dirty = true;
+        }
+
+        self.save(&mut refcounts)?;
+        Ok(removed)
+    }
+
+    /// Persist pending reference count changes
+    pub fn flush(&self) -> Result<()> {
+        let mut refcounts = self.lock()?;
+        self.save(&mut refcounts)
+    }
+
+    /// Recount references from every session's file references and delete
+    /// blobs nothing refers to. Returns the number of blobs deleted.
+    pub fn collect_garbage(&self) -> Result<usize> {
+        let mut refcounts = self.lock()?;
+        refcounts.counts = self.count_references()?;
+        refcounts.dirty = true;
+        self.save(&mut refcounts)?;
+
+        let objects_dir = self.root.join("objects");
+        if !objects_dir.exists() {
+            return Ok(0);
+        }
+
+        let mut removed_count = 0;
+        for shard in fs::read_dir(&objects_dir)? {
+            let shard_dir = shard?.path();
+            if !shard_dir.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&shard_dir)? {
+                let blob_file = entry?.path();
+                let Some(hash) = blob_file.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if !refcounts.counts.contains_key(hash) && fs::remove_file(&blob_file).is_ok() {
+                    removed_count += 1;
+                }
+            }
+            // Only succeeds once the shard is empty
+            let _ = fs::remove_dir(&shard_dir);
+        }
+
+        Ok(removed_count)
+    }
+
+    /// Move content pools stored under individual sessions into this store,
+    /// then rebuild the reference counts.
+    pub fn migrate_session_pools(&self) -> Result<MigrationReport> {
+        let mut report = MigrationReport::default();
+
+        for legacy_pool in self.legacy_pools() {
+            {
+                let _refcounts = self.lock()?;
+                for entry in fs::read_dir(&legacy_pool)? {
+                    let legacy_file = entry?.path();
+                    let Some(hash) = legacy_file.file_name().and_then(|n| n.to_str()) else {
+                        continue;
+                    };
+                    if !legacy_file.is_file() {
+                        continue;
+                    }
+
+                    let blob_file = self.blob_path(hash);
+                    if blob_file.exists() {
+                        report.bytes_freed += fs::metadata(&legacy_file)?.len();
+                        fs::remove_file(&legacy_file)
+                            .context("Failed to remove duplicate content")?;
+                        report.duplicates_removed += 1;
+                    } else {
+                        if let Some(parent) = blob_file.parent() {
+                            fs::create_dir_all(parent)
+                                .context("Failed to create content store directory")?;
+                        }
+                        fs::rename(&legacy_file, &blob_file)
+                            .context("Failed to move content into the project store")?;
+                        report.blobs_moved += 1;
+                    }
+                }
+            }
+
+            fs::remove_dir_all(&legacy_pool).context("Failed to remove session content pool")?;
+            report.sessions_migrated += 1;
+        }
+
+        self.collect_garbage()?;
+        Ok(report)
+    }
+
+    /// Per-session content pools written by earlier versions
+    fn legacy_pools(&self) -> Vec<PathBuf> {
+        self.session_dirs()
+            .into_iter()
+            .map(|dir| dir.join("files").join("content_pool"))
+            .filter(|pool| pool.is_dir())
+            .collect()
+    }
+
+    /// Session directories next to the store, skipping hidden entries such as the store itself
+    fn session_dirs(&self) -> Vec<PathBuf> {
+        let Some(timelines_dir) = self.root.parent() else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(timelines_dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect()
+    }
+
+    /// Count references to each hash across all sessions' file references
+    fn count_references(&self) -> Result<HashMap<String, u64>> {
+        let mut counts = HashMap::new();
+
+        for session_dir in self.session_dirs() {
+            let refs_dir = session_dir.join("files").join("refs");
+            if !refs_dir.exists() {
+                continue;
+            }
+            for checkpoint_entry in fs::read_dir(&refs_dir)? {
+                let checkpoint_dir = checkpoint_entry?.path();
+                if !checkpoint_dir.is_dir() {
+                    continue;
+                }
+                for hash in referenced_hashes(&checkpoint_dir) {
+                    *counts.entry(hash).or_insert(0) += 1;
+                }
+            }
+        }
+
+        Ok(counts)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, RefCounts>> {
+        self.refcounts
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Content store lock is poisoned"))
+    }
+
+    fn save(&self, refcounts: &mut RefCounts) -> Result<()> {
+        if !refcounts.dirty {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.root).context("Failed to create content store directory")?;
+        let json = serde_json::to_string(&refcounts.counts)
+            .context("Failed to serialize reference counts")?;
+        fs::write(self.root.join(REFCOUNT_FILE), json)
+            .context("Failed to write reference counts")?;
+        refcounts.dirty = false;
+        Ok(())
+    }
+}
+
+/// Path of a blob under a store root, sharded by the first two hash characters
+pub fn blob_path(root: &Path, hash: &str) -> PathBuf {
+    let shard = hash.get(..2).unwrap_or(hash);
+    root.join("objects").join(shard).join(hash)
+}
+
+/// Content hashes referenced by a checkpoint's file reference directory
+pub fn referenced_hashes(refs_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(refs_dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .filter_map(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .filter_map(|r| r["hash"].as_str().map(String::from))
+        .filter(|hash| !hash.is_empty())
+        .collect()
+}
+
+fn write_blob(blob_file: &Path, compressed: &[u8]) -> Result<()> {
+    if let Some(parent) = blob_file.parent() {
+        fs::create_dir_all(parent).context("Failed to create content store directory")?;
+    }
+    fs::write(blob_file, compressed).context("Failed to write file content to pool")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::storage::CheckpointStorage;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_migration_deduplicates_session_pools() {
+        let temp_dir = TempDir::new().unwrap();
+        let timelines_dir = temp_dir.path().join(".timelines");
+        let content = b"shared content".to_vec();
+        let hash = CheckpointStorage::calculate_file_hash(&content);
+        let compressed = encode_all(&content[..], 3).unwrap();
+
+        for session in ["one", "two"] {
+            let files_dir = timelines_dir.join(session).join("files");
+            fs::create_dir_all(files_dir.join("content_pool")).unwrap();
+            fs::create_dir_all(files_dir.join("refs").join("checkpoint")).unwrap();
+            fs::write(files_dir.join("content_pool").join(&hash), &compressed).unwrap();
+            fs::write(
+                files_dir.join("refs").join("checkpoint").join("file.json"),
+                serde_json::json!({ "path": "file", "hash": hash }).to_string(),
+            )
+            .unwrap();
+        }
+
+        let store = ContentStore::open(&timelines_dir.join(CONTENT_STORE_DIR)).unwrap();
+        assert_eq!(store.get(&hash).unwrap(), Some(content));
+        assert!(!timelines_dir.join("one/files/content_pool").exists());
+        assert!(!timelines_dir.join("two/files/content_pool").exists());
+
+        // Both sessions reference the blob, so it survives one release
+        assert_eq!(store.release([hash.as_str()]).unwrap(), 0);
+        assert!(store.blob_path(&hash).exists());
+        assert_eq!(store.release([hash.as_str()]).unwrap(), 1);
+        assert!(!store.blob_path(&hash).exists());
+    }
+}
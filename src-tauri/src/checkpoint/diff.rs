@@ -0,0 +1,795 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{Checkpoint, CheckpointDiff, FileDiff, FileRename, FileSnapshot};
+
+/// Number of unchanged lines shown around each change in a hunk
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// Upper bound on the edit distance explored by the Myers search.
+/// Beyond this the differing lines are treated as rewritten, which bounds the
+/// time spent on huge, unrelated inputs; memory is linear in their size
+/// either way.
+const MAX_EDIT_DISTANCE: usize = 8192;
+
+/// Number of leading bytes inspected when sniffing for binary content
+const BINARY_SNIFF_LEN: usize = 8000;
+
+/// A single line-level edit operation, holding indices into the old/new line lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Result of diffing two text files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineDiff {
+    /// Unified diff text (empty when the inputs are identical)
+    pub unified: String,
+    /// Number of added lines
+    pub additions: usize,
+    /// Number of removed lines
+    pub deletions: usize,
+}
+
+/// Returns true if the content looks like binary data.
+///
+/// Uses the same heuristic as git: a NUL byte in the first few kilobytes.
+pub fn is_binary(content: &[u8]) -> bool {
+    let len = content.len().min(BINARY_SNIFF_LEN);
+    content[..len].contains(&0)
+}
+
+/// Produce a unified diff between two versions of a text file
+pub fn unified_diff(path: &Path, old: &str, new: &str, context: usize) -> LineDiff {
+    // Keep line endings so a change to the final newline alone shows up
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+
+    let additions = edits
+        .iter()
+        .filter(|e| matches!(e, Edit::Insert(_)))
+        .count();
+    let deletions = edits
+        .iter()
+        .filter(|e| matches!(e, Edit::Delete(_)))
+        .count();
+
+    if additions == 0 && deletions == 0 {
+        return LineDiff {
+            unified: String::new(),
+            additions,
+            deletions,
+        };
+    }
+
+    let display = path.to_string_lossy();
+    let mut unified = format!("--- a/{}\n+++ b/{}\n", display, display);
+    for (start, end) in hunk_ranges(&edits, context) {
+        // Count the lines of each file consumed before the hunk begins
+        let old_start = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count();
+        let new_start = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count();
+        write_hunk(
+            &mut unified,
+            &edits[start..end],
+            old_start,
+            new_start,
+            &old_lines,
+            &new_lines,
+        );
+    }
+
+    LineDiff {
+        unified,
+        additions,
+        deletions,
+    }
+}
+
+/// Compute the diff between two checkpoints from the state of every file at
+/// each, as returned by `CheckpointStorage::load_file_states`.
+///
+/// Files whose content hash moved from one path to another are reported as
+/// renames instead of a delete/add pair.
+pub fn diff_checkpoints(
+    from: &Checkpoint,
+    from_files: &HashMap<PathBuf, FileSnapshot>,
+    to: &Checkpoint,
+    to_files: &HashMap<PathBuf, FileSnapshot>,
+) -> CheckpointDiff {
+    let from_map: HashMap<&PathBuf, &FileSnapshot> =
+        from_files.iter().filter(|(_, s)| !s.is_deleted).collect();
+    let to_map: HashMap<&PathBuf, &FileSnapshot> =
+        to_files.iter().filter(|(_, s)| !s.is_deleted).collect();
+
+    let mut modified_files = Vec::new();
+    let mut deleted: Vec<&FileSnapshot> = Vec::new();
+    let mut added: Vec<&FileSnapshot> = Vec::new();
+
+    for (path, from_file) in &from_map {
+        match to_map.get(path) {
+            Some(to_file) if to_file.hash != from_file.hash => {
+                modified_files.push(diff_file(path, from_file, to_file));
+            }
+            Some(_) => {}
+            None => deleted.push(from_file),
+        }
+    }
+    for (path, to_file) in &to_map {
+        if !from_map.contains_key(path) {
+            added.push(to_file);
+        }
+    }
+
+    // Pair up deleted and added files that share a content hash
+    let mut renamed_files = Vec::new();
+    let mut added_by_hash: HashMap<&str, Vec<&FileSnapshot>> = HashMap::new();
+    for snapshot in &added {
+        added_by_hash
+            .entry(snapshot.hash.as_str())
+            .or_default()
+            .push(snapshot);
+    }
+    let mut deleted_files = Vec::new();
+    deleted.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    for snapshot in deleted {
+        let target = added_by_hash
+            .get_mut(snapshot.hash.as_str())
+            .and_then(|candidates| candidates.pop());
+        match target {
+            Some(target) => renamed_files.push(FileRename {
+                from_path: snapshot.file_path.clone(),
+                to_path: target.file_path.clone(),
+            }),
+            None => deleted_files.push(snapshot.file_path.clone()),
+        }
+    }
+    let mut added_files: Vec<PathBuf> = added_by_hash
+        .into_values()
+        .flatten()
+        .map(|s| s.file_path.clone())
+        .collect();
+
+    modified_files.sort_by(|a, b| a.path.cmp(&b.path));
+    added_files.sort();
+
+    CheckpointDiff {
+        from_checkpoint_id: from.id.clone(),
+        to_checkpoint_id: to.id.clone(),
+        modified_files,
+        added_files,
+        deleted_files,
+        renamed_files,
+        token_delta: (to.metadata.total_tokens as i64) - (from.metadata.total_tokens as i64),
+    }
+}
+
+/// Diff a single file present in both checkpoints
+fn diff_file(path: &Path, from: &FileSnapshot, to: &FileSnapshot) -> FileDiff {
+    diff_contents(path, &from.content, &to.content)
+}
+
+/// Diff two versions of a file, which may be binary
+pub fn diff_contents(path: &Path, old: &[u8], new: &[u8]) -> FileDiff {
+    let texts = match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+        (Ok(old_text), Ok(new_text)) if !is_binary(old) && !is_binary(new) => {
+            Some((old_text, new_text))
+        }
+        _ => None,
+    };
+
+    let Some((old, new)) = texts else {
+        let display = path.to_string_lossy();
+        return FileDiff {
+            path: path.to_path_buf(),
+            additions: 0,
+            deletions: 0,
+            is_binary: true,
+            diff_content: Some(format!(
+                "Binary files a/{} and b/{} differ\n",
+                display, display
+            )),
+        };
+    };
+
+    let diff = unified_diff(path, old, new, DEFAULT_CONTEXT_LINES);
+    FileDiff {
+        path: path.to_path_buf(),
+        additions: diff.additions,
+        deletions: diff.deletions,
+        is_binary: false,
+        diff_content: Some(diff.unified),
+    }
+}
+
+/// Compute a minimal line edit script using the Myers O(ND) algorithm
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // Strip the common prefix and suffix, which is cheap and usually most of the file
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Equal(i, i)).collect();
+    edits.extend(myers(a, b).into_iter().map(|e| match e {
+        Edit::Equal(x, y) => Edit::Equal(x + prefix, y + prefix),
+        Edit::Delete(x) => Edit::Delete(x + prefix),
+        Edit::Insert(y) => Edit::Insert(y + prefix),
+    }));
+    let old_tail = old.len() - suffix;
+    let new_tail = new.len() - suffix;
+    edits.extend((0..suffix).map(|i| Edit::Equal(old_tail + i, new_tail + i)));
+    edits
+}
+
+/// Myers' linear-space refinement: split at a point on an optimal path
+/// found by searching from both ends, then diff each half the same way
+fn myers(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let mut edits = Vec::with_capacity(a.len() + b.len());
+    diff_range(a, b, 0..a.len(), 0..b.len(), &mut edits);
+    edits
+}
+
+/// Append the edits turning `a[xs]` into `b[ys]`
+fn diff_range(
+    a: &[&str],
+    b: &[&str],
+    mut xs: std::ops::Range<usize>,
+    mut ys: std::ops::Range<usize>,
+    edits: &mut Vec<Edit>,
+) {
+    while !xs.is_empty() && !ys.is_empty() && a[xs.start] == b[ys.start] {
+        edits.push(Edit::Equal(xs.start, ys.start));
+        xs.start += 1;
+        ys.start += 1;
+    }
+    let mut suffix = 0;
+    while !xs.is_empty() && !ys.is_empty() && a[xs.end - 1] == b[ys.end - 1] {
+        xs.end -= 1;
+        ys.end -= 1;
+        suffix += 1;
+    }
+
+    if xs.is_empty() || ys.is_empty() {
+        edits.extend(xs.clone().map(Edit::Delete));
+        edits.extend(ys.clone().map(Edit::Insert));
+    } else {
+        match middle_point(&a[xs.clone()], &b[ys.clone()]) {
+            Some((x, y)) => {
+                let (x, y) = (xs.start + x, ys.start + y);
+                diff_range(a, b, xs.start..x, ys.start..y, edits);
+                diff_range(a, b, x..xs.end, y..ys.end, edits);
+            }
+            None => {
+                // Edit distance too large: treat as a full rewrite
+                edits.extend(xs.clone().map(Edit::Delete));
+                edits.extend(ys.clone().map(Edit::Insert));
+            }
+        }
+    }
+
+    edits.extend((0..suffix).map(|i| Edit::Equal(xs.end + i, ys.end + i)));
+}
+
+/// A point on a shortest edit path between two non-empty sequences that
+/// differ at both ends, with at most half of the path's edits before it, or
+/// None once the edit distance exceeds `MAX_EDIT_DISTANCE`
+fn middle_point(a: &[&str], b: &[&str]) -> Option<(usize, usize)> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+
+    // Furthest x reached on each diagonal k = x - y, searching forward from
+    // the start and backward from the end (in reversed coordinates)
+    let mut forward = vec![0isize; (2 * offset + 1) as usize];
+    let mut backward = vec![0isize; (2 * offset + 1) as usize];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max.min(MAX_EDIT_DISTANCE as isize / 2 + 1) {
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            // The paths meet when they overlap on the same diagonal
+            let reverse_k = delta - k;
+            if odd && reverse_k.abs() < d && x + backward[at(reverse_k)] >= n {
+                return Some((x as usize, y as usize));
+            }
+            k += 2;
+        }
+
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+            let forward_k = delta - k;
+            if !odd && forward_k.abs() <= d && x + forward[at(forward_k)] >= n {
+                return Some(((n - x) as usize, (m - y) as usize));
+            }
+            k += 2;
+        }
+    }
+    None
+}
+
+/// Group edits into hunk ranges (start..end indices into `edits`) with surrounding context
+fn hunk_ranges(edits: &[Edit], context: usize) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for (i, edit) in edits.iter().enumerate() {
+        if matches!(edit, Edit::Equal(..)) {
+            continue;
+        }
+        let start = i.saturating_sub(context);
+        let end = (i + 1 + context).min(edits.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+}
+
+fn write_hunk(
+    out: &mut String,
+    edits: &[Edit],
+    old_start: usize,
+    new_start: usize,
+    old: &[&str],
+    new: &[&str],
+) {
+    let old_len = edits
+        .iter()
+        .filter(|e| !matches!(e, Edit::Insert(_)))
+        .count();
+    let new_len = edits
+        .iter()
+        .filter(|e| !matches!(e, Edit::Delete(_)))
+        .count();
+
+    out.push_str(&format!(
+        "@@ -{} +{} @@\n",
+        format_range(old_start, old_len),
+        format_range(new_start, new_len)
+    ));
+
+    for edit in edits {
+        let (marker, line) = match edit {
+            Edit::Equal(x, _) => (' ', old[*x]),
+            Edit::Delete(x) => ('-', old[*x]),
+            Edit::Insert(y) => ('+', new[*y]),
+        };
+        out.push(marker);
+        match line.strip_suffix('\n') {
+            Some(line) => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            None => {
+                out.push_str(line);
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+}
+
+/// Result of a three-way merge of a text file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextMerge {
+    /// Merged content, with conflict markers around each conflicting region
+    pub content: String,
+    /// Number of conflicting regions
+    pub conflicts: usize,
+}
+
+/// A change to a range of base lines made on one side of a merge
+struct MergeHunk<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+}
+
+/// Three-way merge of two versions of a text file against their common base.
+///
+/// Changes that overlap or touch on both sides are a conflict unless both
+/// sides made the same change. Conflicting regions are written with git-style
+/// markers labelled `ours_label` and `theirs_label`.
+pub fn merge3(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    ours_label: &str,
+    theirs_label: &str,
+) -> TextMerge {
+    // Keep line endings so the merged file round-trips byte for byte
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let ours_hunks = merge_hunks(&base, &ours);
+    let theirs_hunks = merge_hunks(&base, &theirs);
+
+    let mut out: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let (mut i, mut j, mut pos) = (0, 0, 0);
+
+    while i < ours_hunks.len() || j < theirs_hunks.len() {
+        let start = match (ours_hunks.get(i), theirs_hunks.get(j)) {
+            (Some(a), Some(b)) => a.start.min(b.start),
+            (Some(a), None) => a.start,
+            (None, Some(b)) => b.start,
+            (None, None) => break,
+        };
+
+        // Grow the region until no hunk on either side overlaps or touches it
+        let (first_ours, first_theirs) = (i, j);
+        let mut end = start;
+        loop {
+            let mut grew = false;
+            while let Some(hunk) = ours_hunks.get(i).filter(|h| h.start <= end) {
+                end = end.max(hunk.end);
+                i += 1;
+                grew = true;
+            }
+            while let Some(hunk) = theirs_hunks.get(j).filter(|h| h.start <= end) {
+                end = end.max(hunk.end);
+                j += 1;
+                grew = true;
+            }
+            if !grew {
+                break;
+            }
+        }
+
+        out.extend(&base[pos..start]);
+        let ours_region = apply_hunks(&base, &ours_hunks[first_ours..i], start, end);
+        let theirs_region = apply_hunks(&base, &theirs_hunks[first_theirs..j], start, end);
+        if first_theirs == j || ours_region == theirs_region {
+            out.extend(ours_region);
+        } else if first_ours == i {
+            out.extend(theirs_region);
+        } else {
+            conflicts += 1;
+            out.push("<<<<<<< ");
+            out.push(ours_label);
+            out.push("\n");
+            push_region(&mut out, ours_region);
+            out.push("=======\n");
+            push_region(&mut out, theirs_region);
+            out.push(">>>>>>> ");
+            out.push(theirs_label);
+            out.push("\n");
+        }
+        pos = end;
+    }
+    out.extend(&base[pos..]);
+
+    TextMerge {
+        content: out.concat(),
+        conflicts,
+    }
+}
+
+/// Group the edits turning `base` into `side` into hunks of changed base lines
+fn merge_hunks<'a>(base: &[&'a str], side: &[&'a str]) -> Vec<MergeHunk<'a>> {
+    let mut hunks = Vec::new();
+    let mut current: Option<MergeHunk> = None;
+    let mut base_pos = 0;
+
+    for edit in diff_lines(base, side) {
+        match edit {
+            Edit::Equal(x, _) => {
+                hunks.extend(current.take());
+                base_pos = x + 1;
+            }
+            Edit::Delete(x) => {
+                current
+                    .get_or_insert(MergeHunk {
+                        start: x,
+                        end: x,
+                        lines: Vec::new(),
+                    })
+                    .end = x + 1;
+                base_pos = x + 1;
+            }
+            Edit::Insert(y) => current
+                .get_or_insert(MergeHunk {
+                    start: base_pos,
+                    end: base_pos,
+                    lines: Vec::new(),
+                })
+                .lines
+                .push(side[y]),
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// One side's version of base lines `start..end`, given its hunks in that range
+fn apply_hunks<'a>(
+    base: &[&'a str],
+    hunks: &[MergeHunk<'a>],
+    start: usize,
+    end: usize,
+) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let mut pos = start;
+    for hunk in hunks {
+        lines.extend(&base[pos..hunk.start]);
+        lines.extend(&hunk.lines);
+        pos = hunk.end;
+    }
+    lines.extend(&base[pos..end]);
+    lines
+}
+
+/// Append a conflict region, making sure the following marker starts on its own line
+fn push_region<'a>(out: &mut Vec<&'a str>, region: Vec<&'a str>) {
+    let needs_newline = region.last().is_some_and(|l| !l.ends_with('\n'));
+    out.extend(region);
+    if needs_newline {
+        out.push("\n");
+    }
+}
+
+/// Format a hunk range. `start` is the number of lines preceding the hunk,
+/// so an empty range is anchored on the line before it, as in GNU diff.
+fn format_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_counts_changed_lines_only() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nD\ne\nf\ng\nh\ni\n";
+        let diff = unified_diff(Path::new("src/lib.rs"), old, new, DEFAULT_CONTEXT_LINES);
+
+        assert_eq!(diff.additions, 2);
+        assert_eq!(diff.deletions, 1);
+        assert_eq!(
+            diff.unified,
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,8 +1,9 @@\n a\n b\n c\n-d\n+D\n e\n f\n g\n h\n+i\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_splits_distant_hunks() {
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new: String = (1..=20)
+            .filter(|i| *i != 19)
+            .map(|i| match i {
+                2 => "two\n".to_string(),
+                _ => format!("{}\n", i),
+            })
+            .collect();
+        let diff = unified_diff(Path::new("n.txt"), &old, &new, 1);
+
+        assert_eq!(diff.additions, 1);
+        assert_eq!(diff.deletions, 2);
+        assert!(diff.unified.contains("@@ -1,3 +1,3 @@\n 1\n-2\n+two\n 3\n"));
+        assert!(diff.unified.contains("@@ -18,3 +18,2 @@\n 18\n-19\n 20\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_from_empty_file() {
+        let diff = unified_diff(Path::new("new.txt"), "", "x\ny\n", DEFAULT_CONTEXT_LINES);
+        assert_eq!(
+            diff.unified,
+            "--- a/new.txt\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+x\n+y\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_marks_missing_final_newline() {
+        let diff = unified_diff(Path::new("a.txt"), "a\nb\n", "a\nb", DEFAULT_CONTEXT_LINES);
+        assert_eq!((diff.additions, diff.deletions), (1, 1));
+        assert_eq!(
+            diff.unified,
+            "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n a\n-b\n+b\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_lines_is_minimal_and_complete() {
+        // Interleaved changes, so the search has to split the input repeatedly
+        let old: Vec<String> = (0..3000).map(|i| format!("{}", i)).collect();
+        let new: Vec<String> = (0..3000)
+            .map(|i| match i % 7 {
+                0 => format!("changed {}", i),
+                3 => String::new(),
+                _ => format!("{}", i),
+            })
+            .filter(|line| !line.is_empty())
+            .collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        let edits = diff_lines(&old, &new);
+
+        // Every line of both files appears once, in order
+        let (mut x, mut y) = (0, 0);
+        for edit in &edits {
+            match *edit {
+                Edit::Equal(i, j) => {
+                    assert_eq!((i, j), (x, y));
+                    assert_eq!(old[i], new[j]);
+                    x += 1;
+                    y += 1;
+                }
+                Edit::Delete(i) => {
+                    assert_eq!(i, x);
+                    x += 1;
+                }
+                Edit::Insert(j) => {
+                    assert_eq!(j, y);
+                    y += 1;
+                }
+            }
+        }
+        assert_eq!((x, y), (old.len(), new.len()));
+
+        // 429 lines replaced and 429 removed
+        let changes = edits
+            .iter()
+            .filter(|e| !matches!(e, Edit::Equal(..)))
+            .count();
+        assert_eq!(changes, 429 * 3);
+    }
+
+    #[test]
+    fn test_merge3_combines_separate_changes() {
+        let base = "a\nb\nc\nd\ne\n";
+        let ours = "A\nb\nc\nd\ne\n";
+        let theirs = "a\nb\nc\nd\nE\nf";
+        let merged = merge3(base, ours, theirs, "ours", "theirs");
+
+        assert_eq!(merged.conflicts, 0);
+        assert_eq!(merged.content, "A\nb\nc\nd\nE\nf");
+    }
+
+    #[test]
+    fn test_merge3_marks_overlapping_changes() {
+        let base = "a\nb\nc\n";
+        let ours = "a\nB1\nc\n";
+        let theirs = "a\nB2\nc";
+        let merged = merge3(base, ours, theirs, "main", "fork");
+
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.content,
+            "a\n<<<<<<< main\nB1\nc\n=======\nB2\nc\n>>>>>>> fork\n"
+        );
+
+        // The same change on both sides is not a conflict
+        let merged = merge3(base, ours, ours, "main", "fork");
+        assert_eq!(merged.conflicts, 0);
+        assert_eq!(merged.content, ours);
+    }
+
+    #[test]
+    fn test_diff_checkpoints_uses_inherited_files() {
+        use crate::checkpoint::storage::CheckpointStorage;
+        use crate::checkpoint::test_support::make_checkpoint;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        // Each checkpoint only holds the files changed since its parent
+        let mut saved: Vec<Checkpoint> = Vec::new();
+        for changes in [
+            vec![
+                ("a.txt", Some("a\n")),
+                ("b.txt", Some("b\n")),
+                ("c.txt", Some("c\n")),
+            ],
+            vec![("a.txt", Some("a\nmore\n"))],
+            vec![
+                ("c.txt", None),
+                ("d.txt", Some("c\n")),
+                ("e.txt", Some("e\n")),
+            ],
+        ] {
+            let checkpoint = make_checkpoint(saved.last());
+            let snapshots = changes
+                .into_iter()
+                .map(|(path, content)| {
+                    let content = content.unwrap_or_default().as_bytes().to_vec();
+                    FileSnapshot {
+                        checkpoint_id: checkpoint.id.clone(),
+                        file_path: PathBuf::from(path),
+                        hash: CheckpointStorage::calculate_file_hash(&content),
+                        size: content.len() as u64,
+                        is_deleted: content.is_empty(),
+                        content,
+                        permissions: None,
+                    }
+                })
+                .collect();
+            storage
+                .save_checkpoint("project", "session", &checkpoint, snapshots, "")
+                .unwrap();
+            saved.push(checkpoint);
+        }
+
+        let diff_between = |from: &Checkpoint, to: &Checkpoint| {
+            let (from, from_files) = storage
+                .load_file_states("project", "session", &from.id)
+                .unwrap();
+            let (to, to_files) = storage
+                .load_file_states("project", "session", &to.id)
+                .unwrap();
+            diff_checkpoints(&from, &from_files, &to, &to_files)
+        };
+
+        let diff = diff_between(&saved[1], &saved[2]);
+        assert!(diff.modified_files.is_empty());
+        assert_eq!(diff.added_files, vec![PathBuf::from("e.txt")]);
+        assert!(diff.deleted_files.is_empty());
+        assert_eq!(diff.renamed_files.len(), 1);
+        assert_eq!(diff.renamed_files[0].from_path, PathBuf::from("c.txt"));
+        assert_eq!(diff.renamed_files[0].to_path, PathBuf::from("d.txt"));
+
+        let diff = diff_between(&saved[0], &saved[2]);
+        assert_eq!(diff.modified_files.len(), 1);
+        assert_eq!(diff.modified_files[0].path, PathBuf::from("a.txt"));
+        assert_eq!(diff.modified_files[0].additions, 1);
+        assert_eq!(diff.modified_files[0].deletions, 0);
+        assert_eq!(diff.added_files, vec![PathBuf::from("e.txt")]);
+        assert!(diff.deleted_files.is_empty());
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(!is_binary("plain text ✓".as_bytes()));
+    }
+}
@@ -0,0 +1,462 @@
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+use super::backend::{BackendKind, StorageBackend};
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, CheckpointPaths, FileSnapshot, TimelineIssueKind};
+
+/// Namespace of the hidden refs holding checkpoint commits
+const REF_PREFIX: &str = "refs/claudia";
+
+/// Stores checkpoint content as git objects in the project's repository.
+///
+/// Each checkpoint becomes a commit on `refs/claudia/<session>/<checkpoint>`
+/// whose parents are the commits of its parent checkpoints, so the tree of every
+/// commit holds the complete state of the tracked files at that checkpoint.
+/// Refs outside `refs/heads` are never checked out or pushed by default, and
+/// the user's index and working tree are never touched.
+pub struct GitBackend {
+    repo_path: PathBuf,
+}
+
+impl GitBackend {
+    /// Open the repository at `repo_path`, failing if it is not a git repository
+    pub fn open(repo_path: &Path) -> Result<Self> {
+        let backend = Self {
+            repo_path: repo_path.to_path_buf(),
+        };
+        backend
+            .git(&["rev-parse", "--git-dir"], None)
+            .with_context(|| format!("{} is not a git repository", repo_path.display()))?;
+        Ok(backend)
+    }
+
+    /// Run a git command in the repository, returning its stdout
+    fn git(&self, args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>> {
+        self.git_with_env(args, &[], input)
+    }
+
+    fn git_with_env(
+        &self,
+        args: &[&str],
+        env: &[(&str, &OsStr)],
+        input: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let mut command = Command::new("git");
+        command
+            .args(args)
+            .envs(env.iter().copied())
+            .current_dir(&self.repo_path)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = command.spawn().context("Failed to run git")?;
+
+        // Write stdin from another thread so a full stdout pipe cannot deadlock us
+        let writer = match (input, child.stdin.take()) {
+            (Some(input), Some(mut stdin)) => {
+                let input = input.to_vec();
+                Some(std::thread::spawn(move || stdin.write_all(&input)))
+            }
+            _ => None,
+        };
+
+        let output = child.wait_with_output().context("Failed to wait for git")?;
+        if let Some(writer) = writer {
+            writer
+                .join()
+                .map_err(|_| anyhow::anyhow!("git stdin writer panicked"))?
+                .context("Failed to write to git")?;
+        }
+
+        if !output.status.success() {
+            let subcommand = args
+                .iter()
+                .find(|a| !a.starts_with('-') && !a.contains('='))
+                .copied()
+                .unwrap_or_default();
+            anyhow::bail!(
+                "git {} failed: {}",
+                subcommand,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(output.stdout)
+    }
+
+    /// Resolve a checkpoint's ref to its commit, if it exists
+    fn resolve_commit(&self, ref_name: &str) -> Option<String> {
+        self.git(
+            &[
+                "rev-parse",
+                "--verify",
+                "-q",
+                &format!("{}^{{commit}}", ref_name),
+            ],
+            None,
+        )
+        .ok()
+        .map(|out| String::from_utf8_lossy(&out).trim().to_string())
+    }
+
+    fn ref_name(session_id: &str, checkpoint_id: &str) -> String {
+        format!("{}/{}/{}", REF_PREFIX, session_id, checkpoint_id)
+    }
+
+    /// Session a set of checkpoint paths belongs to
+    fn session_id(paths: &CheckpointPaths) -> String {
+        paths
+            .timeline_file
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Path of a snapshot inside a commit tree
+    fn tree_path(file_path: &Path) -> String {
+        file_path.to_string_lossy().replace('\\', "/")
+    }
+}
+
+impl StorageBackend for GitBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Git
+    }
+
+    fn save_contents(
+        &self,
+        _paths: &CheckpointPaths,
+        checkpoint: &Checkpoint,
+        snapshots: &[&FileSnapshot],
+    ) -> Result<Vec<(PathBuf, anyhow::Error)>> {
+        // Build the tree in a private index so the user's index is left alone
+        let index_dir = TempDir::new().context("Failed to create temporary index directory")?;
+        let index_file = index_dir.path().join("index");
+        let index = [("GIT_INDEX_FILE", index_file.as_os_str())];
+
+        let parent = checkpoint
+            .parent_checkpoint_id
+            .as_deref()
+            .and_then(|id| self.resolve_commit(&Self::ref_name(&checkpoint.session_id, id)));
+        match &parent {
+            Some(commit) => self.git_with_env(&["read-tree", commit], &index, None)?,
+            None => self.git_with_env(&["read-tree", "--empty"], &index, None)?,
+        };
+
+        let mut failures = Vec::new();
+        let mut index_info = Vec::new();
+        let mut removals = Vec::new();
+        for snapshot in snapshots {
+            let path = Self::tree_path(&snapshot.file_path);
+            if snapshot.is_deleted || snapshot.hash.is_empty() {
+                removals.extend_from_slice(path.as_bytes());
+                removals.push(0);
+                continue;
+            }
+
+            match self.git(&["hash-object", "-w", "--stdin"], Some(&snapshot.content)) {
+                Ok(oid) => {
+                    let mode = if snapshot.permissions.is_some_and(|p| p & 0o111 != 0) {
+                        "100755"
+                    } else {
+                        "100644"
+                    };
+                    index_info.extend_from_slice(
+                        format!(
+                            "{} blob {}\t{}\0",
+                            mode,
+                            String::from_utf8_lossy(&oid).trim(),
+                            path
+                        )
+                        .as_bytes(),
+                    );
+                }
+                Err(e) => failures.push((snapshot.file_path.clone(), e)),
+            }
+        }
+
+        if !index_info.is_empty() {
+            self.git_with_env(
+                &["update-index", "-z", "--index-info"],
+                &index,
+                Some(&index_info),
+            )?;
+        }
+        if !removals.is_empty() {
+            self.git_with_env(
+                &["update-index", "-z", "--force-remove", "--stdin"],
+                &index,
+                Some(&removals),
+            )?;
+        }
+        let tree = self.git_with_env(&["write-tree"], &index, None)?;
+        let tree = String::from_utf8_lossy(&tree).trim().to_string();
+
+        // Date the commit at the checkpoint so `git log` matches the timeline
+        let message = checkpoint
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("Checkpoint {}", checkpoint.id));
+        let date = format!("{} +0000", checkpoint.timestamp.timestamp());
+        let mut args = vec![
+            "-c",
+            "user.name=Claudia",
+            "-c",
+            "user.email=claudia@localhost",
+            "commit-tree",
+            &tree,
+            "-m",
+            &message,
+        ];
+        // Merge checkpoints also record the commits they were merged from
+        let merge_parents: Vec<String> = checkpoint
+            .merge_parent_ids
+            .iter()
+            .filter_map(|id| self.resolve_commit(&Self::ref_name(&checkpoint.session_id, id)))
+            .collect();
+        for parent in parent.iter().chain(&merge_parents) {
+            args.extend(["-p", parent.as_str()]);
+        }
+        let commit = self.git_with_env(
+            &args,
+            &[
+                ("GIT_AUTHOR_DATE", OsStr::new(&date)),
+                ("GIT_COMMITTER_DATE", OsStr::new(&date)),
+            ],
+            None,
+        )?;
+        let commit = String::from_utf8_lossy(&commit).trim().to_string();
+
+        self.git(
+            &[
+                "update-ref",
+                &Self::ref_name(&checkpoint.session_id, &checkpoint.id),
+                &commit,
+            ],
+            None,
+        )?;
+
+        Ok(failures)
+    }
+
+    fn load_contents(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        snapshots: &mut [FileSnapshot],
+    ) -> Result<()> {
+        let ref_name = Self::ref_name(&Self::session_id(paths), checkpoint_id);
+        let Some(commit) = self.resolve_commit(&ref_name) else {
+            log::warn!("Checkpoint commit missing: {}", ref_name);
+            return Ok(());
+        };
+
+        let wanted: Vec<usize> = (0..snapshots.len())
+            .filter(|&i| !snapshots[i].hash.is_empty())
+            .collect();
+        if wanted.is_empty() {
+            return Ok(());
+        }
+
+        let mut input = Vec::new();
+        for &i in &wanted {
+            input.extend_from_slice(
+                format!("{}:{}\n", commit, Self::tree_path(&snapshots[i].file_path)).as_bytes(),
+            );
+        }
+        let output = self.git(&["cat-file", "--batch"], Some(&input))?;
+
+        // Each object is "<oid> <type> <size>\n<content>\n", or "<name> missing\n"
+        let mut rest = &output[..];
+        for &i in &wanted {
+            let header_end = rest
+                .iter()
+                .position(|&b| b == b'\n')
+                .context("Truncated git cat-file output")?;
+            let header = String::from_utf8_lossy(&rest[..header_end]).to_string();
+            rest = &rest[header_end + 1..];
+
+            let size = match header
+                .rsplit(' ')
+                .next()
+                .and_then(|s| s.parse::<usize>().ok())
+            {
+                Some(size) if !header.ends_with(" missing") => size,
+                _ => {
+                    log::warn!("Content missing for {}", snapshots[i].file_path.display());
+                    continue;
+                }
+            };
+            if rest.len() < size + 1 {
+                anyhow::bail!("Truncated git cat-file output");
+            }
+            snapshots[i].content = rest[..size].to_vec();
+            rest = &rest[size + 1..];
+        }
+
+        Ok(())
+    }
+
+    fn check_content(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        file_path: &Path,
+        hash: &str,
+    ) -> Option<TimelineIssueKind> {
+        let object = format!(
+            "{}:{}",
+            Self::ref_name(&Self::session_id(paths), checkpoint_id),
+            Self::tree_path(file_path)
+        );
+        match self.git(&["cat-file", "blob", &object], None) {
+            Ok(content) if CheckpointStorage::calculate_file_hash(&content) == hash => None,
+            Ok(_) => Some(TimelineIssueKind::CorruptContent),
+            Err(_) => Some(TimelineIssueKind::MissingContent),
+        }
+    }
+
+    fn check_checkpoint(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Option<String> {
+        let ref_name = Self::ref_name(&Self::session_id(paths), checkpoint_id);
+        match self.resolve_commit(&ref_name) {
+            Some(_) => None,
+            None => Some(format!("No checkpoint commit at {}", ref_name)),
+        }
+    }
+
+    /// A checkpoint's commit holds the full tree, inherited files included,
+    /// so there is nothing to take over.
+    fn retain_contents(
+        &self,
+        _paths: &CheckpointPaths,
+        _checkpoint_id: &str,
+        _hashes: &[String],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn release_checkpoint(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        _hashes: &[String],
+    ) -> Result<()> {
+        let ref_name = Self::ref_name(&Self::session_id(paths), checkpoint_id);
+        self.git(&["update-ref", "-d", &ref_name], None)?;
+        Ok(())
+    }
+
+    /// Objects of removed checkpoints stay reachable through their
+    /// descendants' commits or are left to `git gc`, so there is nothing to do.
+    fn collect_garbage(&self, _content_store_dir: &Path) -> Result<usize> {
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::backend::{self, BackendConfig};
+    use crate::checkpoint::test_support::{make_checkpoint, make_snapshot};
+
+    #[test]
+    fn test_checkpoints_become_linked_commits() {
+        let claude_dir = TempDir::new().unwrap();
+        let repo = TempDir::new().unwrap();
+        let status = Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(repo.path())
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let config = BackendConfig {
+            backend: BackendKind::Git,
+            repo_path: Some(repo.path().to_path_buf()),
+        };
+        backend::save_config(claude_dir.path(), "project", &config).unwrap();
+        let storage =
+            CheckpointStorage::for_project(claude_dir.path().to_path_buf(), "project", None)
+                .unwrap();
+        assert_eq!(storage.backend_kind(), BackendKind::Git);
+        storage.init_storage("project", "session").unwrap();
+
+        let first = make_checkpoint(None);
+        let second = make_checkpoint(Some(&first));
+        storage
+            .save_checkpoint(
+                "project",
+                "session",
+                &first,
+                vec![
+                    make_snapshot(&first, "src/main.rs", b"fn main() {}\n"),
+                    make_snapshot(&first, "logo.png", &[0x89, b'P', 0x00, 0xff]),
+                ],
+                "",
+            )
+            .unwrap();
+        storage
+            .save_checkpoint(
+                "project",
+                "session",
+                &second,
+                vec![make_snapshot(
+                    &second,
+                    "src/main.rs",
+                    b"fn main() { run() }\n",
+                )],
+                "",
+            )
+            .unwrap();
+
+        let git = GitBackend::open(repo.path()).unwrap();
+        let first_commit = git
+            .resolve_commit(&GitBackend::ref_name("session", &first.id))
+            .unwrap();
+        let second_ref = GitBackend::ref_name("session", &second.id);
+        let parent = git
+            .git(&["rev-parse", &format!("{}^", second_ref)], None)
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&parent).trim(), first_commit);
+
+        // The second commit's tree carries the unchanged file forward
+        let files = git
+            .git(&["ls-tree", "-r", "--name-only", &second_ref], None)
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&files), "logo.png\nsrc/main.rs\n");
+
+        let (_, snapshots, _) = storage
+            .load_checkpoint("project", "session", &first.id)
+            .unwrap();
+        let logo = snapshots
+            .iter()
+            .find(|s| s.file_path == Path::new("logo.png"))
+            .unwrap();
+        assert_eq!(logo.content, vec![0x89, b'P', 0x00, 0xff]);
+        let (_, snapshots, _) = storage
+            .load_checkpoint("project", "session", &second.id)
+            .unwrap();
+        assert_eq!(snapshots[0].content, b"fn main() { run() }\n");
+
+        assert!(storage
+            .verify_timeline("project", "session", false)
+            .unwrap()
+            .is_healthy());
+
+        // The user's branches and index are untouched
+        assert!(git
+            .git(&["rev-parse", "--verify", "-q", "HEAD"], None)
+            .is_err());
+        assert!(git.git(&["ls-files"], None).unwrap().is_empty());
+    }
+}
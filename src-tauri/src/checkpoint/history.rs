@@ -0,0 +1,240 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::diff;
+use super::storage::CheckpointStorage;
+use super::{CheckpointPaths, FileDiff, FileSnapshot, TimelineNode};
+
+/// How a checkpoint changed a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A checkpoint at which a file's content changed
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHistoryEntry {
+    pub session_id: String,
+    pub checkpoint_id: String,
+    pub timestamp: DateTime<Utc>,
+    /// Prompt that led to the checkpoint
+    pub user_prompt: String,
+    pub change: FileChangeKind,
+    /// Checkpoint holding the version this one replaced
+    pub previous_checkpoint_id: Option<String>,
+    /// Diff against the previous version
+    pub diff: FileDiff,
+}
+
+/// A change found while walking a timeline, before its diff is computed
+struct Change<'a> {
+    node: &'a TimelineNode,
+    previous: Option<FileSnapshot>,
+    current: FileSnapshot,
+}
+
+impl CheckpointStorage {
+    /// History of a file, relative to the project root, across one session or
+    /// every session of a project. Newest changes come first.
+    ///
+    /// A checkpoint's version of the file is the one it snapshotted, or else
+    /// the version of its parent; an entry is produced wherever that version
+    /// differs from the parent's.
+    pub fn file_history(
+        &self,
+        project_id: &str,
+        session_id: Option<&str>,
+        file_path: &Path,
+    ) -> Result<Vec<FileHistoryEntry>> {
+        let mut entries = Vec::new();
+
+        for (timeline_session, paths, timeline) in self.load_project_timelines(project_id)? {
+            if session_id.is_some_and(|id| id != timeline_session) {
+                continue;
+            }
+            let Some(root) = &timeline.root_node else {
+                continue;
+            };
+
+            let mut changes = Vec::new();
+            self.collect_file_changes(&paths, root, file_path, None, &mut changes)?;
+
+            for change in changes {
+                entries.push(self.history_entry(&paths, &timeline_session, file_path, change)?);
+            }
+        }
+
+        entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        Ok(entries)
+    }
+
+    /// Walk a timeline tree, recording each checkpoint whose version of the
+    /// file differs from its parent's
+    fn collect_file_changes<'a>(
+        &self,
+        paths: &CheckpointPaths,
+        node: &'a TimelineNode,
+        file_path: &Path,
+        inherited: Option<&FileSnapshot>,
+        changes: &mut Vec<Change<'a>>,
+    ) -> Result<()> {
+        let snapshot = self.load_file_snapshot(paths, &node.checkpoint.id, file_path, false)?;
+
+        let current = match snapshot {
+            Some(snapshot) => {
+                let changed = match inherited {
+                    None => !snapshot.is_deleted,
+                    Some(previous) => {
+                        previous.is_deleted != snapshot.is_deleted || previous.hash != snapshot.hash
+                    }
+                };
+                if changed {
+                    changes.push(Change {
+                        node,
+                        previous: inherited.filter(|p| !p.is_deleted).cloned(),
+                        current: snapshot.clone(),
+                    });
+                }
+                Some(snapshot)
+            }
+            None => inherited.cloned(),
+        };
+
+        for child in &node.children {
+            self.collect_file_changes(paths, child, file_path, current.as_ref(), changes)?;
+        }
+        Ok(())
+    }
+
+    fn history_entry(
+        &self,
+        paths: &CheckpointPaths,
+        session_id: &str,
+        file_path: &Path,
+        change: Change<'_>,
+    ) -> Result<FileHistoryEntry> {
+        let load = |snapshot: &FileSnapshot| -> Result<Vec<u8>> {
+            if snapshot.is_deleted {
+                return Ok(Vec::new());
+            }
+            Ok(self
+                .load_file_snapshot(paths, &snapshot.checkpoint_id, file_path, true)?
+                .map(|s| s.content)
+                .unwrap_or_default())
+        };
+
+        let old = change
+            .previous
+            .as_ref()
+            .map(load)
+            .transpose()?
+            .unwrap_or_default();
+        let new = load(&change.current)?;
+
+        let kind = if change.current.is_deleted {
+            FileChangeKind::Deleted
+        } else if change.previous.is_none() {
+            FileChangeKind::Added
+        } else {
+            FileChangeKind::Modified
+        };
+
+        let checkpoint = &change.node.checkpoint;
+        Ok(FileHistoryEntry {
+            session_id: session_id.to_string(),
+            checkpoint_id: checkpoint.id.clone(),
+            timestamp: checkpoint.timestamp,
+            user_prompt: checkpoint.metadata.user_prompt.clone(),
+            change: kind,
+            previous_checkpoint_id: change.previous.map(|p| p.checkpoint_id),
+            diff: diff::diff_contents(file_path, &old, &new),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::manager::CheckpointManager;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_file_history_lists_changes_with_diffs() {
+        let temp_dir = TempDir::new().unwrap();
+        let claude_dir = temp_dir.path().join("claude");
+        let project_path = temp_dir.path().join("project");
+        fs::create_dir_all(&project_path).unwrap();
+        fs::write(project_path.join("notes.txt"), "one\n").unwrap();
+        fs::write(project_path.join("other.txt"), "x\n").unwrap();
+
+        let manager = CheckpointManager::new(
+            "project".to_string(),
+            "session".to_string(),
+            project_path.clone(),
+            claude_dir.clone(),
+        )
+        .await
+        .unwrap();
+
+        let added = manager.create_checkpoint(None, None).await.unwrap();
+        fs::write(project_path.join("other.txt"), "y\n").unwrap();
+        manager.create_checkpoint(None, None).await.unwrap();
+        fs::write(project_path.join("notes.txt"), "one\ntwo\n").unwrap();
+        let modified = manager.create_checkpoint(None, None).await.unwrap();
+        fs::remove_file(project_path.join("notes.txt")).unwrap();
+        manager.track_file_modification("notes.txt").await.unwrap();
+        let deleted = manager.create_checkpoint(None, None).await.unwrap();
+
+        let storage = CheckpointStorage::new(claude_dir);
+        let history = storage
+            .file_history("project", Some("session"), Path::new("notes.txt"))
+            .unwrap();
+
+        let summary: Vec<(&str, FileChangeKind, usize, usize)> = history
+            .iter()
+            .map(|e| {
+                (
+                    e.checkpoint_id.as_str(),
+                    e.change,
+                    e.diff.additions,
+                    e.diff.deletions,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    deleted.checkpoint.id.as_str(),
+                    FileChangeKind::Deleted,
+                    0,
+                    2
+                ),
+                (
+                    modified.checkpoint.id.as_str(),
+                    FileChangeKind::Modified,
+                    1,
+                    0
+                ),
+                (added.checkpoint.id.as_str(), FileChangeKind::Added, 1, 0),
+            ]
+        );
+        assert_eq!(
+            history[1].previous_checkpoint_id.as_deref(),
+            Some(added.checkpoint.id.as_str())
+        );
+        assert!(history[1]
+            .diff
+            .diff_content
+            .as_deref()
+            .unwrap()
+            .contains("+two"));
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Lock file guarding a session's timeline, next to `timeline.json`
+pub const LOCK_FILE: &str = "timeline.lock";
+
+/// Journal of the checkpoint being created, next to `timeline.json`
+pub const JOURNAL_FILE: &str = "journal.json";
+
+/// Write a file so that readers see either the old or the new content, even
+/// if the process dies part way: the content goes to a temporary file in the
+/// same directory, is flushed to disk and then renamed over the target.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let dir = path.parent().context("Path has no parent directory")?;
+    let file_name = path
+        .file_name()
+        .context("Path has no file name")?
+        .to_string_lossy();
+    let temp_path = dir.join(format!(".{}.{}.tmp", file_name, uuid::Uuid::new_v4()));
+
+    let write = || -> Result<()> {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+    }
+
+    // Persist the rename itself. Directories cannot be opened for syncing on
+    // every platform, so this is best effort.
+    if let Ok(dir_handle) = File::open(dir) {
+        let _ = dir_handle.sync_all();
+    }
+    Ok(())
+}
+
+/// Exclusive lock on a session's timeline, held across processes until dropped.
+///
+/// The lock is not re-entrant: code holding it must not try to take it again.
+pub struct SessionLock {
+    _file: File,
+}
+
+impl SessionLock {
+    /// Block until the session owning `timeline_file` can be locked
+    pub fn acquire(timeline_file: &Path) -> Result<Self> {
+        let lock_path = sibling(timeline_file, LOCK_FILE)?;
+        if let Some(dir) = lock_path.parent() {
+            fs::create_dir_all(dir).context("Failed to create timeline directory")?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .context("Failed to open timeline lock")?;
+        file.lock().context("Failed to lock timeline")?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// A checkpoint whose creation started but has not been committed to the timeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub checkpoint_id: String,
+    pub started_at: DateTime<Utc>,
+}
+
+/// Record that a checkpoint is about to be written
+pub fn begin(timeline_file: &Path, checkpoint_id: &str) -> Result<()> {
+    let entry = JournalEntry {
+        checkpoint_id: checkpoint_id.to_string(),
+        started_at: Utc::now(),
+    };
+    let json = serde_json::to_string_pretty(&entry).context("Failed to serialize journal")?;
+    write_atomic(&sibling(timeline_file, JOURNAL_FILE)?, json)
+}
+
+/// Mark the journaled checkpoint as complete
+pub fn commit(timeline_file: &Path) -> Result<()> {
+    let journal_file = sibling(timeline_file, JOURNAL_FILE)?;
+    match fs::remove_file(&journal_file) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).context("Failed to clear journal"),
+    }
+}
+
+/// The checkpoint left incomplete by an interrupted write, if any
+pub fn pending(timeline_file: &Path) -> Result<Option<JournalEntry>> {
+    let journal_file = sibling(timeline_file, JOURNAL_FILE)?;
+    if !journal_file.exists() {
+        return Ok(None);
+    }
+    let json = fs::read_to_string(&journal_file).context("Failed to read journal")?;
+    match serde_json::from_str(&json) {
+        Ok(entry) => Ok(Some(entry)),
+        Err(e) => {
+            // The journal is written atomically, so this is not a torn write
+            log::warn!("Discarding unreadable journal {:?}: {}", journal_file, e);
+            commit(timeline_file)?;
+            Ok(None)
+        }
+    }
+}
+
+fn sibling(timeline_file: &Path, name: &str) -> Result<PathBuf> {
+    Ok(timeline_file
+        .parent()
+        .context("Invalid timeline path")?
+        .join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_session_lock_is_exclusive() {
+        let temp_dir = TempDir::new().unwrap();
+        let timeline_file = temp_dir.path().join("session").join("timeline.json");
+
+        let lock = SessionLock::acquire(&timeline_file).unwrap();
+        let other = File::open(temp_dir.path().join("session").join(LOCK_FILE)).unwrap();
+        assert!(other.try_lock().is_err());
+
+        drop(lock);
+        assert!(other.try_lock().is_ok());
+    }
+}
@@ -1,18 +1,52 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, TimeZone, Utc};
 use log;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use zstd::stream::{decode_all, encode_all};
 
 use super::{
+    diff, journal,
     storage::{self, CheckpointStorage},
-    Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
-    FileSnapshot, FileState, FileTracker, SessionTimeline,
+    walker, Checkpoint, CheckpointMetadata, CheckpointPaths, CheckpointResult, CheckpointStrategy,
+    FileSnapshot, FileState, FileTracker, MergeConflict, MergeConflictKind, MergeResult,
+    PartialRestoreResult, PlannedFileChange, PlannedPermissionChange, RestorePlan, RetentionPolicy,
+    SessionTimeline, StrategyThresholds, TimelineNode, TranscriptRestore, TranscriptRestoreMode,
 };
 
+/// Longest gap between messages that still counts as activity for the interval strategy
+const IDLE_GAP_MINUTES: i64 = 5;
+
+/// Session state held only in memory, saved when the manager is evicted and
+/// picked up by the next manager for the session
+#[derive(Serialize, Deserialize)]
+struct SavedSessionState {
+    messages: Vec<String>,
+    tracked_files: HashMap<PathBuf, FileState>,
+}
+
+/// Memory held by a manager for its session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagerMemoryUsage {
+    pub message_count: usize,
+    /// Total size of the tracked JSONL messages
+    pub message_bytes: usize,
+    pub tracked_files: usize,
+}
+
+/// Progress toward the interval and changed-lines thresholds since the last checkpoint
+#[derive(Debug, Default)]
+struct ActivityProgress {
+    last_message_at: Option<DateTime<Utc>>,
+    active_time: chrono::Duration,
+    changed_lines: usize,
+}
+
 /// Manages checkpoint operations for a session
 pub struct CheckpointManager {
     project_id: String,
@@ -22,17 +56,37 @@ pub struct CheckpointManager {
     pub storage: Arc<CheckpointStorage>,
     timeline: Arc<RwLock<SessionTimeline>>,
     current_messages: Arc<RwLock<Vec<String>>>, // JSONL messages
+    /// Project file index used as the baseline for detecting Bash side effects
+    bash_index: Arc<RwLock<Option<walker::FileIndex>>>,
+    /// Bash tool_use IDs still waiting for their tool_result
+    pending_bash_tools: Arc<RwLock<HashSet<String>>>,
+    /// Bash tool_use IDs whose execution changed files since the last checkpoint
+    bash_changes: Arc<RwLock<HashSet<String>>>,
+    activity: Arc<RwLock<ActivityProgress>>,
+    /// Compiled `bash_patterns` of the timeline's strategy thresholds
+    bash_patterns: Arc<RwLock<Vec<regex::Regex>>>,
 }
 
 impl CheckpointManager {
-    /// Create a new checkpoint manager
+    /// Create a new checkpoint manager using file storage
     pub async fn new(
         project_id: String,
         session_id: String,
         project_path: PathBuf,
         claude_dir: PathBuf,
     ) -> Result<Self> {
-        let storage = Arc::new(CheckpointStorage::new(claude_dir.clone()));
+        let storage = Arc::new(CheckpointStorage::new(claude_dir));
+        Self::with_storage(project_id, session_id, project_path, storage).await
+    }
+
+    /// Create a new checkpoint manager on top of an existing storage instance
+    pub async fn with_storage(
+        project_id: String,
+        session_id: String,
+        project_path: PathBuf,
+        storage: Arc<CheckpointStorage>,
+    ) -> Result<Self> {
+        let claude_dir = storage.claude_dir.clone();
 
         // Initialize storage
         storage.init_storage(&project_id, &session_id)?;
@@ -45,9 +99,20 @@ impl CheckpointManager {
             SessionTimeline::new(session_id.clone())
         };
 
-        let file_tracker = FileTracker {
-            tracked_files: HashMap::new(),
+        // Pick up where an evicted manager for this session left off
+        let saved = match Self::take_session_state(&paths) {
+            Ok(saved) => saved,
+            Err(e) => {
+                log::warn!("Discarding saved state of session {}: {}", session_id, e);
+                None
+            }
         };
+        let (messages, tracked_files) = saved
+            .map(|s| (s.messages, s.tracked_files))
+            .unwrap_or_default();
+
+        let file_tracker = FileTracker { tracked_files };
+        let bash_patterns = compile_bash_patterns(&timeline.strategy_thresholds.bash_patterns);
 
         Ok(Self {
             project_id,
@@ -56,26 +121,104 @@ impl CheckpointManager {
             file_tracker: Arc::new(RwLock::new(file_tracker)),
             storage,
             timeline: Arc::new(RwLock::new(timeline)),
-            current_messages: Arc::new(RwLock::new(Vec::new())),
+            current_messages: Arc::new(RwLock::new(messages)),
+            bash_index: Arc::new(RwLock::new(None)),
+            pending_bash_tools: Arc::new(RwLock::new(HashSet::new())),
+            bash_changes: Arc::new(RwLock::new(HashSet::new())),
+            activity: Arc::new(RwLock::new(ActivityProgress::default())),
+            bash_patterns: Arc::new(RwLock::new(bash_patterns)),
         })
     }
 
-    /// Track a new message in the session
+    /// Save the session's tracked messages and file states so a later manager
+    /// can continue from them once this one is dropped
+    pub async fn save_session_state(&self) -> Result<()> {
+        let state = SavedSessionState {
+            messages: self.current_messages.read().await.clone(),
+            tracked_files: self.file_tracker.read().await.tracked_files.clone(),
+        };
+        let json = serde_json::to_vec(&state).context("Failed to serialize session state")?;
+        let compressed = encode_all(&json[..], 3).context("Failed to compress session state")?;
+
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        journal::write_atomic(&paths.session_state_file(), compressed)
+    }
+
+    /// Load and remove saved session state, so it is only ever resumed once
+    fn take_session_state(paths: &CheckpointPaths) -> Result<Option<SavedSessionState>> {
+        let state_file = paths.session_state_file();
+        if !state_file.exists() {
+            return Ok(None);
+        }
+
+        let compressed = fs::read(&state_file).context("Failed to read session state")?;
+        fs::remove_file(&state_file).context("Failed to remove session state")?;
+        let json = decode_all(&compressed[..]).context("Failed to decompress session state")?;
+        Ok(Some(
+            serde_json::from_slice(&json).context("Failed to parse session state")?,
+        ))
+    }
+
+    /// Memory held for the session's messages and tracked files
+    pub async fn memory_usage(&self) -> ManagerMemoryUsage {
+        let messages = self.current_messages.read().await;
+        ManagerMemoryUsage {
+            message_count: messages.len(),
+            message_bytes: messages.iter().map(String::len).sum(),
+            tracked_files: self.file_tracker.read().await.tracked_files.len(),
+        }
+    }
+
+    /// Track a new message in the session.
+    ///
+    /// Use this for messages that may arrive after the fact, such as a
+    /// recorded transcript or messages relayed by the frontend. Bash commands
+    /// in them may already have run, so they are not checked for side effects;
+    /// that is done by `process_stream_message` as the output is read.
     pub async fn track_message(&self, jsonl_message: String) -> Result<()> {
-        let mut messages = self.current_messages.write().await;
-        messages.push(jsonl_message.clone());
+        self.record_message(jsonl_message, false).await
+    }
+
+    /// Record a message and the file operations of its tool uses. `live` is
+    /// set when the message was just read from the running process's output.
+    async fn record_message(&self, jsonl_message: String, live: bool) -> Result<()> {
+        self.current_messages
+            .write()
+            .await
+            .push(jsonl_message.clone());
+        self.record_activity(Utc::now()).await;
 
         // Parse message to check for tool usage
         if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&jsonl_message) {
             if let Some(content) = msg.get("message").and_then(|m| m.get("content")) {
                 if let Some(content_array) = content.as_array() {
                     for item in content_array {
-                        if item.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                            if let Some(tool_name) = item.get("name").and_then(|n| n.as_str()) {
-                                if let Some(input) = item.get("input") {
-                                    self.track_tool_operation(tool_name, input).await?;
+                        let tool_use_id = item
+                            .get("id")
+                            .or_else(|| item.get("tool_use_id"))
+                            .and_then(|id| id.as_str());
+
+                        match item.get("type").and_then(|t| t.as_str()) {
+                            Some("tool_use") => {
+                                if let Some(tool_name) = item.get("name").and_then(|n| n.as_str()) {
+                                    if let Some(input) = item.get("input") {
+                                        self.track_tool_operation(
+                                            tool_name,
+                                            tool_use_id,
+                                            input,
+                                            live,
+                                        )
+                                        .await?;
+                                    }
+                                }
+                            }
+                            Some("tool_result") => {
+                                if let Some(tool_use_id) = tool_use_id {
+                                    self.track_tool_result(tool_use_id).await?;
                                 }
                             }
+                            _ => {}
                         }
                     }
                 }
@@ -85,8 +228,30 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Add the time since the previous message to the session's activity,
+    /// unless the session was idle in between
+    async fn record_activity(&self, now: DateTime<Utc>) {
+        let mut activity = self.activity.write().await;
+        if let Some(last) = activity.last_message_at {
+            let gap = now - last;
+            if gap > chrono::Duration::zero() && gap <= chrono::Duration::minutes(IDLE_GAP_MINUTES)
+            {
+                activity.active_time += gap;
+            }
+        }
+        activity.last_message_at = Some(now);
+    }
+
     /// Track file operations from tool usage
-    async fn track_tool_operation(&self, tool: &str, input: &serde_json::Value) -> Result<()> {
+    async fn track_tool_operation(
+        &self,
+        tool: &str,
+        tool_use_id: Option<&str>,
+        input: &serde_json::Value,
+        live: bool,
+    ) -> Result<()> {
+        self.activity.write().await.changed_lines += Self::count_changed_lines(tool, input);
+
         match tool.to_lowercase().as_str() {
             "edit" | "write" | "multiedit" => {
                 if let Some(file_path) = input.get("file_path").and_then(|p| p.as_str()) {
@@ -94,9 +259,11 @@ impl CheckpointManager {
                 }
             }
             "bash" => {
-                // Try to detect file modifications from bash commands
-                if let Some(command) = input.get("command").and_then(|c| c.as_str()) {
-                    self.track_bash_side_effects(command).await?;
+                // The command's effects are only known once its result arrives.
+                // Claude runs it after printing the tool use, so the baseline
+                // is only accurate when taken as the output is read.
+                if let Some(tool_use_id) = tool_use_id.filter(|_| live) {
+                    self.begin_bash_tracking(tool_use_id).await;
                 }
             }
             _ => {}
@@ -104,6 +271,110 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Lines an editing tool adds or removes, counting a replaced line twice
+    fn count_changed_lines(tool: &str, input: &serde_json::Value) -> usize {
+        let lines = |key: &str, value: &serde_json::Value| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map_or(0, |text| text.lines().count())
+        };
+        let edit_lines =
+            |edit: &serde_json::Value| lines("old_string", edit) + lines("new_string", edit);
+
+        match tool.to_lowercase().as_str() {
+            "edit" => edit_lines(input),
+            "multiedit" => input
+                .get("edits")
+                .and_then(|e| e.as_array())
+                .map_or(0, |edits| edits.iter().map(edit_lines).sum()),
+            "write" => lines("content", input),
+            _ => 0,
+        }
+    }
+
+    /// Capture the project state before a Bash command runs
+    async fn begin_bash_tracking(&self, tool_use_id: &str) {
+        // Refresh the baseline unless another Bash command is still in flight,
+        // so earlier edits are not attributed to this command
+        let needs_baseline = |pending: &HashSet<String>, index: &Option<walker::FileIndex>| {
+            pending.is_empty() || index.is_none()
+        };
+        let refresh = needs_baseline(
+            &*self.pending_bash_tools.read().await,
+            &*self.bash_index.read().await,
+        );
+        let baseline = if refresh {
+            self.index_project().await
+        } else {
+            None
+        };
+
+        let mut pending = self.pending_bash_tools.write().await;
+        let mut index = self.bash_index.write().await;
+        if let Some(baseline) = baseline {
+            if needs_baseline(&pending, &index) {
+                *index = Some(baseline);
+            }
+        }
+        pending.insert(tool_use_id.to_string());
+    }
+
+    /// Fingerprint the project's files on a blocking thread
+    async fn index_project(&self) -> Option<walker::FileIndex> {
+        let project_path = self.project_path.clone();
+        match tokio::task::spawn_blocking(move || walker::build_file_index(&project_path)).await {
+            Ok(index) => Some(index),
+            Err(e) => {
+                log::warn!("Failed to index project files: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Compare the project against the baseline once a Bash command has finished
+    async fn track_tool_result(&self, tool_use_id: &str) -> Result<()> {
+        if !self.pending_bash_tools.write().await.remove(tool_use_id) {
+            return Ok(());
+        }
+
+        let Some(after) = self.index_project().await else {
+            return Ok(());
+        };
+        let changed = {
+            let mut index = self.bash_index.write().await;
+            let changed = index
+                .as_ref()
+                .map(|before| walker::changed_paths(before, &after))
+                .unwrap_or_default();
+            *index = Some(after);
+            changed
+        };
+
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        log::debug!(
+            "Bash tool {} changed {} file(s)",
+            tool_use_id,
+            changed.len()
+        );
+        for path in &changed {
+            if let Some(p) = path.to_str() {
+                if let Err(e) = self.track_file_modification(p).await {
+                    log::warn!("Failed to track {:?} after Bash command: {}", path, e);
+                }
+            }
+        }
+        self.bash_changes
+            .write()
+            .await
+            .insert(tool_use_id.to_string());
+
+        Ok(())
+    }
+
     /// Track a file modification
     pub async fn track_file_modification(&self, file_path: &str) -> Result<()> {
         let mut tracker = self.file_tracker.write().await;
@@ -111,7 +382,7 @@ impl CheckpointManager {
 
         // Read current file state
         let (hash, exists, _size, modified) = if full_path.exists() {
-            let content = fs::read_to_string(&full_path).unwrap_or_default();
+            let content = fs::read(&full_path).context("Failed to read tracked file")?;
             let metadata = fs::metadata(&full_path)?;
             let modified = metadata
                 .modified()
@@ -161,29 +432,6 @@ impl CheckpointManager {
         Ok(())
     }
 
-    /// Track potential file changes from bash commands
-    async fn track_bash_side_effects(&self, command: &str) -> Result<()> {
-        // Common file-modifying commands
-        let file_commands = [
-            "echo", "cat", "cp", "mv", "rm", "touch", "sed", "awk", "npm", "yarn", "pnpm", "bun",
-            "cargo", "make", "gcc", "g++",
-        ];
-
-        // Simple heuristic: if command contains file-modifying operations
-        for cmd in &file_commands {
-            if command.contains(cmd) {
-                // Mark all tracked files as potentially modified
-                let mut tracker = self.file_tracker.write().await;
-                for (_, state) in tracker.tracked_files.iter_mut() {
-                    state.is_modified = true;
-                }
-                break;
-            }
-        }
-
-        Ok(())
-    }
-
     /// Create a checkpoint
     pub async fn create_checkpoint(
         &self,
@@ -197,37 +445,10 @@ impl CheckpointManager {
         let (user_prompt, model_used, total_tokens) =
             self.extract_checkpoint_metadata(&messages).await?;
 
-        // Ensure every file in the project is tracked so new checkpoints include all files
-        // Recursively walk the project directory and track each file
-        fn collect_files(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-            files: &mut Vec<std::path::PathBuf>,
-        ) -> Result<(), std::io::Error> {
-            for entry in std::fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    // Skip hidden directories like .git
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
-                            continue;
-                        }
-                    }
-                    collect_files(&path, base, files)?;
-                } else if path.is_file() {
-                    // Compute relative path from project root
-                    if let Ok(rel) = path.strip_prefix(base) {
-                        files.push(rel.to_path_buf());
-                    }
-                }
-            }
-            Ok(())
-        }
-        let mut all_files = Vec::new();
-        let project_dir = &self.project_path;
-        let _ = collect_files(project_dir.as_path(), project_dir.as_path(), &mut all_files);
-        for rel in all_files {
+        // Ensure every file in the project is tracked so new checkpoints include all files,
+        // leaving out anything excluded by the project's ignore rules
+        let project_files = walker::collect_project_files(&self.project_path);
+        for rel in &project_files.files {
             if let Some(p) = rel.to_str() {
                 // Track each file for snapshot
                 let _ = self.track_file_modification(p).await;
@@ -257,6 +478,9 @@ impl CheckpointManager {
                     timeline.current_checkpoint_id.clone()
                 }
             },
+            merge_parent_ids: Vec::new(),
+            tags: Vec::new(),
+            milestone: false,
             metadata: CheckpointMetadata {
                 total_tokens,
                 model_used,
@@ -271,13 +495,29 @@ impl CheckpointManager {
 
         // Save checkpoint
         let messages_content = messages.join("\n");
-        let result = self.storage.save_checkpoint(
+        let mut result = self.storage.save_checkpoint(
             &self.project_id,
             &self.session_id,
             &checkpoint,
             file_snapshots,
             &messages_content,
         )?;
+        result.warnings.extend(project_files.skipped_warnings());
+
+        // Prune old checkpoints according to the timeline's retention policy
+        match self
+            .storage
+            .apply_retention_policy(&self.project_id, &self.session_id)
+        {
+            Ok(0) => {}
+            Ok(removed) => log::info!("Retention policy removed {} checkpoints", removed),
+            Err(e) => {
+                log::warn!("Failed to apply retention policy: {}", e);
+                result
+                    .warnings
+                    .push(format!("Failed to apply retention policy: {}", e));
+            }
+        }
 
         // Reload timeline from disk so in-memory timeline has updated nodes and total_checkpoints
         let claude_dir = self.storage.claude_dir.clone();
@@ -297,6 +537,10 @@ impl CheckpointManager {
         for (_, state) in tracker.tracked_files.iter_mut() {
             state.is_modified = false;
         }
+        self.bash_changes.write().await.clear();
+        let mut activity = self.activity.write().await;
+        activity.active_time = chrono::Duration::zero();
+        activity.changed_lines = 0;
 
         Ok(result)
     }
@@ -411,7 +655,13 @@ impl CheckpointManager {
             let full_path = self.project_path.join(rel_path);
 
             let (content, exists, permissions, size, current_hash) = if full_path.exists() {
-                let content = fs::read_to_string(&full_path).unwrap_or_default();
+                let content = match fs::read(&full_path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        log::warn!("Skipping unreadable file {:?}: {}", rel_path, e);
+                        continue;
+                    }
+                };
                 let current_hash = storage::CheckpointStorage::calculate_file_hash(&content);
 
                 // Don't skip based on hash - if is_modified is true, we should snapshot it
@@ -431,7 +681,7 @@ impl CheckpointManager {
                 };
                 (content, true, permissions, metadata.len(), current_hash)
             } else {
-                (String::new(), false, None, 0, String::new())
+                (Vec::new(), false, None, 0, String::new())
             };
 
             snapshots.push(FileSnapshot {
@@ -450,41 +700,15 @@ impl CheckpointManager {
 
     /// Restore a checkpoint
     pub async fn restore_checkpoint(&self, checkpoint_id: &str) -> Result<CheckpointResult> {
-        // Load checkpoint data
-        let (checkpoint, file_snapshots, messages) =
+        // Load the state of every file at the checkpoint, not just its own changes
+        let (checkpoint, file_snapshots) = self.load_file_snapshots_at(checkpoint_id)?;
+        let messages =
             self.storage
-                .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
+                .load_messages(&self.project_id, &self.session_id, checkpoint_id)?;
 
-        // First, collect all files currently in the project to handle deletions
-        fn collect_all_project_files(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-            files: &mut Vec<std::path::PathBuf>,
-        ) -> Result<(), std::io::Error> {
-            for entry in std::fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    // Skip hidden directories like .git
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
-                            continue;
-                        }
-                    }
-                    collect_all_project_files(&path, base, files)?;
-                } else if path.is_file() {
-                    // Compute relative path from project root
-                    if let Ok(rel) = path.strip_prefix(base) {
-                        files.push(rel.to_path_buf());
-                    }
-                }
-            }
-            Ok(())
-        }
-
-        let mut current_files = Vec::new();
-        let _ =
-            collect_all_project_files(&self.project_path, &self.project_path, &mut current_files);
+        // First, collect all files currently in the project to handle deletions.
+        // Paths excluded by ignore rules are never deleted.
+        let project_files = walker::collect_project_files(&self.project_path);
 
         // Create a set of files that should exist after restore
         let mut checkpoint_files = std::collections::HashSet::new();
@@ -495,13 +719,13 @@ impl CheckpointManager {
         }
 
         // Delete files that exist now but shouldn't exist in the checkpoint
-        let mut warnings = Vec::new();
+        let mut warnings = project_files.skipped_warnings();
         let mut files_processed = 0;
 
-        for current_file in current_files {
-            if !checkpoint_files.contains(&current_file) {
+        for current_file in &project_files.files {
+            if !checkpoint_files.contains(current_file) {
                 // This file exists now but not in the checkpoint, so delete it
-                let full_path = self.project_path.join(&current_file);
+                let full_path = self.project_path.join(current_file);
                 match fs::remove_file(&full_path) {
                     Ok(_) => {
                         files_processed += 1;
@@ -518,39 +742,17 @@ impl CheckpointManager {
             }
         }
 
-        // Clean up empty directories
-        fn remove_empty_dirs(
-            dir: &std::path::Path,
-            base: &std::path::Path,
-        ) -> Result<bool, std::io::Error> {
-            if dir == base {
-                return Ok(false); // Don't remove the base directory
-            }
-
-            let mut is_empty = true;
-            for entry in fs::read_dir(dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_dir() {
-                    if !remove_empty_dirs(&path, base)? {
-                        is_empty = false;
-                    }
-                } else {
-                    is_empty = false;
-                }
-            }
-
+        // Clean up any empty directories left after file deletion, deepest first
+        for dir in project_files.dirs.iter().rev() {
+            let full_path = self.project_path.join(dir);
+            let is_empty = fs::read_dir(&full_path)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false);
             if is_empty {
-                fs::remove_dir(dir)?;
-                Ok(true)
-            } else {
-                Ok(false)
+                let _ = fs::remove_dir(&full_path);
             }
         }
 
-        // Clean up any empty directories left after file deletion
-        let _ = remove_empty_dirs(&self.project_path, &self.project_path);
-
         // Restore files from checkpoint
         for snapshot in &file_snapshots {
             match self.restore_file_snapshot(snapshot).await {
@@ -574,6 +776,11 @@ impl CheckpointManager {
         let mut timeline = self.timeline.write().await;
         timeline.current_checkpoint_id = Some(checkpoint_id.to_string());
 
+        // The working tree was rewritten, so any Bash baseline is stale
+        *self.bash_index.write().await = None;
+        self.pending_bash_tools.write().await.clear();
+        self.bash_changes.write().await.clear();
+
         // Update file tracker
         let mut tracker = self.file_tracker.write().await;
         tracker.tracked_files.clear();
@@ -595,9 +802,117 @@ impl CheckpointManager {
             checkpoint: checkpoint.clone(),
             files_processed,
             warnings,
+            transcript: None,
+        })
+    }
+
+    /// Cut the Claude session transcript back to a checkpoint.
+    ///
+    /// The transcript keeps its entries up to the one matching the last
+    /// message recorded at the checkpoint, so resuming the returned session
+    /// continues from exactly that point. `InPlace` truncates this session's
+    /// transcript, dropping later entries; `NewSession` leaves it alone and
+    /// writes the kept entries under a new session ID.
+    ///
+    /// The session must not be running, as Claude would keep appending to the
+    /// transcript.
+    pub async fn restore_transcript(
+        &self,
+        checkpoint_id: &str,
+        mode: TranscriptRestoreMode,
+    ) -> Result<TranscriptRestore> {
+        let (checkpoint, _, messages) =
+            self.storage
+                .load_checkpoint(&self.project_id, &self.session_id, checkpoint_id)?;
+        let lines: Vec<&str> = messages
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(checkpoint.message_index + 1)
+            .collect();
+        if lines.is_empty() {
+            anyhow::bail!("Checkpoint {} has no recorded conversation", checkpoint_id);
+        }
+
+        let projects_dir = self
+            .storage
+            .claude_dir
+            .join("projects")
+            .join(&self.project_id);
+        let current = fs::read_to_string(projects_dir.join(format!("{}.jsonl", self.session_id)))
+            .context("Failed to read session transcript")?;
+        let entries: Vec<&str> = current
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+
+        // The latest recorded message that can be found in the transcript
+        // marks where the checkpoint's conversation ends
+        let end = lines
+            .iter()
+            .rev()
+            .map(|line| transcript_keys(line))
+            .filter(|keys| !keys.is_empty())
+            .find_map(|keys| {
+                entries
+                    .iter()
+                    .rposition(|entry| transcript_keys(entry).iter().any(|k| keys.contains(k)))
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "The session transcript has no entry matching checkpoint {}",
+                    checkpoint_id
+                )
+            })?;
+        let kept = &entries[..=end];
+
+        let (session_id, transcript) = match mode {
+            TranscriptRestoreMode::InPlace => (
+                self.session_id.clone(),
+                kept.iter().map(|l| l.to_string()).collect::<Vec<_>>(),
+            ),
+            TranscriptRestoreMode::NewSession => {
+                let new_id = uuid::Uuid::new_v4().to_string();
+                let rewritten = kept
+                    .iter()
+                    .map(|line| self.rewrite_session_id(line, &new_id))
+                    .collect();
+                (new_id, rewritten)
+            }
+        };
+
+        // Write next to the transcript and rename so a crash never leaves it half-written
+        let session_path = projects_dir.join(format!("{}.jsonl", session_id));
+        let temp_path = session_path.with_extension("jsonl.tmp");
+        fs::write(&temp_path, transcript.join("\n") + "\n")
+            .context("Failed to write session transcript")?;
+        fs::rename(&temp_path, &session_path).context("Failed to replace session transcript")?;
+
+        if mode == TranscriptRestoreMode::InPlace {
+            *self.current_messages.write().await = lines.iter().map(|l| l.to_string()).collect();
+        }
+
+        Ok(TranscriptRestore {
+            session_id,
+            message_count: transcript.len(),
         })
     }
 
+    /// Point a transcript line at another session
+    fn rewrite_session_id(&self, line: &str, new_id: &str) -> String {
+        let Ok(mut message) = serde_json::from_str::<serde_json::Value>(line) else {
+            return line.to_string();
+        };
+        let Some(object) = message.as_object_mut() else {
+            return line.to_string();
+        };
+        for key in ["sessionId", "session_id"] {
+            if object.get(key).and_then(|v| v.as_str()) == Some(self.session_id.as_str()) {
+                object.insert(key.to_string(), serde_json::Value::from(new_id));
+            }
+        }
+        message.to_string()
+    }
+
     /// Restore a single file from snapshot
     async fn restore_file_snapshot(&self, snapshot: &FileSnapshot) -> Result<()> {
         let full_path = self.project_path.join(&snapshot.file_path);
@@ -629,6 +944,306 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Work out what `restore_checkpoint` would do without changing anything.
+    ///
+    /// Directories that would be removed and then recreated by a write are
+    /// not listed in `removed_dirs`.
+    pub async fn plan_restore(&self, checkpoint_id: &str) -> Result<RestorePlan> {
+        let (_, file_snapshots) = self.load_file_snapshots_at(checkpoint_id)?;
+        let project_files = walker::collect_project_files(&self.project_path);
+        let tracker = self.file_tracker.read().await;
+
+        let mut plan = RestorePlan {
+            checkpoint_id: checkpoint_id.to_string(),
+            warnings: project_files.skipped_warnings(),
+            ..Default::default()
+        };
+
+        // Hash of a file on disk, and whether it differs from what the tracker
+        // last saw; a file the tracker never saw was created outside Claude
+        let inspect = |rel_path: &Path| -> (Option<String>, bool) {
+            let Ok(content) = fs::read(self.project_path.join(rel_path)) else {
+                return (None, false);
+            };
+            let hash = storage::CheckpointStorage::calculate_file_hash(&content);
+            let changed_locally = tracker
+                .tracked_files
+                .get(rel_path)
+                .is_none_or(|state| state.last_hash != hash);
+            (Some(hash), changed_locally)
+        };
+
+        let checkpoint_files: HashSet<&PathBuf> = file_snapshots
+            .iter()
+            .filter(|s| !s.is_deleted)
+            .map(|s| &s.file_path)
+            .collect();
+
+        // Files that exist now but not in the checkpoint
+        let mut deleted_first = HashSet::new();
+        for current_file in &project_files.files {
+            if checkpoint_files.contains(current_file) {
+                continue;
+            }
+            let (_, changed_locally) = inspect(current_file);
+            plan.deletions.push(PlannedFileChange {
+                path: current_file.clone(),
+                size: fs::metadata(self.project_path.join(current_file))
+                    .map(|m| m.len())
+                    .unwrap_or(0),
+                exists: true,
+                unchanged: false,
+                would_lose_local_changes: changed_locally,
+            });
+            deleted_first.insert(self.project_path.join(current_file));
+        }
+
+        // Directories emptied by those deletions, deepest first
+        let mut removed_dirs: HashSet<PathBuf> = HashSet::new();
+        for dir in project_files.dirs.iter().rev() {
+            let full_path = self.project_path.join(dir);
+            let Ok(entries) = fs::read_dir(&full_path) else {
+                continue;
+            };
+            let empty_after = entries
+                .filter_map(|e| e.ok())
+                .all(|e| deleted_first.contains(&e.path()) || removed_dirs.contains(&e.path()));
+            if empty_after {
+                removed_dirs.insert(full_path);
+            }
+        }
+
+        for snapshot in &file_snapshots {
+            let full_path = self.project_path.join(&snapshot.file_path);
+            let (disk_hash, changed_locally) = inspect(&snapshot.file_path);
+
+            if snapshot.is_deleted {
+                if full_path.exists() && !deleted_first.contains(&full_path) {
+                    plan.deletions.push(PlannedFileChange {
+                        path: snapshot.file_path.clone(),
+                        size: fs::metadata(&full_path).map(|m| m.len()).unwrap_or(0),
+                        exists: true,
+                        unchanged: false,
+                        would_lose_local_changes: changed_locally,
+                    });
+                }
+                continue;
+            }
+
+            let unchanged = disk_hash.as_deref() == Some(snapshot.hash.as_str());
+            plan.writes.push(PlannedFileChange {
+                path: snapshot.file_path.clone(),
+                size: snapshot.content.len() as u64,
+                exists: disk_hash.is_some(),
+                unchanged,
+                would_lose_local_changes: changed_locally && !unchanged,
+            });
+
+            #[cfg(unix)]
+            if let (Some(mode), Ok(metadata)) = (snapshot.permissions, fs::metadata(&full_path)) {
+                use std::os::unix::fs::PermissionsExt;
+                let current = metadata.permissions().mode();
+                if current != mode {
+                    plan.permission_changes.push(PlannedPermissionChange {
+                        path: snapshot.file_path.clone(),
+                        from: current,
+                        to: mode,
+                    });
+                }
+            }
+
+            // A write recreates any directory above it
+            for ancestor in full_path
+                .ancestors()
+                .skip(1)
+                .take_while(|a| *a != self.project_path)
+            {
+                removed_dirs.remove(ancestor);
+            }
+        }
+
+        plan.removed_dirs = project_files
+            .dirs
+            .iter()
+            .rev()
+            .filter(|dir| removed_dirs.contains(&self.project_path.join(dir)))
+            .cloned()
+            .collect();
+
+        Ok(plan)
+    }
+
+    /// Restore only the files matching `patterns` from a checkpoint.
+    ///
+    /// Patterns are project-relative paths, directories or globs. Matching files
+    /// are brought back to their state at the checkpoint, which deletes those
+    /// that did not exist then. The rest of the working tree and the current
+    /// timeline position are left alone. Files changed on disk since their last
+    /// snapshot are reported as conflicts and skipped unless `force` is set.
+    pub async fn restore_files(
+        &self,
+        checkpoint_id: &str,
+        patterns: &[String],
+        force: bool,
+    ) -> Result<PartialRestoreResult> {
+        let matchers = patterns
+            .iter()
+            .map(|p| PathMatcher::new(p, &self.project_path))
+            .collect::<Result<Vec<_>>>()?;
+
+        let (_, target) = self.load_file_states(checkpoint_id)?;
+
+        // State of each file as of the last checkpoint, to detect local changes
+        let current_checkpoint_id = self.timeline.read().await.current_checkpoint_id.clone();
+        let baseline = match &current_checkpoint_id {
+            Some(id) => Some(self.load_file_states(id)?.1),
+            None => None,
+        };
+
+        let mut result = PartialRestoreResult {
+            checkpoint_id: checkpoint_id.to_string(),
+            ..Default::default()
+        };
+
+        let project_files = walker::collect_project_files(&self.project_path);
+        let candidates: std::collections::BTreeSet<&PathBuf> =
+            target.keys().chain(project_files.files.iter()).collect();
+
+        let mut matched = vec![false; matchers.len()];
+        let mut tracker = self.file_tracker.write().await;
+
+        for rel_path in candidates {
+            let mut is_match = false;
+            for (i, matcher) in matchers.iter().enumerate() {
+                if matcher.matches(rel_path) {
+                    matched[i] = true;
+                    is_match = true;
+                }
+            }
+            if !is_match {
+                continue;
+            }
+
+            let full_path = self.project_path.join(rel_path);
+            let disk_hash = if full_path.is_file() {
+                match fs::read(&full_path) {
+                    Ok(content) => Some(storage::CheckpointStorage::calculate_file_hash(&content)),
+                    Err(e) => {
+                        result.warnings.push(format!(
+                            "Failed to read {}: {}",
+                            rel_path.display(),
+                            e
+                        ));
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+
+            let snapshot = target.get(rel_path).filter(|s| !s.is_deleted);
+            if disk_hash.as_deref() == snapshot.map(|s| s.hash.as_str()) {
+                continue;
+            }
+
+            if let Some(baseline) = &baseline {
+                let last_hash = baseline
+                    .get(rel_path)
+                    .filter(|s| !s.is_deleted)
+                    .map(|s| s.hash.as_str());
+                if disk_hash.as_deref() != last_hash {
+                    result.conflicts.push(rel_path.clone());
+                    if !force {
+                        continue;
+                    }
+                }
+            }
+
+            let restored = match snapshot {
+                Some(snapshot) => self.restore_file_snapshot(snapshot).await,
+                None => fs::remove_file(&full_path).context("Failed to delete file"),
+            };
+            if let Err(e) = restored {
+                result
+                    .warnings
+                    .push(format!("Failed to restore {}: {}", rel_path.display(), e));
+                continue;
+            }
+
+            match snapshot {
+                Some(_) => result.restored_files.push(rel_path.clone()),
+                None => {
+                    result.deleted_files.push(rel_path.clone());
+                    self.remove_empty_parents(&full_path);
+                }
+            }
+
+            // The tree now differs from the current checkpoint for this file
+            tracker.tracked_files.insert(
+                rel_path.clone(),
+                FileState {
+                    last_hash: snapshot.map(|s| s.hash.clone()).unwrap_or_default(),
+                    is_modified: true,
+                    last_modified: Utc::now(),
+                    exists: snapshot.is_some(),
+                },
+            );
+        }
+
+        result.unmatched_patterns = patterns
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(pattern, _)| pattern.clone())
+            .collect();
+
+        Ok(result)
+    }
+
+    /// The state of every file at a checkpoint as snapshots ordered by path
+    fn load_file_snapshots_at(
+        &self,
+        checkpoint_id: &str,
+    ) -> Result<(Checkpoint, Vec<FileSnapshot>)> {
+        let (checkpoint, states) = self.load_file_states(checkpoint_id)?;
+        let mut snapshots: Vec<FileSnapshot> = states.into_values().collect();
+        snapshots.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        Ok((checkpoint, snapshots))
+    }
+
+    /// Load the state of every file at a checkpoint, following the ancestor chain
+    fn load_file_states(
+        &self,
+        checkpoint_id: &str,
+    ) -> Result<(Checkpoint, HashMap<PathBuf, FileSnapshot>)> {
+        self.storage
+            .load_file_states(&self.project_id, &self.session_id, checkpoint_id)
+    }
+
+    /// Remove directories left empty by deleting `file`, up to the project root
+    fn remove_empty_parents(&self, file: &Path) {
+        let mut dir = file.parent();
+        while let Some(current) = dir {
+            if current == self.project_path || !current.starts_with(&self.project_path) {
+                break;
+            }
+            if fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+
+    /// Project this manager's session belongs to
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    /// Session this manager tracks
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
     /// Get the current timeline
     pub async fn get_timeline(&self) -> SessionTimeline {
         self.timeline.read().await.clone()
@@ -679,6 +1294,275 @@ impl CheckpointManager {
             .await
     }
 
+    /// Merge the branch ending at `theirs_id` into the one ending at `ours_id`.
+    ///
+    /// Every file is merged three ways against the nearest common ancestor of
+    /// the two checkpoints. Without conflicts a merge checkpoint is created as
+    /// a child of `ours_id` with `theirs_id` as a merge parent; otherwise
+    /// nothing is saved and the conflicts are returned. The project files and
+    /// the current checkpoint are left alone; restore the merge checkpoint to
+    /// apply it.
+    pub async fn merge_checkpoints(
+        &self,
+        ours_id: &str,
+        theirs_id: &str,
+        description: Option<String>,
+    ) -> Result<MergeResult> {
+        let base_id = {
+            let timeline = self.timeline.read().await;
+            for id in [ours_id, theirs_id] {
+                if timeline.find_checkpoint(id).is_none() {
+                    anyhow::bail!("Checkpoint not found: {}", id);
+                }
+            }
+            Self::merge_base(&timeline, ours_id, theirs_id)
+        };
+        if base_id.as_deref() == Some(theirs_id) {
+            anyhow::bail!("Checkpoint {} is already part of {}", theirs_id, ours_id);
+        }
+
+        let (ours, ours_states) = self.load_file_states(ours_id)?;
+        let (theirs, theirs_states) = self.load_file_states(theirs_id)?;
+        let base_states = match &base_id {
+            Some(id) => self.load_file_states(id)?.1,
+            None => HashMap::new(),
+        };
+
+        let mut result = MergeResult {
+            ours_checkpoint_id: ours_id.to_string(),
+            theirs_checkpoint_id: theirs_id.to_string(),
+            base_checkpoint_id: base_id,
+            ..Default::default()
+        };
+
+        let label = |checkpoint: &Checkpoint| {
+            checkpoint
+                .description
+                .clone()
+                .unwrap_or_else(|| checkpoint.id.chars().take(8).collect())
+        };
+        let (ours_label, theirs_label) = (label(&ours), label(&theirs));
+        let merge_id = storage::CheckpointStorage::generate_checkpoint_id();
+        let mut snapshots = Vec::new();
+
+        let paths: std::collections::BTreeSet<&PathBuf> = ours_states
+            .keys()
+            .chain(theirs_states.keys())
+            .chain(base_states.keys())
+            .collect();
+        for path in paths {
+            let live = |states: &HashMap<PathBuf, FileSnapshot>| {
+                states.get(path).filter(|s| !s.is_deleted).cloned()
+            };
+            let (base, ours, theirs) =
+                (live(&base_states), live(&ours_states), live(&theirs_states));
+            let hash = |s: &Option<FileSnapshot>| s.as_ref().map(|s| s.hash.clone());
+
+            // Only files changed differently on both sides need a real merge
+            if hash(&ours) == hash(&theirs) || hash(&base) == hash(&theirs) {
+                continue;
+            }
+            let merged = if hash(&base) == hash(&ours) {
+                theirs
+            } else {
+                let (Some(ours), Some(theirs)) = (ours, theirs) else {
+                    result.conflicts.push(MergeConflict {
+                        path: path.clone(),
+                        kind: MergeConflictKind::DeleteModify,
+                        content: None,
+                    });
+                    continue;
+                };
+                let base_content = base.map(|s| s.content).unwrap_or_default();
+                let texts = [&base_content, &ours.content, &theirs.content]
+                    .map(|c| std::str::from_utf8(c).ok().filter(|_| !diff::is_binary(c)));
+                let [Some(base_text), Some(ours_text), Some(theirs_text)] = texts else {
+                    result.conflicts.push(MergeConflict {
+                        path: path.clone(),
+                        kind: MergeConflictKind::Binary,
+                        content: None,
+                    });
+                    continue;
+                };
+
+                let merge = diff::merge3(
+                    base_text,
+                    ours_text,
+                    theirs_text,
+                    &ours_label,
+                    &theirs_label,
+                );
+                if merge.conflicts > 0 {
+                    result.conflicts.push(MergeConflict {
+                        path: path.clone(),
+                        kind: MergeConflictKind::Content,
+                        content: Some(merge.content),
+                    });
+                    continue;
+                }
+                let content = merge.content.into_bytes();
+                Some(FileSnapshot {
+                    hash: storage::CheckpointStorage::calculate_file_hash(&content),
+                    size: content.len() as u64,
+                    content,
+                    ..ours
+                })
+            };
+
+            result.merged_files.push(path.clone());
+            snapshots.push(match merged {
+                Some(snapshot) => FileSnapshot {
+                    checkpoint_id: merge_id.clone(),
+                    ..snapshot
+                },
+                None => FileSnapshot {
+                    checkpoint_id: merge_id.clone(),
+                    file_path: path.clone(),
+                    content: Vec::new(),
+                    hash: String::new(),
+                    is_deleted: true,
+                    permissions: None,
+                    size: 0,
+                },
+            });
+        }
+
+        if !result.conflicts.is_empty() {
+            return Ok(result);
+        }
+
+        let (_, _, messages) =
+            self.storage
+                .load_checkpoint(&self.project_id, &self.session_id, ours_id)?;
+        let checkpoint = Checkpoint {
+            id: merge_id,
+            session_id: self.session_id.clone(),
+            project_id: self.project_id.clone(),
+            message_index: ours.message_index,
+            timestamp: Utc::now(),
+            description: Some(
+                description
+                    .unwrap_or_else(|| format!("Merge {} into {}", theirs_label, ours_label)),
+            ),
+            parent_checkpoint_id: Some(ours_id.to_string()),
+            merge_parent_ids: vec![theirs_id.to_string()],
+            tags: Vec::new(),
+            milestone: false,
+            metadata: CheckpointMetadata {
+                file_changes: snapshots.len(),
+                snapshot_size: storage::CheckpointStorage::estimate_checkpoint_size(
+                    &messages, &snapshots,
+                ),
+                user_prompt: String::new(),
+                ..ours.metadata
+            },
+        };
+        self.storage.save_checkpoint(
+            &self.project_id,
+            &self.session_id,
+            &checkpoint,
+            snapshots,
+            &messages,
+        )?;
+
+        // Saving moved the current checkpoint to the merge; keep it where it was
+        let mut timeline = self.timeline.write().await;
+        let paths =
+            CheckpointPaths::new(&self.storage.claude_dir, &self.project_id, &self.session_id);
+        let mut updated = self.storage.load_timeline(&paths.timeline_file)?;
+        updated.current_checkpoint_id = timeline.current_checkpoint_id.clone();
+        self.storage.save_timeline(&paths.timeline_file, &updated)?;
+        *timeline = updated;
+
+        result.checkpoint = Some(checkpoint);
+        Ok(result)
+    }
+
+    /// Replace a checkpoint's tags and milestone flag
+    pub async fn update_checkpoint_labels(
+        &self,
+        checkpoint_id: &str,
+        tags: Vec<String>,
+        milestone: bool,
+    ) -> Result<Checkpoint> {
+        let mut timeline = self.timeline.write().await;
+        let checkpoint = self.storage.update_checkpoint_labels(
+            &self.project_id,
+            &self.session_id,
+            checkpoint_id,
+            tags,
+            milestone,
+        )?;
+        if let Some(node) = timeline.find_checkpoint_mut(checkpoint_id) {
+            node.checkpoint = checkpoint.clone();
+        }
+        Ok(checkpoint)
+    }
+
+    /// Nearest checkpoint that is an ancestor of both `a` and `b`, following merge parents
+    fn merge_base(timeline: &SessionTimeline, a: &str, b: &str) -> Option<String> {
+        let mut nodes = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            Self::collect_nodes(root, &mut nodes);
+        }
+        let parents: HashMap<&str, Vec<&str>> = nodes
+            .iter()
+            .map(|n| {
+                let checkpoint = &n.checkpoint;
+                let parents = checkpoint
+                    .parent_checkpoint_id
+                    .iter()
+                    .chain(&checkpoint.merge_parent_ids)
+                    .map(String::as_str)
+                    .collect();
+                (checkpoint.id.as_str(), parents)
+            })
+            .collect();
+
+        // Breadth-first from each side, so the first shared checkpoint is the nearest
+        let ancestors = |start: &str| {
+            let mut order = vec![start.to_string()];
+            let mut seen = HashSet::from([start.to_string()]);
+            let mut i = 0;
+            while let Some(id) = order.get(i).cloned() {
+                for parent in parents.get(id.as_str()).into_iter().flatten() {
+                    if seen.insert(parent.to_string()) {
+                        order.push(parent.to_string());
+                    }
+                }
+                i += 1;
+            }
+            order
+        };
+        let of_a: HashSet<String> = ancestors(a).into_iter().collect();
+        ancestors(b).into_iter().find(|id| of_a.contains(id))
+    }
+
+    fn collect_nodes<'a>(node: &'a TimelineNode, nodes: &mut Vec<&'a TimelineNode>) {
+        nodes.push(node);
+        for child in &node.children {
+            Self::collect_nodes(child, nodes);
+        }
+    }
+
+    /// Track a streamed message and take a checkpoint if the session's
+    /// strategy calls for one after it
+    pub async fn process_stream_message(&self, message: &str) -> Result<Option<CheckpointResult>> {
+        self.record_message(message.to_string(), true).await?;
+
+        if !self.should_auto_checkpoint(message).await {
+            return Ok(None);
+        }
+
+        let result = self.create_checkpoint(None, None).await?;
+        log::info!(
+            "Created automatic checkpoint {} for session {}",
+            result.checkpoint.id,
+            self.session_id
+        );
+        Ok(Some(result))
+    }
+
     /// Check if auto-checkpoint should be triggered
     pub async fn should_auto_checkpoint(&self, message: &str) -> bool {
         let timeline = self.timeline.read().await;
@@ -690,11 +1574,20 @@ impl CheckpointManager {
         match timeline.checkpoint_strategy {
             CheckpointStrategy::Manual => false,
             CheckpointStrategy::PerPrompt => {
-                // Check if message is a user prompt
-                if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
-                    msg.get("type").and_then(|t| t.as_str()) == Some("user")
-                } else {
-                    false
+                // Check if message is a user prompt. Tool results are sent back
+                // to Claude as user messages too, but carry no text.
+                let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) else {
+                    return false;
+                };
+                if msg.get("type").and_then(|t| t.as_str()) != Some("user") {
+                    return false;
+                }
+                match msg.get("message").and_then(|m| m.get("content")) {
+                    Some(serde_json::Value::String(_)) => true,
+                    Some(serde_json::Value::Array(content)) => content
+                        .iter()
+                        .any(|item| item.get("type").and_then(|t| t.as_str()) == Some("text")),
+                    _ => false,
                 }
             }
             CheckpointStrategy::PerToolUse => {
@@ -716,25 +1609,32 @@ impl CheckpointManager {
                 }
             }
             CheckpointStrategy::Smart => {
-                // Smart strategy: checkpoint after destructive operations
+                // Smart strategy: checkpoint after destructive operations. Bash commands
+                // count once their result shows they actually changed files.
+                let bash_changes = self.bash_changes.read().await;
                 if let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) {
                     if let Some(content) = msg
                         .get("message")
                         .and_then(|m| m.get("content"))
                         .and_then(|c| c.as_array())
                     {
-                        content.iter().any(|item| {
-                            if item.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
-                                let tool_name =
-                                    item.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                                matches!(
-                                    tool_name.to_lowercase().as_str(),
-                                    "write" | "edit" | "multiedit" | "bash" | "rm" | "delete"
-                                )
-                            } else {
-                                false
-                            }
-                        })
+                        content
+                            .iter()
+                            .any(|item| match item.get("type").and_then(|t| t.as_str()) {
+                                Some("tool_use") => {
+                                    let tool_name =
+                                        item.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                                    matches!(
+                                        tool_name.to_lowercase().as_str(),
+                                        "write" | "edit" | "multiedit" | "rm" | "delete"
+                                    )
+                                }
+                                Some("tool_result") => item
+                                    .get("tool_use_id")
+                                    .and_then(|id| id.as_str())
+                                    .is_some_and(|id| bash_changes.contains(id)),
+                                _ => false,
+                            })
                     } else {
                         false
                     }
@@ -742,9 +1642,66 @@ impl CheckpointManager {
                     false
                 }
             }
+            CheckpointStrategy::Interval => {
+                let minutes = timeline.strategy_thresholds.interval_minutes;
+                minutes > 0
+                    && self.activity.read().await.active_time
+                        >= chrono::Duration::minutes(minutes.into())
+            }
+            CheckpointStrategy::ChangedLines => {
+                let threshold = timeline.strategy_thresholds.changed_lines;
+                threshold > 0 && self.activity.read().await.changed_lines >= threshold
+            }
+            CheckpointStrategy::BashPattern => {
+                // Checkpoint as soon as a matching command is requested, before its result
+                let patterns = self.bash_patterns.read().await;
+                let Ok(msg) = serde_json::from_str::<serde_json::Value>(message) else {
+                    return false;
+                };
+                msg.get("message")
+                    .and_then(|m| m.get("content"))
+                    .and_then(|c| c.as_array())
+                    .is_some_and(|content| {
+                        content.iter().any(|item| {
+                            item.get("type").and_then(|t| t.as_str()) == Some("tool_use")
+                                && item
+                                    .get("name")
+                                    .and_then(|n| n.as_str())
+                                    .is_some_and(|n| n.eq_ignore_ascii_case("bash"))
+                                && item
+                                    .get("input")
+                                    .and_then(|i| i.get("command"))
+                                    .and_then(|c| c.as_str())
+                                    .is_some_and(|cmd| patterns.iter().any(|re| re.is_match(cmd)))
+                        })
+                    })
+            }
         }
     }
 
+    /// Update the thresholds of the interval, changed-lines and Bash-pattern strategies
+    pub async fn update_strategy_thresholds(&self, thresholds: StrategyThresholds) -> Result<()> {
+        let patterns = thresholds
+            .bash_patterns
+            .iter()
+            .map(|pattern| {
+                regex::Regex::new(pattern)
+                    .with_context(|| format!("Invalid Bash pattern: {}", pattern))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut timeline = self.timeline.write().await;
+        timeline.strategy_thresholds = thresholds;
+
+        let claude_dir = self.storage.claude_dir.clone();
+        let paths = CheckpointPaths::new(&claude_dir, &self.project_id, &self.session_id);
+        self.storage
+            .save_timeline(&paths.timeline_file, &timeline)?;
+        *self.bash_patterns.write().await = patterns;
+
+        Ok(())
+    }
+
     /// Update checkpoint settings
     pub async fn update_settings(
         &self,
@@ -764,6 +1721,26 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Update the retention policy and apply it immediately
+    pub async fn update_retention_policy(&self, policy: RetentionPolicy) -> Result<usize> {
+        let claude_dir = self.storage.claude_dir.clone();
+        let paths = CheckpointPaths::new(&claude_dir, &self.project_id, &self.session_id);
+
+        let mut timeline = self.timeline.write().await;
+        timeline.retention_policy = policy;
+        self.storage
+            .save_timeline(&paths.timeline_file, &timeline)?;
+
+        let removed = self
+            .storage
+            .apply_retention_policy(&self.project_id, &self.session_id)?;
+        if removed > 0 {
+            *timeline = self.storage.load_timeline(&paths.timeline_file)?;
+        }
+
+        Ok(removed)
+    }
+
     /// Get files modified since a given timestamp
     pub async fn get_files_modified_since(&self, since: DateTime<Utc>) -> Vec<PathBuf> {
         let tracker = self.file_tracker.read().await;
@@ -785,3 +1762,674 @@ impl CheckpointManager {
             .max()
     }
 }
+
+/// Compile the Bash patterns of a saved timeline, skipping any that are invalid
+fn compile_bash_patterns(patterns: &[String]) -> Vec<regex::Regex> {
+    patterns
+        .iter()
+        .filter_map(|p| match regex::Regex::new(p) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                log::warn!("Ignoring invalid Bash pattern {:?}: {}", p, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Identifiers tying a recorded message to entries of Claude's session transcript
+///
+/// A transcript entry's own `uuid`, the API message ID shared by an assistant
+/// message and its transcript entries, and the tool use IDs a user message
+/// answers, each tagged with its kind.
+fn transcript_keys(line: &str) -> Vec<String> {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+        return Vec::new();
+    };
+    let mut keys = Vec::new();
+    if let Some(uuid) = value.get("uuid").and_then(|v| v.as_str()) {
+        keys.push(format!("uuid:{}", uuid));
+    }
+    let message = value.get("message");
+    if let Some(id) = message.and_then(|m| m.get("id")).and_then(|v| v.as_str()) {
+        keys.push(format!("message:{}", id));
+    }
+    if let Some(content) = message
+        .and_then(|m| m.get("content"))
+        .and_then(|c| c.as_array())
+    {
+        keys.extend(
+            content
+                .iter()
+                .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("tool_result"))
+                .filter_map(|block| block.get("tool_use_id").and_then(|v| v.as_str()))
+                .map(|id| format!("tool_result:{}", id)),
+        );
+    }
+    keys
+}
+
+/// Matches project-relative paths against a path, directory or glob
+pub(super) struct PathMatcher {
+    prefix: PathBuf,
+    glob: Option<glob::Pattern>,
+}
+
+impl PathMatcher {
+    pub(super) fn new(pattern: &str, project_path: &Path) -> Result<Self> {
+        // Accept absolute paths inside the project as well
+        let relative = Path::new(pattern)
+            .strip_prefix(project_path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| pattern.to_string());
+        let relative = relative
+            .trim_start_matches("./")
+            .trim_end_matches('/')
+            .to_string();
+
+        let glob = if relative.contains(['*', '?', '[']) {
+            Some(
+                glob::Pattern::new(&relative)
+                    .with_context(|| format!("Invalid pattern: {}", pattern))?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Self {
+            prefix: PathBuf::from(relative),
+            glob,
+        })
+    }
+
+    pub(super) fn matches(&self, path: &Path) -> bool {
+        match &self.glob {
+            Some(glob) => glob.matches_path_with(
+                path,
+                glob::MatchOptions {
+                    require_literal_separator: true,
+                    ..Default::default()
+                },
+            ),
+            None => path.starts_with(&self.prefix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::test_support::make_manager;
+
+    #[tokio::test]
+    async fn test_bash_tool_result_tracks_changed_files() {
+        let (_temp_dir, project_path, manager) = make_manager(&[("untouched.txt", "same")]).await;
+
+        let tool_use = serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{
+                "type": "tool_use",
+                "id": "toolu_1",
+                "name": "Bash",
+                "input": {"command": "cat untouched.txt > created.txt"}
+            }]}
+        });
+        manager
+            .process_stream_message(&tool_use.to_string())
+            .await
+            .unwrap();
+
+        fs::write(project_path.join("created.txt"), "same").unwrap();
+
+        let tool_result = serde_json::json!({
+            "type": "user",
+            "message": {"content": [{"type": "tool_result", "tool_use_id": "toolu_1"}]}
+        });
+        manager
+            .process_stream_message(&tool_result.to_string())
+            .await
+            .unwrap();
+
+        let tracker = manager.file_tracker.read().await;
+        let tracked: Vec<&PathBuf> = tracker.tracked_files.keys().collect();
+        assert_eq!(tracked, vec![&PathBuf::from("created.txt")]);
+        drop(tracker);
+
+        // A Bash command replayed after it ran has no reliable baseline, so
+        // its changes are not attributed to it
+        let replayed = tool_use.to_string().replace("toolu_1", "toolu_2");
+        manager.track_message(replayed).await.unwrap();
+        fs::write(project_path.join("replayed.txt"), "late").unwrap();
+        manager
+            .track_message(tool_result.to_string().replace("toolu_1", "toolu_2"))
+            .await
+            .unwrap();
+        assert!(!manager
+            .file_tracker
+            .read()
+            .await
+            .tracked_files
+            .contains_key(Path::new("replayed.txt")));
+
+        // Auto-checkpointing is disabled by default
+        assert!(
+            !manager
+                .should_auto_checkpoint(&tool_result.to_string())
+                .await
+        );
+        manager
+            .update_settings(true, CheckpointStrategy::Smart)
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .should_auto_checkpoint(&tool_result.to_string())
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_process_stream_message_follows_strategy() {
+        let (_temp_dir, _, manager) = make_manager(&[("a.txt", "a")]).await;
+        manager
+            .update_settings(true, CheckpointStrategy::PerToolUse)
+            .await
+            .unwrap();
+
+        let text = serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{"type": "text", "text": "Editing"}]}
+        });
+        let tool_use = serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{
+                "type": "tool_use",
+                "id": "toolu_1",
+                "name": "Edit",
+                "input": {"file_path": "a.txt"}
+            }]}
+        });
+
+        assert!(manager
+            .process_stream_message(&text.to_string())
+            .await
+            .unwrap()
+            .is_none());
+        let result = manager
+            .process_stream_message(&tool_use.to_string())
+            .await
+            .unwrap()
+            .expect("tool use should trigger a checkpoint");
+
+        assert_eq!(result.checkpoint.message_index, 1);
+        assert_eq!(manager.list_checkpoints().await.len(), 1);
+        assert_eq!(manager.current_messages.read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_per_prompt_ignores_tool_results() {
+        let (_temp_dir, _, manager) = make_manager(&[]).await;
+        manager
+            .update_settings(true, CheckpointStrategy::PerPrompt)
+            .await
+            .unwrap();
+
+        let prompt = serde_json::json!({
+            "type": "user",
+            "message": {"role": "user", "content": "Fix the tests"}
+        });
+        let text_blocks = serde_json::json!({
+            "type": "user",
+            "message": {"role": "user", "content": [{"type": "text", "text": "Fix the tests"}]}
+        });
+        let tool_result = serde_json::json!({
+            "type": "user",
+            "message": {"role": "user", "content": [{
+                "type": "tool_result",
+                "tool_use_id": "toolu_1",
+                "content": "ok"
+            }]}
+        });
+        assert!(manager.should_auto_checkpoint(&prompt.to_string()).await);
+        assert!(
+            manager
+                .should_auto_checkpoint(&text_blocks.to_string())
+                .await
+        );
+        assert!(
+            !manager
+                .should_auto_checkpoint(&tool_result.to_string())
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_threshold_strategies() {
+        let (_temp_dir, _, manager) = make_manager(&[]).await;
+        manager
+            .update_strategy_thresholds(StrategyThresholds {
+                interval_minutes: 10,
+                changed_lines: 3,
+                bash_patterns: vec![r"^git\s+reset".to_string()],
+            })
+            .await
+            .unwrap();
+        assert!(manager
+            .update_strategy_thresholds(StrategyThresholds {
+                bash_patterns: vec!["(".to_string()],
+                ..Default::default()
+            })
+            .await
+            .is_err());
+
+        let bash = |command: &str| {
+            serde_json::json!({
+                "type": "assistant",
+                "message": {"content": [{
+                    "type": "tool_use",
+                    "id": "toolu_bash",
+                    "name": "Bash",
+                    "input": {"command": command}
+                }]}
+            })
+            .to_string()
+        };
+        manager
+            .update_settings(true, CheckpointStrategy::BashPattern)
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .should_auto_checkpoint(&bash("git reset --hard"))
+                .await
+        );
+        assert!(!manager.should_auto_checkpoint(&bash("git status")).await);
+
+        manager
+            .update_settings(true, CheckpointStrategy::ChangedLines)
+            .await
+            .unwrap();
+        let edit = serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [{
+                "type": "tool_use",
+                "id": "toolu_edit",
+                "name": "Edit",
+                "input": {"file_path": "a.txt", "old_string": "a", "new_string": "b\nc"}
+            }]}
+        })
+        .to_string();
+        manager.track_message(edit.clone()).await.unwrap();
+        assert!(manager.should_auto_checkpoint(&edit).await);
+
+        // Only time between messages close together counts as activity
+        manager
+            .update_settings(true, CheckpointStrategy::Interval)
+            .await
+            .unwrap();
+        let start = Utc::now();
+        manager.record_activity(start).await;
+        manager
+            .record_activity(start + chrono::Duration::minutes(30))
+            .await;
+        assert!(!manager.should_auto_checkpoint(&edit).await);
+        for minute in 1..=10 {
+            manager
+                .record_activity(start + chrono::Duration::minutes(30 + minute * 2))
+                .await;
+        }
+        assert!(manager.should_auto_checkpoint(&edit).await);
+
+        manager.create_checkpoint(None, None).await.unwrap();
+        assert!(!manager.should_auto_checkpoint(&edit).await);
+    }
+
+    #[tokio::test]
+    async fn test_merge_checkpoints_three_way() {
+        let (_temp_dir, project_path, manager) = make_manager(&[("a.txt", "1\n2\n3\n")]).await;
+        let checkpoint = |description: &str, parent: Option<&str>| {
+            let manager = &manager;
+            let description = description.to_string();
+            let parent = parent.map(String::from);
+            async move {
+                manager
+                    .create_checkpoint(Some(description), parent)
+                    .await
+                    .unwrap()
+                    .checkpoint
+                    .id
+            }
+        };
+
+        let base = checkpoint("base", None).await;
+        fs::write(project_path.join("a.txt"), "one\n2\n3\n").unwrap();
+        let ours = checkpoint("ours", Some(&base)).await;
+        fs::write(project_path.join("a.txt"), "1\n2\nthree\n").unwrap();
+        fs::write(project_path.join("b.txt"), "new").unwrap();
+        let theirs = checkpoint("theirs", Some(&base)).await;
+
+        let result = manager
+            .merge_checkpoints(&ours, &theirs, None)
+            .await
+            .unwrap();
+        assert_eq!(result.base_checkpoint_id.as_deref(), Some(base.as_str()));
+        assert!(result.conflicts.is_empty());
+        assert_eq!(
+            result.merged_files,
+            vec![PathBuf::from("a.txt"), PathBuf::from("b.txt")]
+        );
+
+        let merge = result.checkpoint.unwrap();
+        assert_eq!(merge.parent_checkpoint_id.as_deref(), Some(ours.as_str()));
+        assert_eq!(merge.merge_parent_ids, vec![theirs.clone()]);
+        let (_, states) = manager.load_file_states(&merge.id).unwrap();
+        assert_eq!(states[Path::new("a.txt")].content, b"one\n2\nthree\n");
+        assert_eq!(states[Path::new("b.txt")].content, b"new");
+        assert_eq!(
+            manager
+                .get_timeline()
+                .await
+                .current_checkpoint_id
+                .as_deref(),
+            Some(theirs.as_str())
+        );
+
+        // Both branches changing the same line is reported with markers
+        fs::write(project_path.join("a.txt"), "uno\n2\n3\n").unwrap();
+        let other = checkpoint("other", Some(&base)).await;
+        let result = manager
+            .merge_checkpoints(&ours, &other, None)
+            .await
+            .unwrap();
+        assert!(result.checkpoint.is_none());
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(result.conflicts[0].kind, MergeConflictKind::Content);
+        assert_eq!(
+            result.conflicts[0].content.as_deref(),
+            Some("<<<<<<< ours\none\n=======\nuno\n>>>>>>> other\n2\n3\n")
+        );
+
+        // Merging an ancestor is refused
+        assert!(manager.merge_checkpoints(&ours, &base, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_restore_transcript_to_checkpoint() {
+        let (temp_dir, _, manager) = make_manager(&[]).await;
+        let claude_dir = temp_dir.path().join("claude");
+
+        // Streamed output, as recorded by the manager
+        let assistant = |id: &str| {
+            serde_json::json!({"type": "assistant", "session_id": "session", "message": {"id": id, "content": [{"type": "text", "text": id}]}})
+                .to_string()
+        };
+        let tool_result = serde_json::json!({"type": "user", "session_id": "session", "message": {"content": [{"type": "tool_result", "tool_use_id": "tool_1"}]}})
+            .to_string();
+        for line in [assistant("msg_1"), tool_result] {
+            manager.track_message(line).await.unwrap();
+        }
+        let checkpoint = manager
+            .create_checkpoint(None, None)
+            .await
+            .unwrap()
+            .checkpoint;
+        manager.track_message(assistant("msg_2")).await.unwrap();
+
+        // Claude's own transcript of the same conversation
+        let entry = |uuid: &str, parent: Option<&str>, message: serde_json::Value| {
+            serde_json::json!({"type": "user", "uuid": uuid, "parentUuid": parent, "sessionId": "session", "message": message})
+                .to_string()
+        };
+        let transcript = [
+            entry(
+                "u1",
+                None,
+                serde_json::json!({"role": "user", "content": "go"}),
+            ),
+            entry(
+                "u2",
+                Some("u1"),
+                serde_json::json!({"id": "msg_1", "content": []}),
+            ),
+            entry(
+                "u3",
+                Some("u2"),
+                serde_json::json!({"content": [{"type": "tool_result", "tool_use_id": "tool_1"}]}),
+            ),
+            entry(
+                "u4",
+                Some("u3"),
+                serde_json::json!({"id": "msg_2", "content": []}),
+            ),
+        ];
+        let session_path = claude_dir.join("projects/project/session.jsonl");
+        let full = transcript.join("\n") + "\n";
+        fs::write(&session_path, &full).unwrap();
+
+        // A copy leaves the transcript alone and points at the new session
+        let copy = manager
+            .restore_transcript(&checkpoint.id, TranscriptRestoreMode::NewSession)
+            .await
+            .unwrap();
+        assert_ne!(copy.session_id, "session");
+        assert_eq!(copy.message_count, 3);
+        assert_eq!(fs::read_to_string(&session_path).unwrap(), full);
+        let copied = fs::read_to_string(
+            claude_dir.join(format!("projects/project/{}.jsonl", copy.session_id)),
+        )
+        .unwrap();
+        for (line, uuid) in copied.lines().zip(["u1", "u2", "u3"]) {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["sessionId"], copy.session_id.as_str());
+            assert_eq!(value["uuid"], uuid);
+        }
+
+        // In place, the transcript is cut after the entry of the last recorded message
+        let restored = manager
+            .restore_transcript(&checkpoint.id, TranscriptRestoreMode::InPlace)
+            .await
+            .unwrap();
+        assert_eq!(restored.session_id, "session");
+        assert_eq!(
+            fs::read_to_string(&session_path).unwrap(),
+            transcript[..3].join("\n") + "\n"
+        );
+        assert_eq!(manager.current_messages.read().await.len(), 2);
+
+        // A transcript without the checkpoint's messages is left alone
+        fs::write(&session_path, &transcript[0]).unwrap();
+        assert!(manager
+            .restore_transcript(&checkpoint.id, TranscriptRestoreMode::InPlace)
+            .await
+            .is_err());
+        assert_eq!(fs::read_to_string(&session_path).unwrap(), transcript[0]);
+    }
+
+    #[tokio::test]
+    async fn test_restore_files_restores_only_matching_paths() {
+        let (_temp_dir, project_path, manager) =
+            make_manager(&[("a.txt", "1"), ("dir/b.txt", "1")]).await;
+        let first = manager.create_checkpoint(None, None).await.unwrap();
+
+        // The second checkpoint only snapshots a.txt
+        fs::write(project_path.join("a.txt"), "2").unwrap();
+        let second = manager.create_checkpoint(None, None).await.unwrap();
+
+        // Local changes made after the last checkpoint
+        fs::write(project_path.join("dir/b.txt"), "local").unwrap();
+        fs::write(project_path.join("dir/new.txt"), "new").unwrap();
+
+        let patterns = vec!["dir".to_string(), "missing/*.rs".to_string()];
+        let result = manager
+            .restore_files(&first.checkpoint.id, &patterns, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.conflicts,
+            vec![PathBuf::from("dir/b.txt"), PathBuf::from("dir/new.txt")]
+        );
+        assert!(result.restored_files.is_empty());
+        assert_eq!(result.unmatched_patterns, vec!["missing/*.rs".to_string()]);
+        assert_eq!(
+            fs::read_to_string(project_path.join("dir/b.txt")).unwrap(),
+            "local"
+        );
+
+        let result = manager
+            .restore_files(&first.checkpoint.id, &patterns, true)
+            .await
+            .unwrap();
+        assert_eq!(result.restored_files, vec![PathBuf::from("dir/b.txt")]);
+        assert_eq!(result.deleted_files, vec![PathBuf::from("dir/new.txt")]);
+        assert_eq!(
+            fs::read_to_string(project_path.join("dir/b.txt")).unwrap(),
+            "1"
+        );
+        assert!(!project_path.join("dir/new.txt").exists());
+
+        // Everything outside the patterns and the timeline position stay put
+        assert_eq!(fs::read_to_string(project_path.join("a.txt")).unwrap(), "2");
+        assert_eq!(
+            manager.get_timeline().await.current_checkpoint_id,
+            Some(second.checkpoint.id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_checkpoint_keeps_inherited_files() {
+        let (_temp_dir, project_path, manager) =
+            make_manager(&[("a.txt", "1"), ("b.txt", "1")]).await;
+        manager.create_checkpoint(None, None).await.unwrap();
+
+        // The second checkpoint only holds a.txt; b.txt comes from its parent
+        fs::write(project_path.join("a.txt"), "2").unwrap();
+        let second = manager.create_checkpoint(None, None).await.unwrap();
+        assert_eq!(second.files_processed, 1);
+
+        fs::write(project_path.join("a.txt"), "3").unwrap();
+        fs::remove_file(project_path.join("b.txt")).unwrap();
+        manager
+            .restore_checkpoint(&second.checkpoint.id)
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(project_path.join("a.txt")).unwrap(), "2");
+        assert_eq!(fs::read_to_string(project_path.join("b.txt")).unwrap(), "1");
+    }
+
+    #[tokio::test]
+    async fn test_pruned_root_files_survive_in_descendants() {
+        let (_temp_dir, project_path, manager) =
+            make_manager(&[("a.txt", "1"), ("b.txt", "1")]).await;
+        let root = manager.create_checkpoint(None, None).await.unwrap();
+        fs::write(project_path.join("a.txt"), "2").unwrap();
+        manager.create_checkpoint(None, None).await.unwrap();
+        fs::write(project_path.join("a.txt"), "3").unwrap();
+        let leaf = manager.create_checkpoint(None, None).await.unwrap();
+
+        // A cap nothing fits under removes everything but the current checkpoint,
+        // including the root that holds the only snapshot of b.txt
+        let removed = manager
+            .update_retention_policy(RetentionPolicy {
+                max_project_bytes: Some(1),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        let timeline = manager.get_timeline().await;
+        assert!(timeline.find_checkpoint(&root.checkpoint.id).is_none());
+        assert_eq!(
+            timeline.root_node.unwrap().checkpoint.id,
+            leaf.checkpoint.id
+        );
+
+        fs::write(project_path.join("a.txt"), "4").unwrap();
+        fs::remove_file(project_path.join("b.txt")).unwrap();
+        manager
+            .restore_checkpoint(&leaf.checkpoint.id)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(project_path.join("a.txt")).unwrap(), "3");
+        assert_eq!(fs::read_to_string(project_path.join("b.txt")).unwrap(), "1");
+
+        let report = manager
+            .storage
+            .verify_timeline("project", "session", false)
+            .unwrap();
+        assert!(report.is_healthy(), "{:?}", report.issues);
+    }
+
+    #[tokio::test]
+    async fn test_plan_restore_includes_inherited_files() {
+        let (_temp_dir, project_path, manager) =
+            make_manager(&[("a.txt", "1"), ("dir/b.txt", "1")]).await;
+        manager.create_checkpoint(None, None).await.unwrap();
+
+        // Only a.txt changes, so dir/b.txt is held by the parent checkpoint
+        fs::write(project_path.join("a.txt"), "2").unwrap();
+        let second = manager.create_checkpoint(None, None).await.unwrap();
+        fs::write(project_path.join("a.txt"), "3").unwrap();
+
+        let plan = manager.plan_restore(&second.checkpoint.id).await.unwrap();
+        let writes: Vec<_> = plan
+            .writes
+            .iter()
+            .map(|w| (w.path.clone(), w.unchanged))
+            .collect();
+        assert_eq!(
+            writes,
+            vec![
+                (PathBuf::from("a.txt"), false),
+                (PathBuf::from("dir/b.txt"), true),
+            ]
+        );
+        assert!(plan.deletions.is_empty());
+        assert!(plan.removed_dirs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plan_restore_matches_restore() {
+        let (_temp_dir, project_path, manager) =
+            make_manager(&[("a.txt", "1"), ("dir/b.txt", "1")]).await;
+        let checkpoint = manager.create_checkpoint(None, None).await.unwrap();
+
+        // Changed outside Claude, plus a new directory
+        fs::write(project_path.join("a.txt"), "2").unwrap();
+        fs::create_dir_all(project_path.join("other")).unwrap();
+        fs::write(project_path.join("other/new.txt"), "new").unwrap();
+
+        let plan = manager
+            .plan_restore(&checkpoint.checkpoint.id)
+            .await
+            .unwrap();
+        let mut writes: Vec<_> = plan
+            .writes
+            .iter()
+            .map(|w| (w.path.clone(), w.unchanged, w.would_lose_local_changes))
+            .collect();
+        writes.sort();
+        assert_eq!(
+            writes,
+            vec![
+                (PathBuf::from("a.txt"), false, true),
+                (PathBuf::from("dir/b.txt"), true, false),
+            ]
+        );
+        assert_eq!(plan.deletions.len(), 1);
+        assert_eq!(plan.deletions[0].path, PathBuf::from("other/new.txt"));
+        // A file created since the checkpoint is lost by the restore
+        assert!(plan.deletions[0].would_lose_local_changes);
+        assert_eq!(plan.removed_dirs, vec![PathBuf::from("other")]);
+
+        // Planning leaves the tree alone; restoring does what was planned
+        assert_eq!(fs::read_to_string(project_path.join("a.txt")).unwrap(), "2");
+        manager
+            .restore_checkpoint(&checkpoint.checkpoint.id)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(project_path.join("a.txt")).unwrap(), "1");
+        assert!(!project_path.join("other").exists());
+        assert!(project_path.join("dir").exists());
+    }
+}
@@ -1,11 +1,23 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+pub mod archive;
+pub mod backend;
+pub mod content_store;
+pub mod diff;
+pub mod git_backend;
+pub mod history;
+pub mod journal;
 pub mod manager;
+pub mod search;
 pub mod state;
 pub mod storage;
+pub mod walker;
+
+#[cfg(test)]
+mod test_support;
 
 /// Represents a checkpoint in the session timeline
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +37,16 @@ pub struct Checkpoint {
     pub description: Option<String>,
     /// Parent checkpoint ID for fork tracking
     pub parent_checkpoint_id: Option<String>,
+    /// Further parents of a merge checkpoint. The timeline tree and file
+    /// snapshots follow `parent_checkpoint_id`; these only record history.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merge_parent_ids: Vec<String>,
+    /// User-assigned tags
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Pinned milestone; never removed by the retention policy
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub milestone: bool,
     /// Metadata about the checkpoint
     pub metadata: CheckpointMetadata,
 }
@@ -53,8 +75,8 @@ pub struct FileSnapshot {
     pub checkpoint_id: String,
     /// Relative path from project root
     pub file_path: PathBuf,
-    /// Full content of the file (will be compressed)
-    pub content: String,
+    /// Raw bytes of the file (will be compressed)
+    pub content: Vec<u8>,
     /// SHA-256 hash for integrity verification
     pub hash: String,
     /// Whether this file was deleted at this checkpoint
@@ -93,6 +115,29 @@ pub struct SessionTimeline {
     pub checkpoint_strategy: CheckpointStrategy,
     /// Total number of checkpoints in timeline
     pub total_checkpoints: usize,
+    /// Rules for pruning old checkpoints
+    #[serde(default)]
+    pub retention_policy: RetentionPolicy,
+    /// Thresholds used by the interval, changed-lines and Bash-pattern strategies
+    #[serde(default)]
+    pub strategy_thresholds: StrategyThresholds,
+}
+
+/// Retention rules applied after each checkpoint is created.
+///
+/// Checkpoints with a description, fork points and the current checkpoint are
+/// always kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionPolicy {
+    /// Keep every checkpoint from the last N hours. Older checkpoints are
+    /// thinned out; `None` keeps everything.
+    pub keep_all_hours: Option<u32>,
+    /// Keep the newest checkpoint of each day older than `keep_all_hours`
+    pub keep_daily: bool,
+    /// Cap on the on-disk size of all timelines of the project, in bytes.
+    /// Oldest checkpoints of the session are removed until it is met.
+    pub max_project_bytes: Option<u64>,
 }
 
 /// Strategy for automatic checkpoint creation
@@ -107,6 +152,40 @@ pub enum CheckpointStrategy {
     PerToolUse,
     /// Create checkpoint after destructive operations
     Smart,
+    /// Create checkpoint after every `interval_minutes` of session activity
+    Interval,
+    /// Create checkpoint once edits have changed `changed_lines` lines
+    ChangedLines,
+    /// Create checkpoint before Bash commands matching `bash_patterns`
+    BashPattern,
+}
+
+/// Thresholds for the interval, changed-lines and Bash-pattern strategies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StrategyThresholds {
+    /// Minutes of activity between checkpoints. Gaps of more than a few
+    /// minutes without output do not count as activity.
+    pub interval_minutes: u32,
+    /// Lines added or removed by Edit, MultiEdit and Write tools since the
+    /// last checkpoint
+    pub changed_lines: usize,
+    /// Regular expressions matched against Bash commands
+    pub bash_patterns: Vec<String>,
+}
+
+impl Default for StrategyThresholds {
+    fn default() -> Self {
+        Self {
+            interval_minutes: 15,
+            changed_lines: 200,
+            bash_patterns: vec![
+                r"\brm\s".to_string(),
+                r"\bgit\s+(reset|clean|checkout)\b".to_string(),
+                r"\bmigrat".to_string(),
+            ],
+        }
+    }
 }
 
 /// Tracks the state of files for checkpointing
@@ -117,7 +196,7 @@ pub struct FileTracker {
 }
 
 /// State of a tracked file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileState {
     /// Last known hash of the file
     pub last_hash: String,
@@ -138,10 +217,142 @@ pub struct CheckpointResult {
     pub files_processed: usize,
     /// Any warnings during the operation
     pub warnings: Vec<String>,
+    /// Session transcript rewritten by a restore, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<TranscriptRestore>,
+}
+
+/// How a restore brings the Claude session transcript back to a checkpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptRestoreMode {
+    /// Truncate the session's own transcript to the checkpoint
+    InPlace,
+    /// Write the conversation up to the checkpoint to a new session
+    NewSession,
+}
+
+/// A session transcript written from a checkpoint's conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptRestore {
+    /// Session to resume to continue from the checkpoint
+    pub session_id: String,
+    /// Number of messages in the transcript
+    pub message_count: usize,
+}
+
+/// What restoring a checkpoint would do, without touching the working tree
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestorePlan {
+    /// Checkpoint the plan restores
+    pub checkpoint_id: String,
+    /// Files that would be written with their content at the checkpoint
+    pub writes: Vec<PlannedFileChange>,
+    /// Files that would be deleted
+    pub deletions: Vec<PlannedFileChange>,
+    /// Existing files whose permissions would change
+    pub permission_changes: Vec<PlannedPermissionChange>,
+    /// Directories that would be removed because they end up empty
+    pub removed_dirs: Vec<PathBuf>,
+    /// Any warnings the restore would report
+    pub warnings: Vec<String>,
+}
+
+/// A file write or deletion planned by a restore
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFileChange {
+    /// Relative path from project root
+    pub path: PathBuf,
+    /// Size in bytes after a write, or of the file being deleted
+    pub size: u64,
+    /// Whether the file exists on disk
+    pub exists: bool,
+    /// Whether the file on disk already has the content being written
+    pub unchanged: bool,
+    /// Whether the file was modified outside Claude since it was last
+    /// tracked, so the restore would lose local changes
+    pub would_lose_local_changes: bool,
+}
+
+/// A permission change planned by a restore
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedPermissionChange {
+    /// Relative path from project root
+    pub path: PathBuf,
+    /// Current Unix mode
+    pub from: u32,
+    /// Unix mode at the checkpoint
+    pub to: u32,
+}
+
+/// Result of restoring selected files from a checkpoint
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialRestoreResult {
+    /// Checkpoint the files were restored from
+    pub checkpoint_id: String,
+    /// Files written with their content at the checkpoint
+    pub restored_files: Vec<PathBuf>,
+    /// Files deleted because they did not exist at the checkpoint
+    pub deleted_files: Vec<PathBuf>,
+    /// Files changed on disk since their last snapshot. They are left
+    /// untouched unless the restore is forced.
+    pub conflicts: Vec<PathBuf>,
+    /// Requested paths or globs that matched nothing
+    pub unmatched_patterns: Vec<String>,
+    /// Any warnings during the operation
+    pub warnings: Vec<String>,
+}
+
+/// Result of merging two timeline branches
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeResult {
+    /// Checkpoint merged into; the merge checkpoint is created as its child
+    pub ours_checkpoint_id: String,
+    /// Checkpoint merged from
+    pub theirs_checkpoint_id: String,
+    /// Nearest common ancestor of the two checkpoints
+    pub base_checkpoint_id: Option<String>,
+    /// The merge checkpoint, created only when there are no conflicts
+    pub checkpoint: Option<Checkpoint>,
+    /// Files whose merged state differs from `ours`
+    pub merged_files: Vec<PathBuf>,
+    /// Files changed differently on both branches
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A file that could not be merged automatically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    /// File path relative to the project root
+    pub path: PathBuf,
+    /// Why the file could not be merged
+    pub kind: MergeConflictKind,
+    /// Merged content with conflict markers, for text conflicts
+    pub content: Option<String>,
+}
+
+/// Kinds of merge conflicts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeConflictKind {
+    /// Overlapping changes to the same lines
+    Content,
+    /// Deleted on one branch and modified on the other
+    DeleteModify,
+    /// Binary content changed on both branches
+    Binary,
 }
 
 /// Diff between two checkpoints
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CheckpointDiff {
     /// Source checkpoint ID
     pub from_checkpoint_id: String,
@@ -153,12 +364,15 @@ pub struct CheckpointDiff {
     pub added_files: Vec<PathBuf>,
     /// Files that were deleted
     pub deleted_files: Vec<PathBuf>,
+    /// Files that were moved without content changes
+    pub renamed_files: Vec<FileRename>,
     /// Token usage difference
     pub token_delta: i64,
 }
 
 /// Diff for a single file
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FileDiff {
     /// File path
     pub path: PathBuf,
@@ -166,16 +380,99 @@ pub struct FileDiff {
     pub additions: usize,
     /// Number of deletions
     pub deletions: usize,
+    /// Whether either version of the file is binary
+    pub is_binary: bool,
     /// Unified diff content (optional)
     pub diff_content: Option<String>,
 }
 
+/// A file detected as renamed between two checkpoints
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRename {
+    /// Path in the source checkpoint
+    pub from_path: PathBuf,
+    /// Path in the target checkpoint
+    pub to_path: PathBuf,
+}
+
+/// Result of verifying a session's checkpoint storage
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineVerifyReport {
+    /// Session that was verified
+    pub session_id: String,
+    /// Number of checkpoints reachable from the timeline root
+    pub checkpoints_checked: usize,
+    /// Number of distinct content blobs checked
+    pub blobs_checked: usize,
+    /// Problems found in reachable checkpoints
+    pub issues: Vec<TimelineIssue>,
+    /// Checkpoint directories on disk not reachable from the root node
+    pub orphan_checkpoints: Vec<String>,
+    /// Whether repair mode was requested
+    pub repaired: bool,
+    /// Checkpoints removed from the timeline by repair
+    pub pruned_checkpoints: Vec<String>,
+}
+
+impl TimelineVerifyReport {
+    /// Whether the timeline has no issues and no orphans
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty() && self.orphan_checkpoints.is_empty()
+    }
+}
+
+/// A single problem found while verifying checkpoint storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineIssue {
+    /// Checkpoint the issue belongs to, if any
+    pub checkpoint_id: Option<String>,
+    /// Kind of problem
+    pub kind: TimelineIssueKind,
+    /// Affected file, relative to the project root or the timeline directory
+    pub path: Option<PathBuf>,
+    /// Human-readable description
+    pub detail: String,
+}
+
+/// Kinds of checkpoint storage problems
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineIssueKind {
+    /// metadata.json is missing or unreadable
+    MissingMetadata,
+    /// messages.jsonl is missing or cannot be decompressed
+    MissingMessages,
+    /// A file reference listed by the timeline node has no refs entry
+    MissingReference,
+    /// A refs entry cannot be parsed
+    InvalidReference,
+    /// A referenced content pool blob does not exist
+    MissingContent,
+    /// A content pool blob does not decompress to content matching its hash
+    CorruptContent,
+    /// total_checkpoints does not match the number of nodes in the tree
+    CountMismatch,
+}
+
 impl Default for CheckpointStrategy {
     fn default() -> Self {
         CheckpointStrategy::Smart
     }
 }
 
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_all_hours: None,
+            keep_daily: true,
+            max_project_bytes: None,
+        }
+    }
+}
+
 impl SessionTimeline {
     /// Create a new empty timeline
     pub fn new(session_id: String) -> Self {
@@ -186,6 +483,8 @@ impl SessionTimeline {
             auto_checkpoint_enabled: false,
             checkpoint_strategy: CheckpointStrategy::default(),
             total_checkpoints: 0,
+            retention_policy: RetentionPolicy::default(),
+            strategy_thresholds: StrategyThresholds::default(),
         }
     }
 
@@ -196,6 +495,26 @@ impl SessionTimeline {
             .and_then(|root| Self::find_in_tree(root, checkpoint_id))
     }
 
+    /// Find a checkpoint by ID in the timeline tree for modification
+    pub fn find_checkpoint_mut(&mut self, checkpoint_id: &str) -> Option<&mut TimelineNode> {
+        self.root_node
+            .as_mut()
+            .and_then(|root| Self::find_in_tree_mut(root, checkpoint_id))
+    }
+
+    fn find_in_tree_mut<'a>(
+        node: &'a mut TimelineNode,
+        checkpoint_id: &str,
+    ) -> Option<&'a mut TimelineNode> {
+        if node.checkpoint.id == checkpoint_id {
+            return Some(node);
+        }
+
+        node.children
+            .iter_mut()
+            .find_map(|child| Self::find_in_tree_mut(child, checkpoint_id))
+    }
+
     fn find_in_tree<'a>(node: &'a TimelineNode, checkpoint_id: &str) -> Option<&'a TimelineNode> {
         if node.checkpoint.id == checkpoint_id {
             return Some(node);
@@ -211,28 +530,48 @@ impl SessionTimeline {
     }
 }
 
+/// Name of the file holding a session's saved in-memory state
+pub const SESSION_STATE_FILE: &str = "session_state.json.zst";
+
 /// Checkpoint storage paths
 pub struct CheckpointPaths {
     pub timeline_file: PathBuf,
     pub checkpoints_dir: PathBuf,
     pub files_dir: PathBuf,
+    /// Content store shared by all sessions of the project
+    pub content_store_dir: PathBuf,
 }
 
 impl CheckpointPaths {
     pub fn new(claude_dir: &PathBuf, project_id: &str, session_id: &str) -> Self {
-        let base_dir = claude_dir
+        let timelines_dir = claude_dir
             .join("projects")
             .join(project_id)
-            .join(".timelines")
-            .join(session_id);
+            .join(".timelines");
+        let base_dir = timelines_dir.join(session_id);
 
         Self {
             timeline_file: base_dir.join("timeline.json"),
             checkpoints_dir: base_dir.join("checkpoints"),
             files_dir: base_dir.join("files"),
+            content_store_dir: timelines_dir.join(content_store::CONTENT_STORE_DIR),
         }
     }
 
+    /// Content store directory of a project, without reference to a session
+    pub fn project_content_store_dir(claude_dir: &Path, project_id: &str) -> PathBuf {
+        claude_dir
+            .join("projects")
+            .join(project_id)
+            .join(".timelines")
+            .join(content_store::CONTENT_STORE_DIR)
+    }
+
+    /// In-memory session state saved when a session's manager is evicted
+    pub fn session_state_file(&self) -> PathBuf {
+        self.timeline_file.with_file_name(SESSION_STATE_FILE)
+    }
+
     pub fn checkpoint_dir(&self, checkpoint_id: &str) -> PathBuf {
         self.checkpoints_dir.join(checkpoint_id)
     }
@@ -247,8 +586,8 @@ impl CheckpointPaths {
 
     #[allow(dead_code)]
     pub fn file_snapshot_path(&self, _checkpoint_id: &str, file_hash: &str) -> PathBuf {
-        // In content-addressable storage, files are stored by hash in the content pool
-        self.files_dir.join("content_pool").join(file_hash)
+        // In content-addressable storage, files are stored by hash in the project's content store
+        content_store::blob_path(&self.content_store_dir, file_hash)
     }
 
     #[allow(dead_code)]
@@ -0,0 +1,284 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::manager::PathMatcher;
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, CheckpointPaths, SessionTimeline, TimelineNode};
+
+/// Criteria for finding checkpoints across a project's timelines.
+/// Every given criterion must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CheckpointQuery {
+    /// Tags the checkpoint must all carry, compared case-insensitively
+    pub tags: Vec<String>,
+    /// Case-insensitive text to find in the prompt that led to the checkpoint
+    pub text: Option<String>,
+    /// Path, directory or glob of a file snapshotted by the checkpoint
+    pub path: Option<String>,
+    /// Earliest checkpoint time, inclusive
+    pub since: Option<DateTime<Utc>>,
+    /// Latest checkpoint time, inclusive
+    pub until: Option<DateTime<Utc>>,
+    /// Only return milestone checkpoints
+    pub milestones_only: bool,
+}
+
+/// A checkpoint found by a search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointReference {
+    pub session_id: String,
+    pub checkpoint: Checkpoint,
+    /// Snapshotted files matching the path criterion
+    pub matched_files: Vec<PathBuf>,
+}
+
+impl CheckpointStorage {
+    /// Search every session timeline of a project, newest checkpoints first
+    pub fn search_checkpoints(
+        &self,
+        project_id: &str,
+        query: &CheckpointQuery,
+    ) -> Result<Vec<CheckpointReference>> {
+        let path_matcher = query
+            .path
+            .as_deref()
+            .map(|p| PathMatcher::new(p, Path::new("")))
+            .transpose()?;
+        let tags: Vec<String> = query.tags.iter().map(|t| t.trim().to_lowercase()).collect();
+        let text = query.text.as_ref().map(|t| t.to_lowercase());
+
+        let mut results = Vec::new();
+        for (session_id, paths, timeline) in self.load_project_timelines(project_id)? {
+            let mut nodes = Vec::new();
+            if let Some(root) = &timeline.root_node {
+                collect_nodes(root, &mut nodes);
+            }
+
+            for node in nodes {
+                let checkpoint = &node.checkpoint;
+                if query.milestones_only && !checkpoint.milestone {
+                    continue;
+                }
+                if query
+                    .since
+                    .is_some_and(|since| checkpoint.timestamp < since)
+                    || query
+                        .until
+                        .is_some_and(|until| checkpoint.timestamp > until)
+                {
+                    continue;
+                }
+                if !tags
+                    .iter()
+                    .all(|tag| checkpoint.tags.iter().any(|t| t.to_lowercase() == *tag))
+                {
+                    continue;
+                }
+                if let Some(text) = &text {
+                    if !checkpoint
+                        .metadata
+                        .user_prompt
+                        .to_lowercase()
+                        .contains(text)
+                    {
+                        continue;
+                    }
+                }
+
+                let matched_files = match &path_matcher {
+                    Some(matcher) => {
+                        let files: Vec<PathBuf> =
+                            referenced_paths(&paths.files_dir.join("refs").join(&checkpoint.id))
+                                .into_iter()
+                                .filter(|p| matcher.matches(p))
+                                .collect();
+                        if files.is_empty() {
+                            continue;
+                        }
+                        files
+                    }
+                    None => Vec::new(),
+                };
+
+                results.push(CheckpointReference {
+                    session_id: session_id.clone(),
+                    checkpoint: checkpoint.clone(),
+                    matched_files,
+                });
+            }
+        }
+
+        results.sort_by_key(|r| std::cmp::Reverse(r.checkpoint.timestamp));
+        Ok(results)
+    }
+
+    /// Load the timeline of every session of a project, skipping unreadable ones
+    pub(super) fn load_project_timelines(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<(String, CheckpointPaths, SessionTimeline)>> {
+        let timelines_dir = self
+            .claude_dir
+            .join("projects")
+            .join(project_id)
+            .join(".timelines");
+        if !timelines_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut timelines = Vec::new();
+        for entry in fs::read_dir(&timelines_dir)? {
+            let session_dir = entry?.path();
+            let Some(session_id) = session_dir.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+            if !paths.timeline_file.exists() {
+                continue;
+            }
+            match self.load_timeline(&paths.timeline_file) {
+                Ok(timeline) => timelines.push((session_id.to_string(), paths, timeline)),
+                Err(e) => log::warn!("Skipping unreadable timeline for {}: {}", session_id, e),
+            }
+        }
+        Ok(timelines)
+    }
+}
+
+/// Paths of the files a checkpoint snapshotted, from its file references
+fn referenced_paths(refs_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(refs_dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .filter_map(|r| r["path"].as_str().map(PathBuf::from))
+        .collect();
+    paths.sort();
+    paths
+}
+
+pub(super) fn collect_nodes<'a>(node: &'a TimelineNode, nodes: &mut Vec<&'a TimelineNode>) {
+    nodes.push(node);
+    for child in &node.children {
+        collect_nodes(child, nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::manager::CheckpointManager;
+    use chrono::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_search_checkpoints_across_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let claude_dir = temp_dir.path().join("claude");
+        let project_path = temp_dir.path().join("project");
+        fs::create_dir_all(project_path.join("src")).unwrap();
+        fs::write(project_path.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let first = CheckpointManager::new(
+            "project".to_string(),
+            "first".to_string(),
+            project_path.clone(),
+            claude_dir.clone(),
+        )
+        .await
+        .unwrap();
+        let tagged = first
+            .create_checkpoint(None, None)
+            .await
+            .unwrap()
+            .checkpoint;
+        first
+            .update_checkpoint_labels(
+                &tagged.id,
+                vec![
+                    " Release ".to_string(),
+                    "release".to_string(),
+                    "v1".to_string(),
+                ],
+                true,
+            )
+            .await
+            .unwrap();
+
+        fs::write(project_path.join("README.md"), "docs").unwrap();
+        let second = CheckpointManager::new(
+            "project".to_string(),
+            "second".to_string(),
+            project_path.clone(),
+            claude_dir.clone(),
+        )
+        .await
+        .unwrap();
+        let untagged = second
+            .create_checkpoint(None, None)
+            .await
+            .unwrap()
+            .checkpoint;
+
+        let storage = CheckpointStorage::new(claude_dir);
+        let search = |query: CheckpointQuery| {
+            storage
+                .search_checkpoints("project", &query)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.session_id, r.checkpoint.id))
+                .collect::<Vec<_>>()
+        };
+
+        let hits = storage
+            .search_checkpoints(
+                "project",
+                &CheckpointQuery {
+                    tags: vec!["RELEASE".to_string()],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session_id, "first");
+        assert_eq!(hits[0].checkpoint.tags, vec!["Release", "v1"]);
+        assert!(hits[0].checkpoint.milestone);
+
+        assert_eq!(
+            search(CheckpointQuery {
+                path: Some("README.md".to_string()),
+                ..Default::default()
+            }),
+            vec![("second".to_string(), untagged.id.clone())]
+        );
+        assert_eq!(
+            search(CheckpointQuery {
+                path: Some("src".to_string()),
+                ..Default::default()
+            })
+            .len(),
+            2
+        );
+        assert_eq!(
+            search(CheckpointQuery {
+                milestones_only: true,
+                ..Default::default()
+            }),
+            vec![("first".to_string(), tagged.id.clone())]
+        );
+        assert!(search(CheckpointQuery {
+            since: Some(Utc::now() + Duration::hours(1)),
+            ..Default::default()
+        })
+        .is_empty());
+    }
+}
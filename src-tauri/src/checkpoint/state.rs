@@ -1,31 +1,87 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
-use super::manager::CheckpointManager;
+use super::manager::{CheckpointManager, ManagerMemoryUsage};
+use super::storage::CheckpointStorage;
+
+/// How long a manager may go unused before it is evicted
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Number of managers kept before the least recently used are evicted
+const DEFAULT_MAX_MANAGERS: usize = 16;
+
+/// A manager kept in memory, with when it was last handed out
+struct ManagedSession {
+    manager: Arc<CheckpointManager>,
+    last_used: Instant,
+}
+
+impl ManagedSession {
+    /// Whether the manager is still referenced outside the state, e.g. by a
+    /// running stream; such managers are never evicted
+    fn in_use(&self) -> bool {
+        Arc::strong_count(&self.manager) > 1
+    }
+}
+
+/// Memory statistics for one session's manager
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagerStats {
+    pub session_id: String,
+    pub project_id: String,
+    /// Seconds since the manager was last used
+    pub idle_seconds: u64,
+    /// Whether the manager is referenced outside the state and cannot be evicted
+    pub in_use: bool,
+    #[serde(flatten)]
+    pub usage: ManagerMemoryUsage,
+}
 
 /// Manages checkpoint managers for active sessions
 ///
 /// This struct maintains a stateful collection of CheckpointManager instances,
 /// one per active session, to avoid recreating them on every command invocation.
 /// It provides thread-safe access to managers and handles their lifecycle.
-#[derive(Default, Clone)]
+///
+/// Managers unused for longer than the idle timeout, or beyond the most
+/// recently used `max_managers`, are evicted. Their in-memory state is saved
+/// to disk and picked up again when the session's manager is next created.
+#[derive(Clone)]
 pub struct CheckpointState {
     /// Map of session_id to CheckpointManager
     /// Uses Arc<CheckpointManager> to allow sharing across async boundaries
-    managers: Arc<RwLock<HashMap<String, Arc<CheckpointManager>>>>,
+    managers: Arc<RwLock<HashMap<String, ManagedSession>>>,
     /// The Claude directory path for consistent access
     claude_dir: Arc<RwLock<Option<PathBuf>>>,
+    idle_timeout: Duration,
+    max_managers: usize,
+}
+
+impl Default for CheckpointState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CheckpointState {
     /// Creates a new CheckpointState instance
     pub fn new() -> Self {
+        Self::with_limits(DEFAULT_IDLE_TIMEOUT, DEFAULT_MAX_MANAGERS)
+    }
+
+    /// Creates a CheckpointState with custom eviction limits
+    pub fn with_limits(idle_timeout: Duration, max_managers: usize) -> Self {
         Self {
             managers: Arc::new(RwLock::new(HashMap::new())),
             claude_dir: Arc::new(RwLock::new(None)),
+            idle_timeout,
+            max_managers,
         }
     }
 
@@ -40,7 +96,8 @@ impl CheckpointState {
     /// Gets or creates a CheckpointManager for a session
     ///
     /// If a manager already exists for the session, it returns the existing one.
-    /// Otherwise, it creates a new manager and stores it for future use.
+    /// Otherwise, it creates a new manager and stores it for future use,
+    /// evicting the least recently used managers beyond `max_managers`.
     ///
     /// # Arguments
     /// * `session_id` - The session identifier
@@ -58,8 +115,9 @@ impl CheckpointState {
         let mut managers = self.managers.write().await;
 
         // Check if manager already exists
-        if let Some(manager) = managers.get(&session_id) {
-            return Ok(Arc::clone(manager));
+        if let Some(entry) = managers.get_mut(&session_id) {
+            entry.last_used = Instant::now();
+            return Ok(Arc::clone(&entry.manager));
         }
 
         // Get Claude directory
@@ -70,13 +128,38 @@ impl CheckpointState {
                 .clone()
         };
 
-        // Create new manager
-        let manager =
-            CheckpointManager::new(project_id, session_id.clone(), project_path, claude_dir)
-                .await?;
+        // Create new manager with the storage backend selected for the project
+        let storage = CheckpointStorage::for_project(claude_dir, &project_id, Some(&project_path))?;
+        let manager = CheckpointManager::with_storage(
+            project_id,
+            session_id.clone(),
+            project_path,
+            Arc::new(storage),
+        )
+        .await?;
 
         let manager_arc = Arc::new(manager);
-        managers.insert(session_id, Arc::clone(&manager_arc));
+        managers.insert(
+            session_id.clone(),
+            ManagedSession {
+                manager: Arc::clone(&manager_arc),
+                last_used: Instant::now(),
+            },
+        );
+
+        // Make room by evicting the least recently used idle managers
+        let excess = managers.len().saturating_sub(self.max_managers);
+        if excess > 0 {
+            let mut candidates: Vec<(String, Instant)> = managers
+                .iter()
+                .filter(|(id, entry)| **id != session_id && !entry.in_use())
+                .map(|(id, entry)| (id.clone(), entry.last_used))
+                .collect();
+            candidates.sort_by_key(|(_, last_used)| *last_used);
+            for (id, _) in candidates.into_iter().take(excess) {
+                Self::evict(&mut managers, &id).await;
+            }
+        }
 
         Ok(manager_arc)
     }
@@ -86,8 +169,11 @@ impl CheckpointState {
     /// Returns None if no manager exists for the session
     #[allow(dead_code)]
     pub async fn get_manager(&self, session_id: &str) -> Option<Arc<CheckpointManager>> {
-        let managers = self.managers.read().await;
-        managers.get(session_id).map(Arc::clone)
+        let mut managers = self.managers.write().await;
+        managers.get_mut(session_id).map(|entry| {
+            entry.last_used = Instant::now();
+            Arc::clone(&entry.manager)
+        })
     }
 
     /// Removes a CheckpointManager for a session
@@ -95,7 +181,51 @@ impl CheckpointState {
     /// This should be called when a session ends to free resources
     pub async fn remove_manager(&self, session_id: &str) -> Option<Arc<CheckpointManager>> {
         let mut managers = self.managers.write().await;
-        managers.remove(session_id)
+        managers.remove(session_id).map(|entry| entry.manager)
+    }
+
+    /// Removes the managers of every session of a project, returning how many were removed
+    ///
+    /// This should be called when a project's storage settings change so the
+    /// next manager is created with them
+    pub async fn remove_project_managers(&self, project_id: &str) -> usize {
+        let mut managers = self.managers.write().await;
+        let before = managers.len();
+        managers.retain(|_, entry| entry.manager.project_id() != project_id);
+        before - managers.len()
+    }
+
+    /// Evicts managers that have not been used within the idle timeout,
+    /// returning how many were evicted
+    ///
+    /// This should be called periodically
+    pub async fn evict_idle(&self) -> usize {
+        let mut managers = self.managers.write().await;
+        let idle: Vec<String> = managers
+            .iter()
+            .filter(|(_, entry)| !entry.in_use() && entry.last_used.elapsed() >= self.idle_timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &idle {
+            Self::evict(&mut managers, id).await;
+        }
+        idle.len()
+    }
+
+    /// Drop a manager after saving its in-memory state for the next one
+    async fn evict(managers: &mut HashMap<String, ManagedSession>, session_id: &str) {
+        let Some(entry) = managers.remove(session_id) else {
+            return;
+        };
+        match entry.manager.save_session_state().await {
+            Ok(()) => log::info!("Evicted checkpoint manager for session {}", session_id),
+            Err(e) => log::warn!(
+                "Evicted checkpoint manager for session {} without saving its state: {}",
+                session_id,
+                e
+            ),
+        }
     }
 
     /// Clears all managers
@@ -119,10 +249,32 @@ impl CheckpointState {
         managers.keys().cloned().collect()
     }
 
+    /// Memory statistics for every active manager, most recently used first
+    pub async fn manager_stats(&self) -> Vec<ManagerStats> {
+        let managers = self.managers.read().await;
+        let mut stats = Vec::with_capacity(managers.len());
+        for (session_id, entry) in managers.iter() {
+            stats.push(ManagerStats {
+                session_id: session_id.clone(),
+                project_id: entry.manager.project_id().to_string(),
+                idle_seconds: entry.last_used.elapsed().as_secs(),
+                in_use: entry.in_use(),
+                usage: entry.manager.memory_usage().await,
+            });
+        }
+        stats.sort_by_key(|s| s.idle_seconds);
+        stats
+    }
+
+    /// The idle timeout and manager cap used for eviction
+    pub fn limits(&self) -> (Duration, usize) {
+        (self.idle_timeout, self.max_managers)
+    }
+
     /// Checks if a session has an active manager
     #[allow(dead_code)]
     pub async fn has_active_manager(&self, session_id: &str) -> bool {
-        self.get_manager(session_id).await.is_some()
+        self.managers.read().await.contains_key(session_id)
     }
 
     /// Clears all managers and returns the count that were cleared
@@ -181,4 +333,43 @@ mod tests {
 
         assert!(!Arc::ptr_eq(&manager1, &manager3));
     }
+
+    #[tokio::test]
+    async fn test_evicted_manager_state_is_reloaded() {
+        let state = CheckpointState::with_limits(Duration::ZERO, 1);
+        let temp_dir = TempDir::new().unwrap();
+        state.set_claude_dir(temp_dir.path().to_path_buf()).await;
+        let project_path = temp_dir.path().join("project");
+        std::fs::create_dir_all(&project_path).unwrap();
+
+        let manager = |session: &str| {
+            state.get_or_create_manager(
+                session.to_string(),
+                "project".to_string(),
+                project_path.clone(),
+            )
+        };
+
+        let first = manager("first").await.unwrap();
+        first
+            .track_message(r#"{"type":"user"}"#.to_string())
+            .await
+            .unwrap();
+        drop(first);
+
+        // Going over the cap evicts the least recently used manager
+        let second = manager("second").await.unwrap();
+        assert_eq!(state.list_active_sessions().await, vec!["second"]);
+
+        // Managers still referenced elsewhere are kept
+        assert_eq!(state.evict_idle().await, 0);
+        drop(second);
+        assert_eq!(state.evict_idle().await, 1);
+
+        let reloaded = manager("first").await.unwrap();
+        assert_eq!(reloaded.memory_usage().await.message_count, 1);
+        let stats = state.manager_stats().await;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].usage.message_bytes, 15);
+    }
 }
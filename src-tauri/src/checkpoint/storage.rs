@@ -1,29 +1,65 @@
 use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDate, Utc};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use uuid::Uuid;
+use walkdir::WalkDir;
 use zstd::stream::{decode_all, encode_all};
 
+use super::backend::{self, BackendKind, FileBackend, StorageBackend};
+use super::content_store::{self, ContentStore, MigrationReport};
+use super::journal::{self, SessionLock};
 use super::{
-    Checkpoint, CheckpointPaths, CheckpointResult, FileSnapshot, SessionTimeline, TimelineNode,
+    Checkpoint, CheckpointPaths, CheckpointResult, FileSnapshot, SessionTimeline, TimelineIssue,
+    TimelineIssueKind, TimelineNode, TimelineVerifyReport,
 };
 
+/// Default zstd compression level
+const COMPRESSION_LEVEL: i32 = 3;
+
 /// Manages checkpoint storage operations
 pub struct CheckpointStorage {
     pub claude_dir: PathBuf,
     compression_level: i32,
+    /// Where snapshot content is kept
+    backend: Arc<dyn StorageBackend>,
 }
 
 impl CheckpointStorage {
-    /// Create a new checkpoint storage instance
+    /// Create a new checkpoint storage instance using the file backend
     pub fn new(claude_dir: PathBuf) -> Self {
+        Self::with_backend(claude_dir, Arc::new(FileBackend::new(COMPRESSION_LEVEL)))
+    }
+
+    /// Create a checkpoint storage instance keeping content in `backend`
+    pub fn with_backend(claude_dir: PathBuf, backend: Arc<dyn StorageBackend>) -> Self {
         Self {
             claude_dir,
-            compression_level: 3, // Default zstd compression level
+            compression_level: COMPRESSION_LEVEL,
+            backend,
         }
     }
 
+    /// Create a checkpoint storage instance using the backend configured for a
+    /// project. `project_path` is used as the git repository if none is configured.
+    pub fn for_project(
+        claude_dir: PathBuf,
+        project_id: &str,
+        project_path: Option<&Path>,
+    ) -> Result<Self> {
+        let config = backend::load_config(&claude_dir, project_id);
+        let backend = backend::open_backend(&config, project_path, COMPRESSION_LEVEL)?;
+        Ok(Self::with_backend(claude_dir, backend))
+    }
+
+    /// Kind of backend snapshot content is kept in
+    pub fn backend_kind(&self) -> BackendKind {
+        self.backend.kind()
+    }
+
     /// Initialize checkpoint storage for a session
     pub fn init_storage(&self, project_id: &str, session_id: &str) -> Result<()> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
@@ -33,15 +69,42 @@ impl CheckpointStorage {
             .context("Failed to create checkpoints directory")?;
         fs::create_dir_all(&paths.files_dir).context("Failed to create files directory")?;
 
+        let _lock = SessionLock::acquire(&paths.timeline_file)?;
+        self.recover_interrupted_checkpoint(&paths)?;
+
         // Initialize empty timeline if it doesn't exist
         if !paths.timeline_file.exists() {
             let timeline = SessionTimeline::new(session_id.to_string());
-            self.save_timeline(&paths.timeline_file, &timeline)?;
+            self.write_timeline(&paths.timeline_file, &timeline)?;
         }
 
         Ok(())
     }
 
+    /// Roll back a checkpoint whose creation was interrupted before it was
+    /// added to the timeline. Must be called with the session locked.
+    fn recover_interrupted_checkpoint(&self, paths: &CheckpointPaths) -> Result<()> {
+        let Some(entry) = journal::pending(&paths.timeline_file)? else {
+            return Ok(());
+        };
+
+        let committed = paths.timeline_file.exists()
+            && self
+                .load_timeline(&paths.timeline_file)?
+                .find_checkpoint(&entry.checkpoint_id)
+                .is_some();
+        if !committed {
+            log::warn!(
+                "Rolling back checkpoint {} interrupted at {}",
+                entry.checkpoint_id,
+                entry.started_at
+            );
+            self.remove_checkpoint(paths, &entry.checkpoint_id)?;
+        }
+
+        journal::commit(&paths.timeline_file)
+    }
+
     /// Save a checkpoint to disk
     pub fn save_checkpoint(
         &self,
@@ -54,6 +117,11 @@ impl CheckpointStorage {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
         let checkpoint_dir = paths.checkpoint_dir(&checkpoint.id);
 
+        // Everything written before the timeline update is rolled back if the
+        // process dies part way
+        let _lock = SessionLock::acquire(&paths.timeline_file)?;
+        journal::begin(&paths.timeline_file, &checkpoint.id)?;
+
         // Create checkpoint directory
         fs::create_dir_all(&checkpoint_dir).context("Failed to create checkpoint directory")?;
 
@@ -61,7 +129,8 @@ impl CheckpointStorage {
         let metadata_path = paths.checkpoint_metadata_file(&checkpoint.id);
         let metadata_json = serde_json::to_string_pretty(checkpoint)
             .context("Failed to serialize checkpoint metadata")?;
-        fs::write(&metadata_path, metadata_json).context("Failed to write checkpoint metadata")?;
+        journal::write_atomic(&metadata_path, metadata_json)
+            .context("Failed to write checkpoint metadata")?;
 
         // Save messages (compressed)
         let messages_path = paths.checkpoint_messages_file(&checkpoint.id);
@@ -70,13 +139,14 @@ impl CheckpointStorage {
         fs::write(&messages_path, compressed_messages)
             .context("Failed to write compressed messages")?;
 
-        // Save file snapshots
+        // Save file references, then hand the content to the backend. References
+        // are written first so a concurrent garbage collection never sees the
+        // content without them.
         let mut warnings = Vec::new();
-        let mut files_processed = 0;
-
+        let mut saved = Vec::new();
         for snapshot in &file_snapshots {
-            match self.save_file_snapshot(&paths, snapshot) {
-                Ok(_) => files_processed += 1,
+            match self.save_file_reference(&paths, snapshot) {
+                Ok(ref_path) => saved.push((snapshot, ref_path)),
                 Err(e) => warnings.push(format!(
                     "Failed to save {}: {}",
                     snapshot.file_path.display(),
@@ -85,40 +155,50 @@ impl CheckpointStorage {
             }
         }
 
+        let to_store: Vec<&FileSnapshot> = saved.iter().map(|(s, _)| *s).collect();
+        let failures = match self.backend.save_contents(&paths, checkpoint, &to_store) {
+            Ok(failures) => failures,
+            Err(e) => {
+                let message = e.to_string();
+                to_store
+                    .iter()
+                    .map(|s| (s.file_path.clone(), anyhow::anyhow!(message.clone())))
+                    .collect()
+            }
+        };
+        for (file_path, e) in &failures {
+            if let Some((_, ref_path)) = saved.iter().find(|(s, _)| &s.file_path == file_path) {
+                let _ = fs::remove_file(ref_path);
+            }
+            warnings.push(format!("Failed to save {}: {}", file_path.display(), e));
+        }
+        let files_processed = saved.len() - failures.len();
+
         // Update timeline
         self.update_timeline_with_checkpoint(&paths.timeline_file, checkpoint, &file_snapshots)?;
+        journal::commit(&paths.timeline_file)?;
 
         Ok(CheckpointResult {
             checkpoint: checkpoint.clone(),
             files_processed,
             warnings,
+            transcript: None,
         })
     }
 
-    /// Save a single file snapshot
-    fn save_file_snapshot(&self, paths: &CheckpointPaths, snapshot: &FileSnapshot) -> Result<()> {
-        // Use content-addressable storage: store files by their hash
-        // This prevents duplication of identical file content across checkpoints
-        let content_pool_dir = paths.files_dir.join("content_pool");
-        fs::create_dir_all(&content_pool_dir).context("Failed to create content pool directory")?;
-
-        // Store the actual content in the content pool
-        let content_file = content_pool_dir.join(&snapshot.hash);
-
-        // Only write the content if it doesn't already exist
-        if !content_file.exists() {
-            // Compress and save file content
-            let compressed_content =
-                encode_all(snapshot.content.as_bytes(), self.compression_level)
-                    .context("Failed to compress file content")?;
-            fs::write(&content_file, compressed_content)
-                .context("Failed to write file content to pool")?;
-        }
-
+    /// Save the reference to a single file snapshot, returning its path
+    fn save_file_reference(
+        &self,
+        paths: &CheckpointPaths,
+        snapshot: &FileSnapshot,
+    ) -> Result<PathBuf> {
         // Create a reference in the checkpoint-specific directory
-        let checkpoint_refs_dir = paths.files_dir.join("refs").join(&snapshot.checkpoint_id);
-        fs::create_dir_all(&checkpoint_refs_dir)
-            .context("Failed to create checkpoint refs directory")?;
+        let ref_path =
+            Self::file_reference_path(paths, &snapshot.checkpoint_id, &snapshot.file_path);
+        if let Some(checkpoint_refs_dir) = ref_path.parent() {
+            fs::create_dir_all(checkpoint_refs_dir)
+                .context("Failed to create checkpoint refs directory")?;
+        }
 
         // Save file metadata with reference to content
         let ref_metadata = serde_json::json!({
@@ -129,18 +209,77 @@ impl CheckpointStorage {
             "size": snapshot.size,
         });
 
-        // Use a sanitized filename for the reference
-        let safe_filename = snapshot
-            .file_path
+        fs::write(&ref_path, serde_json::to_string_pretty(&ref_metadata)?)
+            .context("Failed to write file reference")?;
+
+        Ok(ref_path)
+    }
+
+    /// Path of the reference a checkpoint keeps for a file, using a sanitized filename
+    fn file_reference_path(
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        file_path: &Path,
+    ) -> PathBuf {
+        let safe_filename = file_path
             .to_string_lossy()
             .replace('/', "_")
             .replace('\\', "_");
-        let ref_path = checkpoint_refs_dir.join(format!("{}.json", safe_filename));
+        paths
+            .files_dir
+            .join("refs")
+            .join(checkpoint_id)
+            .join(format!("{}.json", safe_filename))
+    }
 
-        fs::write(&ref_path, serde_json::to_string_pretty(&ref_metadata)?)
-            .context("Failed to write file reference")?;
+    /// Read a file reference into a snapshot without its content
+    fn read_file_reference(checkpoint_id: &str, ref_path: &Path) -> Result<FileSnapshot> {
+        let ref_json = fs::read_to_string(ref_path).context("Failed to read file reference")?;
+        let ref_metadata: serde_json::Value =
+            serde_json::from_str(&ref_json).context("Failed to parse file reference")?;
+
+        let hash = ref_metadata["hash"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing hash in reference"))?;
+
+        Ok(FileSnapshot {
+            checkpoint_id: checkpoint_id.to_string(),
+            file_path: PathBuf::from(ref_metadata["path"].as_str().unwrap_or("")),
+            content: Vec::new(),
+            hash: hash.to_string(),
+            is_deleted: ref_metadata["is_deleted"].as_bool().unwrap_or(false),
+            permissions: ref_metadata["permissions"].as_u64().map(|p| p as u32),
+            size: ref_metadata["size"].as_u64().unwrap_or(0),
+        })
+    }
 
-        Ok(())
+    /// Load the snapshot a checkpoint took of a single file, if it recorded a
+    /// change to it. Content is only loaded when `with_content` is set.
+    pub(super) fn load_file_snapshot(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        file_path: &Path,
+        with_content: bool,
+    ) -> Result<Option<FileSnapshot>> {
+        let ref_path = Self::file_reference_path(paths, checkpoint_id, file_path);
+        if !ref_path.exists() {
+            return Ok(None);
+        }
+
+        let snapshot = Self::read_file_reference(checkpoint_id, &ref_path)?;
+        // Different paths can share a sanitized filename
+        if snapshot.file_path != file_path {
+            return Ok(None);
+        }
+
+        let mut snapshots = [snapshot];
+        if with_content {
+            self.backend
+                .load_contents(paths, checkpoint_id, &mut snapshots)?;
+        }
+        let [snapshot] = snapshots;
+        Ok(Some(snapshot))
     }
 
     /// Load a checkpoint from disk
@@ -160,18 +299,67 @@ impl CheckpointStorage {
             serde_json::from_str(&metadata_json).context("Failed to parse checkpoint metadata")?;
 
         // Load messages
+        let messages = Self::read_messages(&paths, checkpoint_id)?;
+
+        // Load file snapshots
+        let file_snapshots = self.load_file_snapshots(&paths, checkpoint_id)?;
+
+        Ok((checkpoint, file_snapshots, messages))
+    }
+
+    /// Load the messages recorded with a checkpoint
+    pub fn load_messages(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<String> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        Self::read_messages(&paths, checkpoint_id)
+    }
+
+    fn read_messages(paths: &CheckpointPaths, checkpoint_id: &str) -> Result<String> {
         let messages_path = paths.checkpoint_messages_file(checkpoint_id);
         let compressed_messages =
             fs::read(&messages_path).context("Failed to read compressed messages")?;
-        let messages = String::from_utf8(
+        String::from_utf8(
             decode_all(&compressed_messages[..]).context("Failed to decompress messages")?,
         )
-        .context("Invalid UTF-8 in messages")?;
+        .context("Invalid UTF-8 in messages")
+    }
 
-        // Load file snapshots
-        let file_snapshots = self.load_file_snapshots(&paths, checkpoint_id)?;
+    /// Load the state of every file at a checkpoint.
+    ///
+    /// A checkpoint only snapshots files modified since the previous one, so
+    /// each path takes its nearest snapshot along the ancestor chain.
+    pub fn load_file_states(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+    ) -> Result<(Checkpoint, HashMap<PathBuf, FileSnapshot>)> {
+        let (checkpoint, snapshots, _) =
+            self.load_checkpoint(project_id, session_id, checkpoint_id)?;
+
+        let mut states: HashMap<PathBuf, FileSnapshot> = snapshots
+            .into_iter()
+            .map(|s| (s.file_path.clone(), s))
+            .collect();
+
+        let mut visited = HashSet::from([checkpoint.id.clone()]);
+        let mut parent_id = checkpoint.parent_checkpoint_id.clone();
+        while let Some(id) = parent_id {
+            if !visited.insert(id.clone()) {
+                break;
+            }
+            let (parent, snapshots, _) = self.load_checkpoint(project_id, session_id, &id)?;
+            for snapshot in snapshots {
+                states.entry(snapshot.file_path.clone()).or_insert(snapshot);
+            }
+            parent_id = parent.parent_checkpoint_id;
+        }
 
-        Ok((checkpoint, file_snapshots, messages))
+        Ok((checkpoint, states))
     }
 
     /// Load all file snapshots for a checkpoint
@@ -185,7 +373,6 @@ impl CheckpointStorage {
             return Ok(Vec::new());
         }
 
-        let content_pool_dir = paths.files_dir.join("content_pool");
         let mut snapshots = Vec::new();
 
         // Read all reference files
@@ -199,50 +386,28 @@ impl CheckpointStorage {
             }
 
             // Load reference metadata
-            let ref_json = fs::read_to_string(&path).context("Failed to read file reference")?;
-            let ref_metadata: serde_json::Value =
-                serde_json::from_str(&ref_json).context("Failed to parse file reference")?;
-
-            let hash = ref_metadata["hash"]
-                .as_str()
-                .ok_or_else(|| anyhow::anyhow!("Missing hash in reference"))?;
-
-            // Load content from pool
-            let content_file = content_pool_dir.join(hash);
-            let content = if content_file.exists() {
-                let compressed_content =
-                    fs::read(&content_file).context("Failed to read file content from pool")?;
-                String::from_utf8(
-                    decode_all(&compressed_content[..])
-                        .context("Failed to decompress file content")?,
-                )
-                .context("Invalid UTF-8 in file content")?
-            } else {
-                // Handle missing content gracefully
-                log::warn!("Content file missing for hash: {}", hash);
-                String::new()
-            };
-
-            snapshots.push(FileSnapshot {
-                checkpoint_id: checkpoint_id.to_string(),
-                file_path: PathBuf::from(ref_metadata["path"].as_str().unwrap_or("")),
-                content,
-                hash: hash.to_string(),
-                is_deleted: ref_metadata["is_deleted"].as_bool().unwrap_or(false),
-                permissions: ref_metadata["permissions"].as_u64().map(|p| p as u32),
-                size: ref_metadata["size"].as_u64().unwrap_or(0),
-            });
+            snapshots.push(Self::read_file_reference(checkpoint_id, &path)?);
         }
 
+        // Content is stored as raw bytes, so text snapshots written by earlier
+        // versions load unchanged. Missing content is left empty.
+        self.backend
+            .load_contents(paths, checkpoint_id, &mut snapshots)?;
+
         Ok(snapshots)
     }
 
-    /// Save timeline to disk
+    /// Save timeline to disk, locking the session while it is written
     pub fn save_timeline(&self, timeline_path: &Path, timeline: &SessionTimeline) -> Result<()> {
+        let _lock = SessionLock::acquire(timeline_path)?;
+        self.write_timeline(timeline_path, timeline)
+    }
+
+    /// Atomically replace the timeline file. Callers must hold the session lock.
+    fn write_timeline(&self, timeline_path: &Path, timeline: &SessionTimeline) -> Result<()> {
         let timeline_json =
             serde_json::to_string_pretty(timeline).context("Failed to serialize timeline")?;
-        fs::write(timeline_path, timeline_json).context("Failed to write timeline")?;
-        Ok(())
+        journal::write_atomic(timeline_path, timeline_json).context("Failed to write timeline")
     }
 
     /// Load timeline from disk
@@ -287,7 +452,7 @@ impl CheckpointStorage {
         }
 
         timeline.total_checkpoints += 1;
-        self.save_timeline(timeline_path, &timeline)?;
+        self.write_timeline(timeline_path, &timeline)?;
 
         Ok(())
     }
@@ -312,10 +477,50 @@ impl CheckpointStorage {
         anyhow::bail!("Parent checkpoint not found: {}", parent_id)
     }
 
+    /// Replace a checkpoint's tags and milestone flag, returning the updated checkpoint.
+    ///
+    /// Tags are trimmed and empty or duplicate tags dropped. Both the checkpoint's
+    /// metadata and its timeline node are updated.
+    pub fn update_checkpoint_labels(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        checkpoint_id: &str,
+        tags: Vec<String>,
+        milestone: bool,
+    ) -> Result<Checkpoint> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let _lock = SessionLock::acquire(&paths.timeline_file)?;
+        let mut timeline = self.load_timeline(&paths.timeline_file)?;
+        let node = timeline
+            .find_checkpoint_mut(checkpoint_id)
+            .with_context(|| format!("Checkpoint not found: {}", checkpoint_id))?;
+
+        let mut seen = HashSet::new();
+        node.checkpoint.tags = tags
+            .into_iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty() && seen.insert(t.to_lowercase()))
+            .collect();
+        node.checkpoint.milestone = milestone;
+        let checkpoint = node.checkpoint.clone();
+
+        let metadata_json = serde_json::to_string_pretty(&checkpoint)
+            .context("Failed to serialize checkpoint metadata")?;
+        journal::write_atomic(
+            &paths.checkpoint_metadata_file(checkpoint_id),
+            metadata_json,
+        )
+        .context("Failed to write checkpoint metadata")?;
+        self.write_timeline(&paths.timeline_file, &timeline)?;
+
+        Ok(checkpoint)
+    }
+
     /// Calculate hash of file content
-    pub fn calculate_file_hash(content: &str) -> String {
+    pub fn calculate_file_hash(content: &[u8]) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
+        hasher.update(content);
         format!("{:x}", hasher.finalize())
     }
 
@@ -341,6 +546,7 @@ impl CheckpointStorage {
         keep_count: usize,
     ) -> Result<usize> {
         let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let _lock = SessionLock::acquire(&paths.timeline_file)?;
         let timeline = self.load_timeline(&paths.timeline_file)?;
 
         // Collect all checkpoint IDs in chronological order
@@ -364,7 +570,7 @@ impl CheckpointStorage {
 
         // Run garbage collection to clean up orphaned content
         if removed_count > 0 {
-            match self.garbage_collect_content(project_id, session_id) {
+            match self.garbage_collect_content(project_id) {
                 Ok(gc_count) => {
                     log::info!("Garbage collected {} orphaned content files", gc_count);
                 }
@@ -377,6 +583,123 @@ impl CheckpointStorage {
         Ok(removed_count)
     }
 
+    /// Apply the timeline's retention policy, returning the number of checkpoints removed.
+    ///
+    /// Checkpoints older than `keep_all_hours` are thinned to one per day (or
+    /// removed entirely without `keep_daily`), then the oldest checkpoints are
+    /// removed until the project's timelines fit in `max_project_bytes`. Only
+    /// this session's checkpoints are ever removed. Finishes by garbage
+    /// collecting unreferenced content.
+    pub fn apply_retention_policy(&self, project_id: &str, session_id: &str) -> Result<usize> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let _lock = SessionLock::acquire(&paths.timeline_file)?;
+        let mut timeline = self.load_timeline(&paths.timeline_file)?;
+        let policy = timeline.retention_policy.clone();
+
+        let mut nodes = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            Self::collect_nodes(root, &mut nodes);
+        }
+
+        // Described checkpoints, milestones, fork points and the current checkpoint
+        // are never removed
+        let protected: HashSet<String> = nodes
+            .iter()
+            .filter(|n| {
+                n.checkpoint
+                    .description
+                    .as_deref()
+                    .is_some_and(|d| !d.trim().is_empty())
+                    || n.checkpoint.milestone
+                    || n.children.len() > 1
+                    || timeline.current_checkpoint_id.as_deref() == Some(n.checkpoint.id.as_str())
+            })
+            .map(|n| n.checkpoint.id.clone())
+            .collect();
+
+        // Oldest first
+        let mut candidates: Vec<Checkpoint> = nodes
+            .iter()
+            .filter(|n| !protected.contains(&n.checkpoint.id))
+            .map(|n| n.checkpoint.clone())
+            .collect();
+        candidates.sort_by_key(|c| c.timestamp);
+
+        let mut to_remove = HashSet::new();
+        if let Some(hours) = policy.keep_all_hours {
+            let cutoff = Utc::now() - Duration::hours(i64::from(hours));
+
+            // A day with a protected checkpoint is already represented
+            let mut kept_days: HashSet<NaiveDate> = nodes
+                .iter()
+                .filter(|n| protected.contains(&n.checkpoint.id))
+                .map(|n| n.checkpoint.timestamp.date_naive())
+                .collect();
+
+            for checkpoint in candidates.iter().rev() {
+                if checkpoint.timestamp >= cutoff {
+                    continue;
+                }
+                if !(policy.keep_daily && kept_days.insert(checkpoint.timestamp.date_naive())) {
+                    to_remove.insert(checkpoint.id.clone());
+                }
+            }
+        }
+
+        if !to_remove.is_empty() {
+            self.prune_checkpoints(&paths, &mut timeline, &to_remove, &HashSet::new())?;
+        }
+        let mut removed_count = to_remove.len();
+
+        if let Some(max_bytes) = policy.max_project_bytes {
+            let timelines_dir = self
+                .claude_dir
+                .join("projects")
+                .join(project_id)
+                .join(".timelines");
+            let mut remaining = candidates.iter().filter(|c| !to_remove.contains(&c.id));
+
+            // Measured once, then kept up to date with what each removal frees
+            let mut size = Self::dir_size(&timelines_dir);
+            while size > max_bytes {
+                let Some(checkpoint) = remaining.next() else {
+                    log::warn!(
+                        "Project {} exceeds its checkpoint size cap of {} bytes, but no more checkpoints can be removed",
+                        project_id,
+                        max_bytes
+                    );
+                    break;
+                };
+
+                let freed = self.prune_checkpoints(
+                    &paths,
+                    &mut timeline,
+                    &HashSet::from([checkpoint.id.clone()]),
+                    &HashSet::new(),
+                )?;
+                size = size.saturating_sub(freed);
+                removed_count += 1;
+            }
+        }
+
+        if removed_count > 0 {
+            self.garbage_collect_content(project_id)?;
+        }
+
+        Ok(removed_count)
+    }
+
+    /// Total size in bytes of all files under a directory
+    fn dir_size(dir: &Path) -> u64 {
+        WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| e.metadata().ok())
+            .map(|m| m.len())
+            .sum()
+    }
+
     /// Collect all checkpoints from the tree in order
     fn collect_checkpoints(node: &TimelineNode, checkpoints: &mut Vec<Checkpoint>) {
         checkpoints.push(node.checkpoint.clone());
@@ -385,76 +708,900 @@ impl CheckpointStorage {
         }
     }
 
-    /// Remove a checkpoint and its associated files
-    fn remove_checkpoint(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Result<()> {
+    /// Remove a checkpoint and its associated files, returning the bytes freed
+    fn remove_checkpoint(&self, paths: &CheckpointPaths, checkpoint_id: &str) -> Result<u64> {
+        let mut freed = 0;
+
         // Remove checkpoint metadata directory
         let checkpoint_dir = paths.checkpoint_dir(checkpoint_id);
         if checkpoint_dir.exists() {
+            freed += Self::dir_size(&checkpoint_dir);
             fs::remove_dir_all(&checkpoint_dir).context("Failed to remove checkpoint directory")?;
         }
 
-        // Remove file references for this checkpoint
+        // Release the content referenced by this checkpoint, then its file references.
+        // Content shared with other checkpoints or sessions stays in the store.
         let refs_dir = paths.files_dir.join("refs").join(checkpoint_id);
         if refs_dir.exists() {
+            let hashes = content_store::referenced_hashes(&refs_dir);
+            let blobs: HashMap<PathBuf, u64> = hashes
+                .iter()
+                .map(|hash| content_store::blob_path(&paths.content_store_dir, hash))
+                .filter_map(|blob| fs::metadata(&blob).ok().map(|m| (blob, m.len())))
+                .collect();
+
+            self.backend
+                .release_checkpoint(paths, checkpoint_id, &hashes)?;
+            freed += blobs
+                .iter()
+                .filter(|(blob, _)| !blob.exists())
+                .map(|(_, len)| len)
+                .sum::<u64>();
+
+            freed += Self::dir_size(&refs_dir);
             fs::remove_dir_all(&refs_dir).context("Failed to remove file references")?;
         }
 
-        // Note: We don't remove content from the pool here as it might be
-        // referenced by other checkpoints. Use garbage_collect_content() for that.
+        Ok(freed)
+    }
 
-        Ok(())
+    /// Give a checkpoint the file references of removed ancestors, nearest
+    /// first, for every path it does not hold a snapshot of itself. References
+    /// in `lost`, as (checkpoint ID, path), are not passed on. Returns the
+    /// bytes written.
+    fn inherit_file_references(
+        &self,
+        paths: &CheckpointPaths,
+        checkpoint_id: &str,
+        ancestor_ids: &[String],
+        lost: &HashSet<(String, PathBuf)>,
+    ) -> Result<u64> {
+        let mut held: HashSet<PathBuf> = Self::read_file_references(paths, checkpoint_id)
+            .into_iter()
+            .map(|s| s.file_path)
+            .collect();
+
+        let mut written = 0;
+        let mut hashes = Vec::new();
+        for ancestor_id in ancestor_ids {
+            for mut snapshot in Self::read_file_references(paths, ancestor_id) {
+                if lost.contains(&(ancestor_id.clone(), snapshot.file_path.clone()))
+                    || !held.insert(snapshot.file_path.clone())
+                {
+                    continue;
+                }
+                snapshot.checkpoint_id = checkpoint_id.to_string();
+                let ref_path = self.save_file_reference(paths, &snapshot)?;
+                written += fs::metadata(&ref_path).map(|m| m.len()).unwrap_or(0);
+                if !snapshot.is_deleted && !snapshot.hash.is_empty() {
+                    hashes.push(snapshot.hash);
+                }
+            }
+        }
+
+        // Hold the content before the ancestors release it
+        self.backend
+            .retain_contents(paths, checkpoint_id, &hashes)?;
+        Ok(written)
     }
 
-    /// Garbage collect unreferenced content from the content pool
-    pub fn garbage_collect_content(&self, project_id: &str, session_id: &str) -> Result<usize> {
-        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
-        let content_pool_dir = paths.files_dir.join("content_pool");
-        let refs_dir = paths.files_dir.join("refs");
+    /// Read a checkpoint's file references without content, skipping any
+    /// that cannot be read
+    fn read_file_references(paths: &CheckpointPaths, checkpoint_id: &str) -> Vec<FileSnapshot> {
+        let refs_dir = paths.files_dir.join("refs").join(checkpoint_id);
+        let Ok(entries) = fs::read_dir(&refs_dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+            .filter_map(|p| match Self::read_file_reference(checkpoint_id, &p) {
+                Ok(snapshot) => Some(snapshot),
+                Err(e) => {
+                    log::warn!("Skipping file reference {:?}: {}", p, e);
+                    None
+                }
+            })
+            .collect()
+    }
 
-        if !content_pool_dir.exists() {
-            return Ok(0);
+    /// Remove checkpoints from the timeline tree and from disk, keeping their descendants.
+    ///
+    /// Children of a removed checkpoint are re-attached to its nearest surviving
+    /// ancestor and the current checkpoint moves up the same way. A checkpoint
+    /// only snapshots the files changed since its parent, so the children take
+    /// over the file references they inherited through the removed checkpoint,
+    /// except those listed in `lost`. When the root is removed, later survivors
+    /// are attached under the oldest one and mark its other files as deleted, so
+    /// their file states are unchanged. The timeline is saved with a recounted
+    /// `total_checkpoints`. Returns the bytes freed on disk.
+    fn prune_checkpoints(
+        &self,
+        paths: &CheckpointPaths,
+        timeline: &mut SessionTimeline,
+        checkpoint_ids: &HashSet<String>,
+        lost: &HashSet<(String, PathBuf)>,
+    ) -> Result<u64> {
+        let mut nodes = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            Self::collect_nodes(root, &mut nodes);
+        }
+        let parents: HashMap<String, (Option<String>, Vec<String>)> = nodes
+            .iter()
+            .map(|n| {
+                (
+                    n.checkpoint.id.clone(),
+                    (
+                        n.checkpoint.parent_checkpoint_id.clone(),
+                        n.checkpoint.merge_parent_ids.clone(),
+                    ),
+                )
+            })
+            .collect();
+
+        // Survivors of a removed root hang under the oldest of them
+        let mut adopted = Vec::new();
+        if let Some(root) = timeline.root_node.take() {
+            let mut survivors = Self::prune_node(root, None, checkpoint_ids);
+            survivors.sort_by_key(|a| a.checkpoint.timestamp);
+            let mut survivors = survivors.into_iter();
+            timeline.root_node = survivors.next().map(|mut root| {
+                for mut sibling in survivors {
+                    sibling.checkpoint.parent_checkpoint_id = Some(root.checkpoint.id.clone());
+                    adopted.push(sibling.checkpoint.id.clone());
+                    root.children.push(sibling);
+                }
+                root
+            });
         }
 
-        // Collect all referenced hashes
-        let mut referenced_hashes = std::collections::HashSet::new();
+        // Keep per-checkpoint metadata in line with the re-parented tree
+        let mut kept = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            Self::collect_nodes(root, &mut kept);
+        }
+        for node in &kept {
+            let unchanged = parents
+                .get(&node.checkpoint.id)
+                .is_some_and(|(parent, merged)| {
+                    *parent == node.checkpoint.parent_checkpoint_id
+                        && *merged == node.checkpoint.merge_parent_ids
+                });
+            if !unchanged {
+                let metadata_json = serde_json::to_string_pretty(&node.checkpoint)
+                    .context("Failed to serialize checkpoint metadata")?;
+                journal::write_atomic(
+                    &paths.checkpoint_metadata_file(&node.checkpoint.id),
+                    metadata_json,
+                )
+                .context("Failed to write checkpoint metadata")?;
+            }
+        }
+        timeline.total_checkpoints = kept.len();
+
+        // Lifted checkpoints take over what their removed ancestors passed down
+        let mut written = 0;
+        let mut lost_paths: HashMap<&str, HashSet<PathBuf>> = HashMap::new();
+        for node in &kept {
+            let parent_of = |id: &str| parents.get(id).and_then(|(parent, _)| parent.clone());
+            let mut removed_ancestors = Vec::new();
+            let mut ancestor = parent_of(&node.checkpoint.id);
+            while let Some(id) = ancestor.filter(|id| checkpoint_ids.contains(id)) {
+                ancestor = parent_of(&id);
+                removed_ancestors.push(id);
+            }
+            if adopted.contains(&node.checkpoint.id) {
+                lost_paths.insert(
+                    &node.checkpoint.id,
+                    lost.iter()
+                        .filter(|(id, _)| removed_ancestors.contains(id))
+                        .map(|(_, path)| path.clone())
+                        .collect(),
+                );
+            }
+            if !removed_ancestors.is_empty() {
+                written += self.inherit_file_references(
+                    paths,
+                    &node.checkpoint.id,
+                    &removed_ancestors,
+                    lost,
+                )?;
+            }
+        }
 
-        if refs_dir.exists() {
-            for checkpoint_entry in fs::read_dir(&refs_dir)? {
-                let checkpoint_dir = checkpoint_entry?.path();
-                if checkpoint_dir.is_dir() {
-                    for ref_entry in fs::read_dir(&checkpoint_dir)? {
-                        let ref_path = ref_entry?.path();
-                        if ref_path.extension().and_then(|e| e.to_str()) == Some("json") {
-                            if let Ok(ref_json) = fs::read_to_string(&ref_path) {
-                                if let Ok(ref_metadata) =
-                                    serde_json::from_str::<serde_json::Value>(&ref_json)
-                                {
-                                    if let Some(hash) = ref_metadata["hash"].as_str() {
-                                        referenced_hashes.insert(hash.to_string());
-                                    }
-                                }
-                            }
-                        }
+        // An adopted survivor now holds every file it had, but would also pick
+        // up the new root's other files; record those as deleted. Files whose
+        // content was lost fall back to the new root's snapshot instead.
+        if let Some(root) = &timeline.root_node {
+            let root_files = Self::read_file_references(paths, &root.checkpoint.id);
+            for checkpoint_id in &adopted {
+                let mut held: HashSet<PathBuf> = Self::read_file_references(paths, checkpoint_id)
+                    .into_iter()
+                    .map(|s| s.file_path)
+                    .collect();
+                held.extend(
+                    lost_paths
+                        .remove(checkpoint_id.as_str())
+                        .unwrap_or_default(),
+                );
+                for snapshot in &root_files {
+                    if snapshot.is_deleted || held.contains(&snapshot.file_path) {
+                        continue;
                     }
+                    let deleted = FileSnapshot {
+                        checkpoint_id: checkpoint_id.clone(),
+                        file_path: snapshot.file_path.clone(),
+                        content: Vec::new(),
+                        hash: String::new(),
+                        is_deleted: true,
+                        permissions: None,
+                        size: 0,
+                    };
+                    let ref_path = self.save_file_reference(paths, &deleted)?;
+                    written += fs::metadata(&ref_path).map(|m| m.len()).unwrap_or(0);
                 }
             }
         }
 
-        // Remove unreferenced content
-        let mut removed_count = 0;
-        for entry in fs::read_dir(&content_pool_dir)? {
-            let content_file = entry?.path();
-            if content_file.is_file() {
-                if let Some(hash) = content_file.file_name().and_then(|n| n.to_str()) {
-                    if !referenced_hashes.contains(hash) {
-                        if fs::remove_file(&content_file).is_ok() {
-                            removed_count += 1;
-                        }
+        let mut freed = 0;
+        for checkpoint_id in checkpoint_ids {
+            freed += self.remove_checkpoint(paths, checkpoint_id)?;
+        }
+
+        // Walk up from the current checkpoint to the nearest one that survived
+        let mut current = timeline.current_checkpoint_id.clone();
+        while let Some(id) = current.clone() {
+            if timeline.find_checkpoint(&id).is_some() {
+                break;
+            }
+            current = parents.get(&id).and_then(|(parent, _)| parent.clone());
+        }
+        timeline.current_checkpoint_id = current;
+
+        self.write_timeline(&paths.timeline_file, timeline)?;
+        Ok(freed.saturating_sub(written))
+    }
+
+    /// Garbage collect unreferenced content from the project's backend.
+    ///
+    /// For the file backend reference counts are rebuilt from every session's
+    /// file references, so this also corrects counts left wrong by an
+    /// interrupted operation.
+    pub fn garbage_collect_content(&self, project_id: &str) -> Result<usize> {
+        self.backend
+            .collect_garbage(&CheckpointPaths::project_content_store_dir(
+                &self.claude_dir,
+                project_id,
+            ))
+    }
+
+    /// Move the per-session content pools of every project into their project's
+    /// content store. Stores are also migrated when first opened; this reclaims
+    /// space for projects that have not been opened since.
+    pub fn migrate_content_stores(&self) -> Result<MigrationReport> {
+        let mut total = MigrationReport::default();
+        let projects_dir = self.claude_dir.join("projects");
+        if !projects_dir.exists() {
+            return Ok(total);
+        }
+
+        for entry in fs::read_dir(&projects_dir)? {
+            let project_dir = entry?.path();
+            if !project_dir.join(".timelines").is_dir() {
+                continue;
+            }
+            let Some(project_id) = project_dir.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+
+            let store_dir =
+                CheckpointPaths::project_content_store_dir(&self.claude_dir, project_id);
+            let (_, report) = ContentStore::open_with_report(&store_dir)?;
+            total.sessions_migrated += report.sessions_migrated;
+            total.blobs_moved += report.blobs_moved;
+            total.duplicates_removed += report.duplicates_removed;
+            total.bytes_freed += report.bytes_freed;
+        }
+
+        Ok(total)
+    }
+
+    /// Verify the integrity of a session's checkpoint storage.
+    ///
+    /// Walks the timeline tree and checks that every checkpoint has readable
+    /// metadata and messages, that every referenced content hash exists in the
+    /// backend with content matching that hash, and looks
+    /// for checkpoint directories not reachable from the root node. With
+    /// `repair`, checkpoints with problems are pruned from the tree (their
+    /// children are re-attached to the nearest surviving ancestor and keep the
+    /// files they inherited through them, unless that content is lost), orphan
+    /// directories are removed and `total_checkpoints` is rebuilt.
+    pub fn verify_timeline(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        repair: bool,
+    ) -> Result<TimelineVerifyReport> {
+        let paths = CheckpointPaths::new(&self.claude_dir, project_id, session_id);
+        let _lock = SessionLock::acquire(&paths.timeline_file)?;
+        let mut timeline = self.load_timeline(&paths.timeline_file)?;
+
+        let mut report = TimelineVerifyReport {
+            session_id: session_id.to_string(),
+            repaired: repair,
+            ..Default::default()
+        };
+
+        let mut nodes = Vec::new();
+        if let Some(root) = &timeline.root_node {
+            Self::collect_nodes(root, &mut nodes);
+        }
+        report.checkpoints_checked = nodes.len();
+
+        // Blob checks are cached since most blobs are shared between checkpoints
+        let mut blob_status: HashMap<String, Option<TimelineIssueKind>> = HashMap::new();
+        for node in &nodes {
+            self.verify_checkpoint(&paths, node, &mut blob_status, &mut report.issues);
+        }
+        report.blobs_checked = blob_status.len();
+
+        if timeline.total_checkpoints != nodes.len() {
+            report.issues.push(TimelineIssue {
+                checkpoint_id: None,
+                kind: TimelineIssueKind::CountMismatch,
+                path: None,
+                detail: format!(
+                    "Timeline records {} checkpoints but the tree has {}",
+                    timeline.total_checkpoints,
+                    nodes.len()
+                ),
+            });
+        }
+
+        // Anything under checkpoints/ or refs/ that the tree does not reference
+        let reachable: HashSet<&str> = nodes.iter().map(|n| n.checkpoint.id.as_str()).collect();
+        let mut orphans = BTreeSet::new();
+        for dir in [&paths.checkpoints_dir, &paths.files_dir.join("refs")] {
+            if !dir.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                if !entry.path().is_dir() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !reachable.contains(name.as_str()) {
+                    orphans.insert(name);
+                }
+            }
+        }
+        report.orphan_checkpoints = orphans.into_iter().collect();
+
+        if !repair {
+            return Ok(report);
+        }
+
+        // Prune checkpoints with problems, keeping their descendants
+        let dangling: HashSet<String> = report
+            .issues
+            .iter()
+            .filter_map(|issue| issue.checkpoint_id.clone())
+            .collect();
+        // Files whose content is gone are not handed down; descendants fall back
+        // to the nearest earlier snapshot of them
+        let lost: HashSet<(String, PathBuf)> = report
+            .issues
+            .iter()
+            .filter(|issue| {
+                matches!(
+                    issue.kind,
+                    TimelineIssueKind::MissingContent | TimelineIssueKind::CorruptContent
+                )
+            })
+            .filter_map(|issue| Some((issue.checkpoint_id.clone()?, issue.path.clone()?)))
+            .collect();
+        self.prune_checkpoints(&paths, &mut timeline, &dangling, &lost)?;
+        report.pruned_checkpoints = dangling.into_iter().collect();
+        report.pruned_checkpoints.sort();
+
+        for checkpoint_id in &report.orphan_checkpoints {
+            self.remove_checkpoint(&paths, checkpoint_id)?;
+        }
+
+        if let Err(e) = self.garbage_collect_content(project_id) {
+            log::warn!("Failed to garbage collect content after repair: {}", e);
+        }
+
+        Ok(report)
+    }
+
+    /// Check a single checkpoint's metadata, messages and file references
+    fn verify_checkpoint(
+        &self,
+        paths: &CheckpointPaths,
+        node: &TimelineNode,
+        blob_status: &mut HashMap<String, Option<TimelineIssueKind>>,
+        issues: &mut Vec<TimelineIssue>,
+    ) {
+        let checkpoint_id = &node.checkpoint.id;
+        let issue =
+            |kind: TimelineIssueKind, path: Option<PathBuf>, detail: String| TimelineIssue {
+                checkpoint_id: Some(checkpoint_id.clone()),
+                kind,
+                path,
+                detail,
+            };
+
+        let metadata_ok = fs::read_to_string(paths.checkpoint_metadata_file(checkpoint_id))
+            .ok()
+            .and_then(|json| serde_json::from_str::<Checkpoint>(&json).ok())
+            .is_some();
+        if !metadata_ok {
+            issues.push(issue(
+                TimelineIssueKind::MissingMetadata,
+                None,
+                "Checkpoint metadata is missing or unreadable".to_string(),
+            ));
+        }
+
+        let messages_ok = fs::read(paths.checkpoint_messages_file(checkpoint_id))
+            .ok()
+            .and_then(|compressed| decode_all(&compressed[..]).ok())
+            .is_some();
+        if !messages_ok {
+            issues.push(issue(
+                TimelineIssueKind::MissingMessages,
+                None,
+                "Checkpoint messages are missing or cannot be decompressed".to_string(),
+            ));
+        }
+
+        if let Some(detail) = self.backend.check_checkpoint(paths, checkpoint_id) {
+            issues.push(issue(TimelineIssueKind::MissingContent, None, detail));
+        }
+
+        // Read the refs written for this checkpoint
+        let refs_dir = paths.files_dir.join("refs").join(checkpoint_id);
+        let mut referenced = HashSet::new();
+        if let Ok(entries) = fs::read_dir(&refs_dir) {
+            for entry in entries.flatten() {
+                let ref_path = entry.path();
+                if ref_path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+
+                let ref_metadata = fs::read_to_string(&ref_path)
+                    .ok()
+                    .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok());
+                let Some(ref_metadata) = ref_metadata else {
+                    issues.push(issue(
+                        TimelineIssueKind::InvalidReference,
+                        Some(PathBuf::from(entry.file_name())),
+                        "File reference cannot be parsed".to_string(),
+                    ));
+                    continue;
+                };
+
+                let hash = ref_metadata["hash"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                let file_path = PathBuf::from(ref_metadata["path"].as_str().unwrap_or_default());
+                referenced.insert(hash.clone());
+                if ref_metadata["is_deleted"].as_bool().unwrap_or(false) || hash.is_empty() {
+                    continue;
+                }
+
+                // Content in the file backend is shared by hash, in the git
+                // backend it belongs to the checkpoint's commit
+                let status = match self.backend.kind() {
+                    BackendKind::File => blob_status
+                        .entry(hash.clone())
+                        .or_insert_with(|| {
+                            self.backend
+                                .check_content(paths, checkpoint_id, &file_path, &hash)
+                        })
+                        .clone(),
+                    BackendKind::Git => {
+                        let status =
+                            self.backend
+                                .check_content(paths, checkpoint_id, &file_path, &hash);
+                        blob_status.insert(hash.clone(), status.clone());
+                        status
                     }
+                };
+                if let Some(kind) = status {
+                    let detail = match kind {
+                        TimelineIssueKind::MissingContent => {
+                            format!("Content {} is missing from storage", hash)
+                        }
+                        _ => format!("Content {} does not match its hash", hash),
+                    };
+                    issues.push(issue(kind, Some(file_path), detail));
                 }
             }
         }
 
-        Ok(removed_count)
+        // Every snapshot recorded on the node must have a reference
+        for hash in &node.file_snapshot_ids {
+            if !hash.is_empty() && !referenced.contains(hash) {
+                issues.push(issue(
+                    TimelineIssueKind::MissingReference,
+                    None,
+                    format!("No file reference for content {}", hash),
+                ));
+            }
+        }
+    }
+
+    /// Collect references to all nodes of the tree
+    fn collect_nodes<'a>(node: &'a TimelineNode, nodes: &mut Vec<&'a TimelineNode>) {
+        nodes.push(node);
+        for child in &node.children {
+            Self::collect_nodes(child, nodes);
+        }
+    }
+
+    /// Remove dangling nodes from a subtree, lifting their children to `parent_id`.
+    /// Merge parents that are removed are dropped.
+    fn prune_node(
+        node: TimelineNode,
+        parent_id: Option<&str>,
+        dangling: &HashSet<String>,
+    ) -> Vec<TimelineNode> {
+        let TimelineNode {
+            mut checkpoint,
+            children,
+            file_snapshot_ids,
+        } = node;
+        checkpoint
+            .merge_parent_ids
+            .retain(|id| !dangling.contains(id));
+
+        if dangling.contains(&checkpoint.id) {
+            return children
+                .into_iter()
+                .flat_map(|child| Self::prune_node(child, parent_id, dangling))
+                .map(|mut lifted| {
+                    lifted.checkpoint.parent_checkpoint_id = parent_id.map(String::from);
+                    lifted
+                })
+                .collect();
+        }
+
+        let children = children
+            .into_iter()
+            .flat_map(|child| Self::prune_node(child, Some(&checkpoint.id), dangling))
+            .collect();
+
+        vec![TimelineNode {
+            checkpoint,
+            children,
+            file_snapshot_ids,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::test_support;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_binary_snapshot_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        let content = vec![0x89, b'P', b'N', b'G', 0x00, 0xff, 0xfe, 0x0d, 0x0a];
+        let checkpoint = test_support::make_checkpoint(None);
+        let snapshot = test_support::make_snapshot(&checkpoint, "assets/logo.png", &content);
+
+        storage
+            .save_checkpoint("project", "session", &checkpoint, vec![snapshot], "")
+            .unwrap();
+        let (_, snapshots, _) = storage
+            .load_checkpoint("project", "session", &checkpoint.id)
+            .unwrap();
+
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].content, content);
+    }
+
+    fn make_checkpoint(parent: Option<&Checkpoint>, content: &[u8]) -> (Checkpoint, FileSnapshot) {
+        let checkpoint = test_support::make_checkpoint(parent);
+        let snapshot = test_support::make_snapshot(&checkpoint, "main.rs", content);
+        (checkpoint, snapshot)
+    }
+
+    #[test]
+    fn test_interrupted_checkpoint_is_rolled_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+        let paths = CheckpointPaths::new(&storage.claude_dir, "project", "session");
+
+        let (first, first_snapshot) = make_checkpoint(None, b"fn main() {}");
+        storage
+            .save_checkpoint("project", "session", &first, vec![first_snapshot], "")
+            .unwrap();
+        let committed_timeline = fs::read(&paths.timeline_file).unwrap();
+
+        // Simulate a crash after the content was written but before the
+        // timeline was updated
+        let (second, second_snapshot) = make_checkpoint(Some(&first), b"fn main() { panic!() }");
+        let second_hash = second_snapshot.hash.clone();
+        storage
+            .save_checkpoint("project", "session", &second, vec![second_snapshot], "")
+            .unwrap();
+        fs::write(&paths.timeline_file, committed_timeline).unwrap();
+        journal::begin(&paths.timeline_file, &second.id).unwrap();
+
+        storage.init_storage("project", "session").unwrap();
+
+        assert!(journal::pending(&paths.timeline_file).unwrap().is_none());
+        assert!(!paths.checkpoint_dir(&second.id).exists());
+        assert!(!paths.files_dir.join("refs").join(&second.id).exists());
+        assert!(!content_store::blob_path(&paths.content_store_dir, &second_hash).exists());
+        assert!(storage
+            .load_checkpoint("project", "session", &first.id)
+            .is_ok());
+        assert!(storage
+            .verify_timeline("project", "session", false)
+            .unwrap()
+            .orphan_checkpoints
+            .is_empty());
+    }
+
+    #[test]
+    fn test_verify_timeline_detects_and_repairs_missing_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        let (first, first_snapshot) = make_checkpoint(None, b"one");
+        let (second, second_snapshot) = make_checkpoint(Some(&first), b"two");
+        let (third, mut third_snapshot) = make_checkpoint(Some(&second), b"three");
+        third_snapshot.file_path = PathBuf::from("README.md");
+        // Only the middle checkpoint holds lib.rs
+        let lib_snapshot = FileSnapshot {
+            file_path: PathBuf::from("lib.rs"),
+            hash: CheckpointStorage::calculate_file_hash(b"lib"),
+            content: b"lib".to_vec(),
+            size: 3,
+            ..second_snapshot.clone()
+        };
+        for (checkpoint, snapshots) in [
+            (&first, vec![first_snapshot]),
+            (&second, vec![second_snapshot.clone(), lib_snapshot]),
+            (&third, vec![third_snapshot]),
+        ] {
+            storage
+                .save_checkpoint("project", "session", checkpoint, snapshots, "")
+                .unwrap();
+        }
+
+        let report = storage
+            .verify_timeline("project", "session", false)
+            .unwrap();
+        assert!(report.is_healthy());
+        assert_eq!(report.checkpoints_checked, 3);
+
+        // Lose the middle checkpoint's content and leave a stray checkpoint directory
+        let paths = CheckpointPaths::new(&temp_dir.path().to_path_buf(), "project", "session");
+        fs::remove_file(content_store::blob_path(
+            &paths.content_store_dir,
+            &second_snapshot.hash,
+        ))
+        .unwrap();
+        fs::create_dir_all(paths.checkpoint_dir("stray")).unwrap();
+
+        let report = storage
+            .verify_timeline("project", "session", false)
+            .unwrap();
+        assert!(!report.is_healthy());
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, TimelineIssueKind::MissingContent);
+        assert_eq!(
+            report.issues[0].checkpoint_id.as_deref(),
+            Some(second.id.as_str())
+        );
+        assert_eq!(report.orphan_checkpoints, vec!["stray".to_string()]);
+
+        let report = storage.verify_timeline("project", "session", true).unwrap();
+        assert_eq!(report.pruned_checkpoints, vec![second.id.clone()]);
+
+        let timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        assert_eq!(timeline.total_checkpoints, 2);
+        let root = timeline.root_node.as_ref().unwrap();
+        assert_eq!(root.checkpoint.id, first.id);
+        assert_eq!(root.children[0].checkpoint.id, third.id);
+        assert_eq!(
+            root.children[0].checkpoint.parent_checkpoint_id.as_deref(),
+            Some(first.id.as_str())
+        );
+        assert!(!paths.checkpoint_dir("stray").exists());
+
+        // The lifted checkpoint keeps lib.rs; main.rs, whose content was lost,
+        // falls back to the root's snapshot
+        let (_, states) = storage
+            .load_file_states("project", "session", &third.id)
+            .unwrap();
+        let contents: BTreeSet<(PathBuf, Vec<u8>)> = states
+            .into_values()
+            .map(|s| (s.file_path, s.content))
+            .collect();
+        assert_eq!(
+            contents,
+            BTreeSet::from([
+                (PathBuf::from("README.md"), b"three".to_vec()),
+                (PathBuf::from("lib.rs"), b"lib".to_vec()),
+                (PathBuf::from("main.rs"), b"one".to_vec()),
+            ])
+        );
+
+        let report = storage
+            .verify_timeline("project", "session", false)
+            .unwrap();
+        assert!(report.is_healthy());
+    }
+
+    #[test]
+    fn test_repair_keeps_file_states_of_root_siblings() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        // Two branches fork from the root, each adding a file of its own
+        let (root, root_snapshot) = make_checkpoint(None, b"one");
+        let lib_snapshot = FileSnapshot {
+            file_path: PathBuf::from("lib.rs"),
+            hash: CheckpointStorage::calculate_file_hash(b"lib"),
+            content: b"lib".to_vec(),
+            size: 3,
+            ..root_snapshot.clone()
+        };
+        let branch = |content: &[u8], file: &str, offset: i64| {
+            let (mut checkpoint, snapshot) = make_checkpoint(Some(&root), content);
+            checkpoint.timestamp = root.timestamp + Duration::seconds(offset);
+            let added = FileSnapshot {
+                file_path: PathBuf::from(file),
+                ..snapshot.clone()
+            };
+            (checkpoint, vec![snapshot, added])
+        };
+        let (first, first_snapshots) = branch(b"first", "first.txt", 1);
+        let (second, second_snapshots) = branch(b"second", "second.txt", 2);
+        for (checkpoint, snapshots) in [
+            (&root, vec![root_snapshot.clone(), lib_snapshot]),
+            (&first, first_snapshots),
+            (&second, second_snapshots),
+        ] {
+            storage
+                .save_checkpoint("project", "session", checkpoint, snapshots, "")
+                .unwrap();
+        }
+
+        // Losing the root's content removes it, and the second branch is
+        // attached under the first
+        let paths = CheckpointPaths::new(&temp_dir.path().to_path_buf(), "project", "session");
+        fs::remove_file(content_store::blob_path(
+            &paths.content_store_dir,
+            &root_snapshot.hash,
+        ))
+        .unwrap();
+        let report = storage.verify_timeline("project", "session", true).unwrap();
+        assert_eq!(report.pruned_checkpoints, vec![root.id.clone()]);
+
+        let timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        let new_root = timeline.root_node.as_ref().unwrap();
+        assert_eq!(new_root.checkpoint.id, first.id);
+        assert_eq!(new_root.children[0].checkpoint.id, second.id);
+
+        let contents = |checkpoint: &Checkpoint| -> BTreeSet<(PathBuf, Vec<u8>)> {
+            let (_, states) = storage
+                .load_file_states("project", "session", &checkpoint.id)
+                .unwrap();
+            states
+                .into_values()
+                .filter(|s| !s.is_deleted)
+                .map(|s| (s.file_path, s.content))
+                .collect()
+        };
+        assert_eq!(
+            contents(&first),
+            BTreeSet::from([
+                (PathBuf::from("first.txt"), b"first".to_vec()),
+                (PathBuf::from("lib.rs"), b"lib".to_vec()),
+                (PathBuf::from("main.rs"), b"first".to_vec()),
+            ])
+        );
+        assert_eq!(
+            contents(&second),
+            BTreeSet::from([
+                (PathBuf::from("lib.rs"), b"lib".to_vec()),
+                (PathBuf::from("main.rs"), b"second".to_vec()),
+                (PathBuf::from("second.txt"), b"second".to_vec()),
+            ])
+        );
+
+        let report = storage
+            .verify_timeline("project", "session", false)
+            .unwrap();
+        assert!(report.is_healthy());
+    }
+
+    #[test]
+    fn test_retention_policy_thins_old_checkpoints() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = CheckpointStorage::new(temp_dir.path().to_path_buf());
+        storage.init_storage("project", "session").unwrap();
+
+        let day = |days_ago: i64, hour: u32| {
+            (Utc::now() - Duration::days(days_ago))
+                .date_naive()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        let mut saved: Vec<Checkpoint> = Vec::new();
+        for (i, (timestamp, description)) in [
+            (day(3, 10), None),
+            (day(3, 12), None),
+            (day(2, 10), Some("before refactor")),
+            (day(2, 12), None),
+            (Utc::now(), None),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let (mut checkpoint, snapshot) =
+                make_checkpoint(saved.last(), format!("version {}", i).as_bytes());
+            checkpoint.timestamp = timestamp;
+            checkpoint.description = description.map(String::from);
+            storage
+                .save_checkpoint("project", "session", &checkpoint, vec![snapshot], "")
+                .unwrap();
+            saved.push(checkpoint);
+        }
+
+        let paths = CheckpointPaths::new(&temp_dir.path().to_path_buf(), "project", "session");
+        let mut timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        timeline.retention_policy.keep_all_hours = Some(24);
+        storage
+            .save_timeline(&paths.timeline_file, &timeline)
+            .unwrap();
+
+        let removed = storage
+            .apply_retention_policy("project", "session")
+            .unwrap();
+        assert_eq!(removed, 2);
+
+        // The newest of day 3, the described checkpoint of day 2 and the recent one remain
+        let timeline = storage.load_timeline(&paths.timeline_file).unwrap();
+        assert_eq!(timeline.total_checkpoints, 3);
+        for (i, checkpoint) in saved.iter().enumerate() {
+            let kept = timeline.find_checkpoint(&checkpoint.id).is_some();
+            assert_eq!(kept, [1, 2, 4].contains(&i), "checkpoint {}", i);
+            assert_eq!(paths.checkpoint_dir(&checkpoint.id).exists(), kept);
+        }
+        assert_eq!(
+            timeline.current_checkpoint_id.as_deref(),
+            Some(saved[4].id.as_str())
+        );
+
+        // Content only referenced by removed checkpoints is collected
+        for (i, checkpoint) in saved.iter().enumerate() {
+            let hash = CheckpointStorage::calculate_file_hash(format!("version {}", i).as_bytes());
+            assert_eq!(
+                content_store::blob_path(&paths.content_store_dir, &hash).exists(),
+                timeline.find_checkpoint(&checkpoint.id).is_some()
+            );
+        }
     }
 }
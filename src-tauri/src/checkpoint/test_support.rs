@@ -0,0 +1,70 @@
+//! Fixtures shared by the checkpoint tests
+
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use tempfile::TempDir;
+
+use super::manager::CheckpointManager;
+use super::storage::CheckpointStorage;
+use super::{Checkpoint, CheckpointMetadata, FileSnapshot};
+
+/// An undescribed checkpoint of session `session` in project `project`
+pub(crate) fn make_checkpoint(parent: Option<&Checkpoint>) -> Checkpoint {
+    Checkpoint {
+        id: CheckpointStorage::generate_checkpoint_id(),
+        session_id: "session".to_string(),
+        project_id: "project".to_string(),
+        message_index: 0,
+        timestamp: Utc::now(),
+        description: None,
+        parent_checkpoint_id: parent.map(|p| p.id.clone()),
+        merge_parent_ids: Vec::new(),
+        tags: Vec::new(),
+        milestone: false,
+        metadata: CheckpointMetadata {
+            total_tokens: 0,
+            model_used: "unknown".to_string(),
+            user_prompt: String::new(),
+            file_changes: 1,
+            snapshot_size: 0,
+        },
+    }
+}
+
+/// A checkpoint's snapshot of a file holding `content`
+pub(crate) fn make_snapshot(checkpoint: &Checkpoint, path: &str, content: &[u8]) -> FileSnapshot {
+    FileSnapshot {
+        checkpoint_id: checkpoint.id.clone(),
+        file_path: PathBuf::from(path),
+        hash: CheckpointStorage::calculate_file_hash(content),
+        content: content.to_vec(),
+        is_deleted: false,
+        permissions: None,
+        size: content.len() as u64,
+    }
+}
+
+/// A manager for session `session` of a project holding `files`, with the
+/// temporary directory holding both the project and the Claude directory
+pub(crate) async fn make_manager(files: &[(&str, &str)]) -> (TempDir, PathBuf, CheckpointManager) {
+    let temp_dir = TempDir::new().unwrap();
+    let project_path = temp_dir.path().join("project");
+    fs::create_dir_all(&project_path).unwrap();
+    for (path, content) in files {
+        let path = project_path.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    let manager = CheckpointManager::new(
+        "project".to_string(),
+        "session".to_string(),
+        project_path.clone(),
+        temp_dir.path().join("claude"),
+    )
+    .await
+    .unwrap();
+    (temp_dir, project_path, manager)
+}
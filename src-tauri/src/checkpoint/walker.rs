@@ -0,0 +1,253 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Project-level ignore file with gitignore syntax, applied on top of `.gitignore`
+pub const CLAUDIA_IGNORE_FILE: &str = ".claudiaignore";
+
+/// Maximum number of skipped paths listed individually in warnings
+const MAX_REPORTED_SKIPS: usize = 50;
+
+/// Files and directories found while walking a project for checkpointing
+#[derive(Debug, Default)]
+pub struct ProjectFiles {
+    /// Files to include, relative to the project root
+    pub files: Vec<PathBuf>,
+    /// Directories visited, relative to the project root, parents before children
+    pub dirs: Vec<PathBuf>,
+    /// Paths excluded by ignore rules. Ignored directories are listed once
+    /// and not descended into.
+    pub skipped: Vec<PathBuf>,
+}
+
+impl ProjectFiles {
+    /// Describe skipped paths for `CheckpointResult::warnings`
+    pub fn skipped_warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .skipped
+            .iter()
+            .take(MAX_REPORTED_SKIPS)
+            .map(|p| format!("Skipped ignored path: {}", p.display()))
+            .collect();
+
+        if self.skipped.len() > MAX_REPORTED_SKIPS {
+            warnings.push(format!(
+                "Skipped {} more ignored paths",
+                self.skipped.len() - MAX_REPORTED_SKIPS
+            ));
+        }
+
+        warnings
+    }
+}
+
+/// Size and modification time of a file, used to detect changes without hashing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFingerprint {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Fingerprints of every non-ignored file in a project, keyed by relative path
+pub type FileIndex = HashMap<PathBuf, FileFingerprint>;
+
+/// Build a fingerprint index of the project's files
+pub fn build_file_index(project_path: &Path) -> FileIndex {
+    collect_project_files(project_path)
+        .files
+        .into_iter()
+        .filter_map(|rel| {
+            let metadata = fs::metadata(project_path.join(&rel)).ok()?;
+            Some((
+                rel,
+                FileFingerprint {
+                    size: metadata.len(),
+                    modified: metadata.modified().ok(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Paths that were created, modified or deleted between two indexes
+pub fn changed_paths(before: &FileIndex, after: &FileIndex) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = after
+        .iter()
+        .filter(|(path, fingerprint)| before.get(*path) != Some(*fingerprint))
+        .map(|(path, _)| path.clone())
+        .chain(
+            before
+                .keys()
+                .filter(|path| !after.contains_key(*path))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// Collect the files of a project that belong in a checkpoint.
+///
+/// Honours `.gitignore` files at every level, `.git/info/exclude` and
+/// `.claudiaignore` files. Rules in deeper directories take precedence, and
+/// within a directory `.claudiaignore` overrides `.gitignore`. Hidden
+/// directories such as `.git` are never descended into.
+pub fn collect_project_files(project_path: &Path) -> ProjectFiles {
+    let mut result = ProjectFiles::default();
+    let mut matchers = Vec::new();
+
+    // Repository-wide excludes have the lowest precedence
+    let exclude_file = project_path.join(".git").join("info").join("exclude");
+    if exclude_file.is_file() {
+        let mut builder = GitignoreBuilder::new(project_path);
+        if let Some(e) = builder.add(&exclude_file) {
+            log::warn!("Failed to parse {:?}: {}", exclude_file, e);
+        }
+        if let Ok(matcher) = builder.build() {
+            matchers.push(matcher);
+        }
+    }
+
+    walk_dir(project_path, project_path, &mut matchers, &mut result);
+    result
+}
+
+fn walk_dir(dir: &Path, base: &Path, matchers: &mut Vec<Gitignore>, result: &mut ProjectFiles) {
+    let dir_matcher = build_dir_matcher(dir);
+    let pushed = dir_matcher.is_some();
+    if let Some(matcher) = dir_matcher {
+        matchers.push(matcher);
+    }
+
+    let mut entries = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).collect::<Vec<_>>(),
+        Err(e) => {
+            log::warn!("Failed to read directory {:?}: {}", dir, e);
+            Vec::new()
+        }
+    };
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let is_dir = path.is_dir();
+
+        if is_dir {
+            // Skip hidden directories like .git
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if name.starts_with('.') {
+                    continue;
+                }
+            }
+        }
+
+        let rel = match path.strip_prefix(base) {
+            Ok(rel) => rel.to_path_buf(),
+            Err(_) => continue,
+        };
+
+        if is_ignored(matchers, &path, is_dir) {
+            result.skipped.push(rel);
+            continue;
+        }
+
+        if is_dir {
+            result.dirs.push(rel);
+            walk_dir(&path, base, matchers, result);
+        } else if path.is_file() {
+            result.files.push(rel);
+        }
+    }
+
+    if pushed {
+        matchers.pop();
+    }
+}
+
+/// Build the matcher for ignore files placed directly in `dir`
+fn build_dir_matcher(dir: &Path) -> Option<Gitignore> {
+    let candidates = [dir.join(".gitignore"), dir.join(CLAUDIA_IGNORE_FILE)];
+    if !candidates.iter().any(|p| p.is_file()) {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    for file in candidates.iter().filter(|p| p.is_file()) {
+        if let Some(e) = builder.add(file) {
+            log::warn!("Failed to parse {:?}: {}", file, e);
+        }
+    }
+
+    match builder.build() {
+        Ok(matcher) => Some(matcher),
+        Err(e) => {
+            log::warn!("Failed to build ignore rules for {:?}: {}", dir, e);
+            None
+        }
+    }
+}
+
+/// Check a path against the matcher stack, innermost directory first
+fn is_ignored(matchers: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    for matcher in matchers.iter().rev() {
+        let matched = matcher.matched(path, is_dir);
+        if matched.is_ignore() {
+            return true;
+        }
+        if matched.is_whitelist() {
+            return false;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_collect_project_files_honours_ignore_rules() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for file in [
+            "src/main.rs",
+            "node_modules/pkg/index.js",
+            "target/debug/app",
+            "dist/keep.js",
+            "dist/bundle.js",
+            "notes.local",
+            ".git/info/exclude",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        }
+        fs::write(root.join(".git/info/exclude"), "*.local\n").unwrap();
+        fs::write(root.join(".gitignore"), "node_modules/\ntarget/\ndist/*\n").unwrap();
+        fs::write(root.join(CLAUDIA_IGNORE_FILE), "!dist/keep.js\n").unwrap();
+
+        let result = collect_project_files(root);
+        let mut files: Vec<String> = result
+            .files
+            .iter()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .collect();
+        files.sort();
+
+        assert_eq!(
+            files,
+            vec![
+                ".claudiaignore",
+                ".gitignore",
+                "dist/keep.js",
+                "src/main.rs"
+            ]
+        );
+        assert_eq!(result.skipped.len(), 4);
+        assert!(result.skipped.contains(&PathBuf::from("node_modules")));
+        assert!(result.skipped.contains(&PathBuf::from("notes.local")));
+    }
+}
@@ -1,3 +1,8 @@
+use crate::commands::permissions::{
+    expire_pending, init_permission_tables, PermissionMode, PermissionSource, PermissionState,
+};
+use crate::process::limits::{Cgroup, ResourceLimits};
+use crate::process::persistence::{self, ProcessKind, ProcessRecord};
 use crate::sandbox::profile::ProfileBuilder;
 use anyhow::Result;
 use chrono;
@@ -32,6 +37,12 @@ pub struct Agent {
     pub enable_file_read: bool,
     pub enable_file_write: bool,
     pub enable_network: bool,
+    /// How the agent's tool use is approved
+    #[serde(default)]
+    pub permission_mode: PermissionMode,
+    /// Resources the agent's runs may use
+    #[serde(flatten)]
+    pub limits: ResourceLimits,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -52,6 +63,8 @@ pub struct AgentRun {
     pub process_started_at: Option<String>,
     pub created_at: String,
     pub completed_at: Option<String>,
+    /// Why the run ended with its status, e.g. why it failed
+    pub status_reason: Option<String>,
 }
 
 /// Represents runtime metrics calculated from JSONL
@@ -92,6 +105,10 @@ pub struct AgentData {
     pub enable_file_read: bool,
     pub enable_file_write: bool,
     pub enable_network: bool,
+    #[serde(default)]
+    pub permission_mode: PermissionMode,
+    #[serde(flatten)]
+    pub limits: ResourceLimits,
 }
 
 /// Database connection state
@@ -239,6 +256,11 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             enable_file_read BOOLEAN NOT NULL DEFAULT 1,
             enable_file_write BOOLEAN NOT NULL DEFAULT 1,
             enable_network BOOLEAN NOT NULL DEFAULT 0,
+            permission_mode TEXT,
+            max_runtime_secs INTEGER,
+            max_cpu_secs INTEGER,
+            max_memory_mb INTEGER,
+            max_processes INTEGER,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
         )",
@@ -271,6 +293,21 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agents ADD COLUMN enable_network BOOLEAN DEFAULT 0",
         [],
     );
+    // Agents created before permission modes ran with every tool use approved,
+    // which is kept for them when the column is first added
+    if conn
+        .execute("ALTER TABLE agents ADD COLUMN permission_mode TEXT", [])
+        .is_ok()
+    {
+        conn.execute(
+            "UPDATE agents SET permission_mode = ?1 WHERE permission_mode IS NULL",
+            params![PermissionMode::Skip.to_column()],
+        )?;
+    }
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN max_runtime_secs INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN max_cpu_secs INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN max_memory_mb INTEGER", []);
+    let _ = conn.execute("ALTER TABLE agents ADD COLUMN max_processes INTEGER", []);
 
     // Create agent_runs table
     conn.execute(
@@ -288,6 +325,7 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
             process_started_at TEXT,
             created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
             completed_at TEXT,
+            status_reason TEXT,
             FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
         )",
         [],
@@ -304,6 +342,7 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
         [],
     );
+    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN status_reason TEXT", []);
 
     // Drop old columns that are no longer needed (data is now read from JSONL files)
     // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
@@ -318,6 +357,9 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     );
 
+    // Track running Claude processes so they can be re-adopted after a restart
+    persistence::init_process_table(&conn)?;
+
     // Create trigger to update the updated_at timestamp
     conn.execute(
         "CREATE TRIGGER IF NOT EXISTS update_agent_timestamp 
@@ -421,6 +463,9 @@ pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
         [],
     )?;
 
+    // Create audit table for tool permission decisions
+    init_permission_tables(&conn)?;
+
     Ok(conn)
 }
 
@@ -430,7 +475,7 @@ pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare("SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, created_at, updated_at FROM agents ORDER BY created_at DESC")
+        .prepare("SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes, created_at, updated_at FROM agents ORDER BY created_at DESC")
         .map_err(|e| e.to_string())?;
 
     let agents = stmt
@@ -448,8 +493,10 @@ pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
                 enable_file_read: row.get::<_, bool>(7).unwrap_or(true),
                 enable_file_write: row.get::<_, bool>(8).unwrap_or(true),
                 enable_network: row.get::<_, bool>(9).unwrap_or(false),
-                created_at: row.get(10)?,
-                updated_at: row.get(11)?,
+                permission_mode: PermissionMode::from_column(row.get(10)?),
+                limits: ResourceLimits::from_row(row, 11)?,
+                created_at: row.get(15)?,
+                updated_at: row.get(16)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -472,17 +519,21 @@ pub async fn create_agent(
     enable_file_read: Option<bool>,
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
+    permission_mode: Option<PermissionMode>,
+    limits: Option<ResourceLimits>,
 ) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
+    let permission_mode = permission_mode.unwrap_or_default().to_column();
+    let limits = limits.unwrap_or_default();
     let sandbox_enabled = sandbox_enabled.unwrap_or(true);
     let enable_file_read = enable_file_read.unwrap_or(true);
     let enable_file_write = enable_file_write.unwrap_or(true);
     let enable_network = enable_network.unwrap_or(false);
 
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network],
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, limits.max_runtime_secs, limits.max_cpu_secs, limits.max_memory_mb, limits.max_processes],
     )
     .map_err(|e| e.to_string())?;
 
@@ -491,7 +542,7 @@ pub async fn create_agent(
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, created_at, updated_at FROM agents WHERE id = ?1",
+            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes, created_at, updated_at FROM agents WHERE id = ?1",
             params![id],
             |row| {
                 Ok(Agent {
@@ -505,8 +556,10 @@ pub async fn create_agent(
                     enable_file_read: row.get(7)?,
                     enable_file_write: row.get(8)?,
                     enable_network: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
+                    permission_mode: PermissionMode::from_column(row.get(10)?),
+                    limits: ResourceLimits::from_row(row, 11)?,
+                    created_at: row.get(15)?,
+                    updated_at: row.get(16)?,
                 })
             },
         )
@@ -529,6 +582,8 @@ pub async fn update_agent(
     enable_file_read: Option<bool>,
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
+    permission_mode: Option<PermissionMode>,
+    limits: Option<ResourceLimits>,
 ) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
@@ -566,6 +621,23 @@ pub async fn update_agent(
         query.push_str(&format!(", enable_network = ?{}", param_count));
         params_vec.push(Box::new(en));
     }
+    if let Some(pm) = permission_mode {
+        param_count += 1;
+        query.push_str(&format!(", permission_mode = ?{}", param_count));
+        params_vec.push(Box::new(pm.to_column()));
+    }
+    if let Some(limits) = limits {
+        for (column, value) in [
+            ("max_runtime_secs", limits.max_runtime_secs),
+            ("max_cpu_secs", limits.max_cpu_secs),
+            ("max_memory_mb", limits.max_memory_mb),
+            ("max_processes", limits.max_processes),
+        ] {
+            param_count += 1;
+            query.push_str(&format!(", {} = ?{}", column, param_count));
+            params_vec.push(Box::new(value));
+        }
+    }
 
     param_count += 1;
     query.push_str(&format!(" WHERE id = ?{}", param_count));
@@ -580,7 +652,7 @@ pub async fn update_agent(
     // Fetch the updated agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, created_at, updated_at FROM agents WHERE id = ?1",
+            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes, created_at, updated_at FROM agents WHERE id = ?1",
             params![id],
             |row| {
                 Ok(Agent {
@@ -594,8 +666,10 @@ pub async fn update_agent(
                     enable_file_read: row.get(7)?,
                     enable_file_write: row.get(8)?,
                     enable_network: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
+                    permission_mode: PermissionMode::from_column(row.get(10)?),
+                    limits: ResourceLimits::from_row(row, 11)?,
+                    created_at: row.get(15)?,
+                    updated_at: row.get(16)?,
                 })
             },
         )
@@ -622,7 +696,7 @@ pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String>
 
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, created_at, updated_at FROM agents WHERE id = ?1",
+            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes, created_at, updated_at FROM agents WHERE id = ?1",
             params![id],
             |row| {
                 Ok(Agent {
@@ -636,8 +710,10 @@ pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String>
                     enable_file_read: row.get::<_, bool>(7).unwrap_or(true),
                     enable_file_write: row.get::<_, bool>(8).unwrap_or(true),
                     enable_network: row.get::<_, bool>(9).unwrap_or(false),
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
+                    permission_mode: PermissionMode::from_column(row.get(10)?),
+                    limits: ResourceLimits::from_row(row, 11)?,
+                    created_at: row.get(15)?,
+                    updated_at: row.get(16)?,
                 })
             },
         )
@@ -655,10 +731,10 @@ pub async fn list_agent_runs(
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let query = if agent_id.is_some() {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
+        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at, status_reason 
          FROM agent_runs WHERE agent_id = ?1 ORDER BY created_at DESC"
     } else {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
+        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at, status_reason 
          FROM agent_runs ORDER BY created_at DESC"
     };
 
@@ -685,6 +761,7 @@ pub async fn list_agent_runs(
             process_started_at: row.get(10)?,
             created_at: row.get(11)?,
             completed_at: row.get(12)?,
+            status_reason: row.get(13)?,
         })
     };
 
@@ -707,7 +784,7 @@ pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun,
 
     let run = conn
         .query_row(
-            "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
+            "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at, status_reason 
              FROM agent_runs WHERE id = ?1",
             params![id],
             |row| {
@@ -725,6 +802,7 @@ pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun,
                     process_started_at: row.get(10)?,
                     created_at: row.get(11)?,
                     completed_at: row.get(12)?,
+                    status_reason: row.get(13)?,
                 })
             },
         )
@@ -776,6 +854,7 @@ pub async fn execute_agent(
     // Get the agent from database
     let agent = get_agent(db.clone(), agent_id).await?;
     let execution_model = model.unwrap_or(agent.model.clone());
+    let permission_args = agent.permission_mode.args();
 
     // Create a new run record
     let run_id = {
@@ -996,7 +1075,8 @@ pub async fn execute_agent(
         let mut test_cmd = std::process::Command::new(&claude_path);
         test_cmd
             .arg("-p")
-            .arg(&task)
+            .arg("--input-format")
+            .arg("stream-json")
             .arg("--system-prompt")
             .arg(&agent.system_prompt)
             .arg("--model")
@@ -1004,15 +1084,27 @@ pub async fn execute_agent(
             .arg("--output-format")
             .arg("stream-json")
             .arg("--verbose")
-            .arg("--dangerously-skip-permissions")
-            .current_dir(&project_path);
+            .args(&permission_args)
+            .current_dir(&project_path)
+            .stdin(Stdio::piped());
 
-        info!("🧪 Testing command: claude -p \"{}\" --system-prompt \"{}\" --model {} --output-format stream-json --verbose --dangerously-skip-permissions", 
-              task, agent.system_prompt, execution_model);
+        info!("🧪 Testing command: claude -p --input-format stream-json --system-prompt \"{}\" --model {} --output-format stream-json --verbose {} with task \"{}\" on stdin", 
+              agent.system_prompt, execution_model, permission_args.join(" "), task);
 
         // Start the test process and give it 5 seconds to produce output
         match test_cmd.spawn() {
             Ok(mut child) => {
+                // Send the task as the only message; closing stdin ends the run
+                // after its turn, as with the agent's own process
+                if let Some(mut stdin) = child.stdin.take() {
+                    use std::io::Write;
+                    if let Err(e) =
+                        writeln!(stdin, "{}", crate::process::input::user_message(&task))
+                    {
+                        warn!("🧪 Failed to send the task to the test process: {}", e);
+                    }
+                }
+
                 // Wait for 5 seconds to see if it produces output
                 let start = std::time::Instant::now();
                 let mut output_received = false;
@@ -1083,9 +1175,10 @@ pub async fn execute_agent(
                             );
 
                         // Prepare the sandboxed command
-                        let args = vec![
+                        let mut args = vec![
                             "-p",
-                            &task,
+                            "--input-format",
+                            "stream-json",
                             "--system-prompt",
                             &agent.system_prompt,
                             "--model",
@@ -1093,8 +1186,8 @@ pub async fn execute_agent(
                             "--output-format",
                             "stream-json",
                             "--verbose",
-                            "--dangerously-skip-permissions",
                         ];
+                        args.extend(permission_args.iter().map(String::as_str));
 
                         let claude_path = match find_claude_binary(&app) {
                             Ok(path) => path,
@@ -1116,7 +1209,8 @@ pub async fn execute_agent(
                         };
                         let mut cmd = create_command_with_env(&claude_path);
                         cmd.arg("-p")
-                            .arg(&task)
+                            .arg("--input-format")
+                            .arg("stream-json")
                             .arg("--system-prompt")
                             .arg(&agent.system_prompt)
                             .arg("--model")
@@ -1124,7 +1218,7 @@ pub async fn execute_agent(
                             .arg("--output-format")
                             .arg("stream-json")
                             .arg("--verbose")
-                            .arg("--dangerously-skip-permissions")
+                            .args(&permission_args)
                             .current_dir(&project_path)
                             .stdout(Stdio::piped())
                             .stderr(Stdio::piped());
@@ -1148,7 +1242,8 @@ pub async fn execute_agent(
                 };
                 let mut cmd = create_command_with_env(&claude_path);
                 cmd.arg("-p")
-                    .arg(&task)
+                    .arg("--input-format")
+                    .arg("stream-json")
                     .arg("--system-prompt")
                     .arg(&agent.system_prompt)
                     .arg("--model")
@@ -1156,7 +1251,7 @@ pub async fn execute_agent(
                     .arg("--output-format")
                     .arg("stream-json")
                     .arg("--verbose")
-                    .arg("--dangerously-skip-permissions")
+                    .args(&permission_args)
                     .current_dir(&project_path)
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped());
@@ -1178,7 +1273,8 @@ pub async fn execute_agent(
         };
         let mut cmd = create_command_with_env(&claude_path);
         cmd.arg("-p")
-            .arg(&task)
+            .arg("--input-format")
+            .arg("stream-json")
             .arg("--system-prompt")
             .arg(&agent.system_prompt)
             .arg("--model")
@@ -1186,22 +1282,48 @@ pub async fn execute_agent(
             .arg("--output-format")
             .arg("stream-json")
             .arg("--verbose")
-            .arg("--dangerously-skip-permissions")
+            .args(&permission_args)
             .current_dir(&project_path)
-            .stdin(Stdio::null()) // Don't pipe stdin - we have no input to send
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
         cmd
     };
 
-    // Spawn the process
+    // Contain the process in a cgroup enforcing the agent's memory and process
+    // limits, which it joins before it runs anything
+    let cgroup = match Cgroup::create(&format!("run-{}", run_id), &agent.limits) {
+        Ok(cgroup) => cgroup,
+        Err(e) => {
+            warn!(
+                "⚠️ No cgroup for run {}, limits are enforced by polling: {}",
+                run_id, e
+            );
+            None
+        }
+    };
+    if let Some(cgroup) = &cgroup {
+        cgroup.apply(&mut cmd);
+    }
+
+    // Spawn the process in its own process group so the processes it starts
+    // can be terminated with it
     info!("🚀 Spawning Claude process...");
-    let mut child = cmd.spawn().map_err(|e| {
+    crate::process::group::isolate(&mut cmd);
+    crate::process::limits::apply_rlimits(&mut cmd, &agent.limits);
+    let mut child = cmd.stdin(Stdio::piped()).spawn().map_err(|e| {
         error!("❌ Failed to spawn Claude process: {}", e);
         format!("Failed to spawn Claude: {}", e)
     })?;
 
-    info!("🔌 Using Stdio::null() for stdin - no input expected");
+    // Keep stdin open so messages can be sent while the agent runs, starting with the task
+    let stdin = child.stdin.take().ok_or("Failed to get stdin")?;
+    let input = std::sync::Arc::new(crate::process::input::StreamInput::new(stdin));
+    if let Err(e) = input.send_user_message(&task).await {
+        error!("❌ Failed to send task to Claude process: {}", e);
+        let _ = child.kill().await;
+        return Err(e);
+    }
+    info!("🔌 Sent task over stdin - stdin stays open for follow-up messages");
 
     // Get the PID and register the process
     let pid = child.id().unwrap_or(0);
@@ -1239,6 +1361,8 @@ pub async fn execute_agent(
     let registry_clone = registry.0.clone();
     let first_output = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let first_output_clone = first_output.clone();
+    let mut checkpointer = crate::commands::claude::StreamCheckpointer::new(project_path.clone());
+    let input_clone = input.clone();
 
     let stdout_task = tokio::spawn(async move {
         info!("📖 Starting to read Claude stdout...");
@@ -1263,6 +1387,17 @@ pub async fn execute_agent(
                 debug!("stdout[{}]: {}", line_count, line);
             }
 
+            // Surface tool permission requests instead of forwarding them as output
+            if let Some(request) = app_handle.state::<PermissionState>().register(
+                &PermissionSource::AgentRun(run_id),
+                &line,
+                &input_clone,
+            ) {
+                let _ = app_handle.emit(&format!("agent-permission-request:{}", run_id), &request);
+                let _ = app_handle.emit("agent-permission-request", &request);
+                continue;
+            }
+
             // Store live output in both local buffer and registry
             if let Ok(mut output) = live_output_clone.lock() {
                 output.push_str(&line);
@@ -1274,11 +1409,25 @@ pub async fn execute_agent(
 
             // Extract session ID from JSONL output
             if let Ok(json) = serde_json::from_str::<JsonValue>(&line) {
-                if let Some(sid) = json.get("sessionId").and_then(|s| s.as_str()) {
+                if let Some(sid) = json
+                    .get("sessionId")
+                    .or_else(|| json.get("session_id"))
+                    .and_then(|s| s.as_str())
+                {
                     if let Ok(mut current_session_id) = session_id_clone.lock() {
                         if current_session_id.is_empty() {
                             *current_session_id = sid.to_string();
                             info!("🔑 Extracted session ID: {}", sid);
+
+                            // Record it so the transcript can be followed after a restart
+                            if let Ok(conn) = app_handle.state::<AgentDb>().0.lock() {
+                                let _ = persistence::set_claude_session_id(
+                                    &conn,
+                                    ProcessKind::AgentRun,
+                                    &run_id.to_string(),
+                                    sid,
+                                );
+                            }
                         }
                     }
                 }
@@ -1288,6 +1437,12 @@ pub async fn execute_agent(
             let _ = app_handle.emit(&format!("agent-output:{}", run_id), &line);
             // Also emit to the generic event for backward compatibility
             let _ = app_handle.emit("agent-output", &line);
+
+            // Close stdin once the last turn has finished so the process exits
+            input_clone.observe_output(&line).await;
+
+            // Track the line for the session's automatic checkpoints
+            checkpointer.process_line(&app_handle, &line).await;
         }
 
         info!(
@@ -1343,10 +1498,36 @@ pub async fn execute_agent(
             task.clone(),
             execution_model.clone(),
             child,
+            input,
         )
         .map_err(|e| format!("Failed to register process: {}", e))?;
     info!("📋 Registered process in registry");
 
+    // Persist the process so it can be re-adopted if the app restarts while it runs
+    {
+        let record = ProcessRecord::spawned(
+            ProcessKind::AgentRun,
+            run_id.to_string(),
+            pid,
+            project_path.clone(),
+            format!("{:?}", cmd.as_std()),
+        );
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Err(e) = persistence::save_process(&conn, &record) {
+            warn!("Failed to persist process for run {}: {}", run_id, e);
+        }
+    }
+
+    // Enforce the agent's resource limits while it runs
+    enforce_limits(
+        &app,
+        registry.0.clone(),
+        run_id,
+        pid,
+        &agent.limits,
+        start_time,
+    );
+
     // Create variables we need for the spawned task
     let app_dir = app
         .path()
@@ -1355,6 +1536,8 @@ pub async fn execute_agent(
     let db_path = app_dir.join("agents.db");
 
     // Monitor process status and wait for completion
+    let registry_monitor = registry.0.clone();
+    let agent_limits = agent.limits.clone();
     tokio::spawn(async move {
         info!("🕐 Starting process monitoring...");
 
@@ -1390,30 +1573,28 @@ pub async fn execute_agent(
             warn!("   4. Network connectivity issues");
             warn!("   5. Authentication issues (API key not found/invalid)");
 
-            // Process timed out - kill it via PID
+            // Process timed out - kill it and everything it started
             warn!(
                 "🔍 Process likely stuck waiting for input, attempting to kill PID: {}",
                 pid
             );
-            let kill_result = std::process::Command::new("kill")
-                .arg("-TERM")
-                .arg(pid.to_string())
-                .output();
-
-            match kill_result {
-                Ok(output) if output.status.success() => {
-                    warn!("🔍 Successfully sent TERM signal to process");
-                }
-                Ok(_) => {
-                    warn!("🔍 Failed to kill process with TERM, trying KILL");
-                    let _ = std::process::Command::new("kill")
-                        .arg("-KILL")
-                        .arg(pid.to_string())
-                        .output();
-                }
-                Err(e) => {
-                    warn!("🔍 Error killing process: {}", e);
+            let grace = Connection::open(&db_path)
+                .map(|conn| kill_grace_period(&conn))
+                .unwrap_or(DEFAULT_KILL_GRACE_PERIOD);
+            match registry_monitor.kill_process(run_id, grace).await {
+                Ok(Some(report)) => {
+                    if report.terminated() {
+                        warn!(
+                            "🔍 Killed process tree, reaping {} descendants",
+                            report.reaped.len()
+                        );
+                    } else {
+                        warn!("🔍 {} processes survived the kill", report.survivors.len());
+                    }
+                    let _ = app.emit(&format!("agent-processes-reaped:{}", run_id), &report);
                 }
+                Ok(None) => warn!("🔍 Process {} is no longer registered", run_id),
+                Err(e) => warn!("🔍 Error killing process: {}", e),
             }
 
             // Update database
@@ -1422,8 +1603,15 @@ pub async fn execute_agent(
                     "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
                     params![run_id],
                 );
+                let _ =
+                    persistence::remove_process(&conn, ProcessKind::AgentRun, &run_id.to_string());
             }
 
+            expire_pending(
+                &app.state::<PermissionState>(),
+                &app.state::<AgentDb>(),
+                &PermissionSource::AgentRun(run_id),
+            );
             let _ = app.emit("agent-complete", false);
             let _ = app.emit(&format!("agent-complete:{}", run_id), false);
             return;
@@ -1447,18 +1635,52 @@ pub async fn execute_agent(
         // Wait for process completion and update status
         info!("✅ Claude process execution monitoring complete");
 
-        // Update the run record with session ID and mark as completed - open a new connection
+        // A process the kernel killed for exceeding the cgroup's memory limit
+        // fails the run
+        if let Some(mb) = agent_limits.max_memory_mb {
+            if cgroup
+                .as_ref()
+                .is_some_and(|cgroup| cgroup.memory_exceeded())
+            {
+                let reason = crate::process::limits::LimitExceeded::Memory(mb).to_string();
+                warn!("⛔ Run {}: {}", run_id, reason);
+                if let Ok(conn) = Connection::open(&db_path) {
+                    let _ = conn.execute(
+                        "UPDATE agent_runs SET status = 'failed', status_reason = ?1, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                        params![reason, run_id],
+                    );
+                }
+            }
+        }
+        drop(cgroup);
+
+        // Update the run record with session ID and mark as completed, unless it
+        // was cancelled or failed meanwhile - open a new connection
+        let mut succeeded = false;
         if let Ok(conn) = Connection::open(&db_path) {
             let _ = conn.execute(
-                "UPDATE agent_runs SET session_id = ?1, status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2",
+                "UPDATE agent_runs SET session_id = ?1, status = CASE WHEN status = 'running' THEN 'completed' ELSE status END, completed_at = COALESCE(completed_at, CURRENT_TIMESTAMP) WHERE id = ?2",
                 params![extracted_session_id, run_id],
             );
+            succeeded = conn
+                .query_row(
+                    "SELECT status FROM agent_runs WHERE id = ?1",
+                    params![run_id],
+                    |row| row.get::<_, String>(0),
+                )
+                .is_ok_and(|status| status == "completed");
+            let _ = persistence::remove_process(&conn, ProcessKind::AgentRun, &run_id.to_string());
         }
 
         // Cleanup will be handled by the cleanup_finished_processes function
+        expire_pending(
+            &app.state::<PermissionState>(),
+            &app.state::<AgentDb>(),
+            &PermissionSource::AgentRun(run_id),
+        );
 
-        let _ = app.emit("agent-complete", true);
-        let _ = app.emit(&format!("agent-complete:{}", run_id), true);
+        let _ = app.emit("agent-complete", succeeded);
+        let _ = app.emit(&format!("agent-complete:{}", run_id), succeeded);
     });
 
     Ok(run_id)
@@ -1470,7 +1692,7 @@ pub async fn list_running_sessions(db: State<'_, AgentDb>) -> Result<Vec<AgentRu
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn.prepare(
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
+        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at, status_reason 
          FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC"
     ).map_err(|e| e.to_string())?;
 
@@ -1496,6 +1718,7 @@ pub async fn list_running_sessions(db: State<'_, AgentDb>) -> Result<Vec<AgentRu
                 process_started_at: row.get(10)?,
                 created_at: row.get(11)?,
                 completed_at: row.get(12)?,
+                status_reason: row.get(13)?,
             })
         })
         .map_err(|e| e.to_string())?
@@ -1511,29 +1734,33 @@ pub async fn kill_agent_session(
     app: AppHandle,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
+    permissions: State<'_, PermissionState>,
     run_id: i64,
 ) -> Result<bool, String> {
     info!("Attempting to kill agent session {}", run_id);
+    let grace = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        kill_grace_period(&conn)
+    };
 
     // First try to kill using the process registry
-    let killed_via_registry = match registry.0.kill_process(run_id).await {
-        Ok(success) => {
-            if success {
-                info!("Successfully killed process {} via registry", run_id);
-                true
-            } else {
-                warn!("Process {} not found in registry", run_id);
-                false
-            }
+    let mut report = match registry.0.kill_process(run_id, grace).await {
+        Ok(Some(report)) => {
+            info!("Killed process tree of {} via registry", run_id);
+            Some(report)
+        }
+        Ok(None) => {
+            warn!("Process {} not found in registry", run_id);
+            None
         }
         Err(e) => {
             warn!("Failed to kill process {} via registry: {}", run_id, e);
-            false
+            None
         }
     };
 
     // If registry kill didn't work, try fallback with PID from database
-    if !killed_via_registry {
+    if report.is_none() {
         let pid_result = {
             let conn = db.0.lock().map_err(|e| e.to_string())?;
             conn.query_row(
@@ -1546,9 +1773,15 @@ pub async fn kill_agent_session(
 
         if let Some(pid) = pid_result {
             info!("Attempting fallback kill for PID {} from database", pid);
-            let _ = registry.0.kill_process_by_pid(run_id, pid as u32)?;
+            report = Some(
+                registry
+                    .0
+                    .kill_process_by_pid(run_id, pid as u32, grace)
+                    .await?,
+            );
         }
     }
+    let killed = report.as_ref().is_some_and(|r| r.terminated());
 
     // Update the database to mark as cancelled
     let conn = db.0.lock().map_err(|e| e.to_string())?;
@@ -1556,11 +1789,18 @@ pub async fn kill_agent_session(
         "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
         params![run_id],
     ).map_err(|e| e.to_string())?;
+    persistence::remove_process(&conn, ProcessKind::AgentRun, &run_id.to_string())?;
+
+    drop(conn);
+    expire_pending(&permissions, &db, &PermissionSource::AgentRun(run_id));
 
     // Emit cancellation event with run_id for proper isolation
     let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    if let Some(report) = &report {
+        let _ = app.emit(&format!("agent-processes-reaped:{}", run_id), report);
+    }
 
-    Ok(updated > 0 || killed_via_registry)
+    Ok(updated > 0 || killed)
 }
 
 /// Get the status of a specific agent session
@@ -1648,6 +1888,236 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
     Ok(cleaned_up)
 }
 
+/// Re-adopt agent runs whose Claude process outlived the previous run of the app
+///
+/// Runs whose process is still alive are registered again and their output is
+/// followed through the session transcript; the rest are marked failed with the
+/// reason they could not be re-adopted.
+pub fn recover_agent_runs(app: &AppHandle) -> Result<(), String> {
+    let (records, running_runs) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let records = persistence::load_processes(&conn, ProcessKind::AgentRun)?;
+        let mut stmt = conn
+            .prepare("SELECT id FROM agent_runs WHERE status = 'running'")
+            .map_err(|e| e.to_string())?;
+        let running_runs = stmt
+            .query_map([], |row| row.get::<_, i64>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        (records, running_runs)
+    };
+
+    let mut adopted = Vec::new();
+    for record in records {
+        let Ok(run_id) = record.key.parse::<i64>() else {
+            continue;
+        };
+        let result = match record.verify_identity() {
+            Ok(()) => adopt_agent_run(app, run_id, record.clone()),
+            Err(reason) => Err(reason),
+        };
+        match result {
+            Ok(()) => {
+                info!("Re-adopted agent run {} (PID {})", run_id, record.pid);
+                adopted.push(run_id);
+            }
+            Err(reason) => {
+                warn!("Could not re-adopt agent run {}: {}", run_id, reason);
+                let db = app.state::<AgentDb>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                conn.execute(
+                    "UPDATE agent_runs SET session_id = COALESCE(?1, session_id), status = 'failed', status_reason = ?2, completed_at = CURRENT_TIMESTAMP WHERE id = ?3 AND status = 'running'",
+                    params![record.claude_session_id, reason, run_id],
+                )
+                .map_err(|e| e.to_string())?;
+                persistence::remove_process(&conn, ProcessKind::AgentRun, &record.key)?;
+            }
+        }
+    }
+
+    // Runs still marked running without a recorded process were interrupted
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    for run_id in running_runs {
+        if !adopted.contains(&run_id) {
+            conn.execute(
+                "UPDATE agent_runs SET status = 'failed', status_reason = ?1, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                params!["The process was not recorded and could not be re-adopted after a restart", run_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Watch a run's process tree, killing it and failing the run once it goes
+/// over one of `limits`
+fn enforce_limits(
+    app: &AppHandle,
+    registry: std::sync::Arc<crate::process::ProcessRegistry>,
+    run_id: i64,
+    pid: u32,
+    limits: &ResourceLimits,
+    started: std::time::Instant,
+) {
+    if limits.is_unlimited() {
+        return;
+    }
+    let limits = limits.clone();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Some(exceeded) = crate::process::limits::watch(pid, &limits, started).await else {
+            return;
+        };
+        warn!("⛔ Run {}: {}", run_id, exceeded);
+
+        let db = app.state::<AgentDb>();
+        let grace =
+            db.0.lock()
+                .map(|conn| kill_grace_period(&conn))
+                .unwrap_or(DEFAULT_KILL_GRACE_PERIOD);
+        if let Ok(Some(report)) = registry.kill_process(run_id, grace).await {
+            let _ = app.emit(&format!("agent-processes-reaped:{}", run_id), &report);
+        }
+        if let Ok(conn) = db.0.lock() {
+            let _ = conn.execute(
+                "UPDATE agent_runs SET status = 'failed', status_reason = ?1, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                params![exceeded.to_string(), run_id],
+            );
+        }
+        let _ = app.emit(
+            &format!("agent-limit-exceeded:{}", run_id),
+            exceeded.to_string(),
+        );
+    });
+}
+
+/// Register a live run's process again and follow its output until it exits
+fn adopt_agent_run(app: &AppHandle, run_id: i64, record: ProcessRecord) -> Result<(), String> {
+    let (agent_id, agent_name, task, model, limits) = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.query_row(
+            "SELECT r.agent_id, r.agent_name, r.task, r.model, a.max_runtime_secs, a.max_cpu_secs, a.max_memory_mb, a.max_processes FROM agent_runs r LEFT JOIN agents a ON a.id = r.agent_id WHERE r.id = ?1 AND r.status = 'running'",
+            params![run_id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    ResourceLimits::from_row(row, 4)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("Run is no longer marked running: {}", e))?
+    };
+    let started_at = chrono::DateTime::parse_from_rfc3339(&record.started_at)
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .unwrap_or_else(|_| chrono::Utc::now());
+    // The wall-clock limit counts from when the run started, not from now
+    let started = (chrono::Utc::now() - started_at)
+        .to_std()
+        .ok()
+        .and_then(|elapsed| std::time::Instant::now().checked_sub(elapsed))
+        .unwrap_or_else(std::time::Instant::now);
+
+    // The process is normally still in the cgroup created when it was
+    // spawned, which is reused; otherwise only the process itself is moved in
+    let cgroup = match Cgroup::create(&format!("run-{}", run_id), &limits) {
+        Ok(Some(cgroup)) => match cgroup.add(record.pid) {
+            Ok(()) => Some(cgroup),
+            Err(e) => {
+                warn!(
+                    "⚠️ No cgroup for re-adopted run {}, limits are enforced by polling: {}",
+                    run_id, e
+                );
+                None
+            }
+        },
+        Ok(None) => None,
+        Err(e) => {
+            warn!(
+                "⚠️ No cgroup for re-adopted run {}, limits are enforced by polling: {}",
+                run_id, e
+            );
+            None
+        }
+    };
+
+    let registry = app
+        .state::<crate::process::ProcessRegistryState>()
+        .0
+        .clone();
+    registry.adopt_process(crate::process::ProcessInfo {
+        run_id,
+        agent_id,
+        agent_name,
+        pid: record.pid,
+        started_at,
+        project_path: record.project_path.clone(),
+        task,
+        model,
+    })?;
+    enforce_limits(app, registry.clone(), run_id, record.pid, &limits, started);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        persistence::tail_session_file(&record, |line| {
+            let _ = registry.append_live_output(run_id, line);
+            let _ = app.emit(&format!("agent-output:{}", run_id), line);
+            let _ = app.emit("agent-output", line);
+        })
+        .await;
+
+        info!("Re-adopted agent run {} has exited", run_id);
+        // A process the kernel killed for exceeding the cgroup's memory limit
+        // fails the run
+        if let (Some(mb), Some(cgroup)) = (limits.max_memory_mb, &cgroup) {
+            if cgroup.memory_exceeded() {
+                let reason = crate::process::limits::LimitExceeded::Memory(mb).to_string();
+                warn!("⛔ Run {}: {}", run_id, reason);
+                if let Ok(conn) = app.state::<AgentDb>().0.lock() {
+                    let _ = conn.execute(
+                        "UPDATE agent_runs SET status = 'failed', status_reason = ?1, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                        params![reason, run_id],
+                    );
+                }
+            }
+        }
+        drop(cgroup);
+        if let Ok(conn) = app.state::<AgentDb>().0.lock() {
+            let _ = conn.execute(
+                "UPDATE agent_runs SET session_id = COALESCE(?1, session_id), status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                params![record.claude_session_id, run_id],
+            );
+            let _ = persistence::remove_process(&conn, ProcessKind::AgentRun, &record.key);
+        }
+        let _ = registry.unregister_process(run_id);
+
+        let _ = app.emit("agent-complete", true);
+        let _ = app.emit(&format!("agent-complete:{}", run_id), true);
+    });
+
+    Ok(())
+}
+
+/// Send a user message to a running agent session
+///
+/// The message is handled once the agent's current turn finishes.
+#[tauri::command]
+pub async fn send_agent_input(
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+    message: String,
+) -> Result<(), String> {
+    info!("Sending input to agent run {}", run_id);
+    registry.0.send_input(run_id, &message).await
+}
+
 /// Get live output from a running process
 #[tauri::command]
 pub async fn get_live_session_output(
@@ -1782,7 +2252,7 @@ pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, Str
     // Fetch the agent
     let agent = conn
         .query_row(
-            "SELECT name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network FROM agents WHERE id = ?1",
+            "SELECT name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes FROM agents WHERE id = ?1",
             params![id],
             |row| {
                 Ok(serde_json::json!({
@@ -1794,7 +2264,12 @@ pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, Str
                     "sandbox_enabled": row.get::<_, bool>(5)?,
                     "enable_file_read": row.get::<_, bool>(6)?,
                     "enable_file_write": row.get::<_, bool>(7)?,
-                    "enable_network": row.get::<_, bool>(8)?
+                    "enable_network": row.get::<_, bool>(8)?,
+                    "permission_mode": PermissionMode::from_column(row.get(9)?),
+                    "max_runtime_secs": row.get::<_, Option<u64>>(10)?,
+                    "max_cpu_secs": row.get::<_, Option<u64>>(11)?,
+                    "max_memory_mb": row.get::<_, Option<u64>>(12)?,
+                    "max_processes": row.get::<_, Option<u64>>(13)?
                 }))
             },
         )
@@ -1878,6 +2353,47 @@ pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Res
     Ok(())
 }
 
+/// How long killed processes get to exit after SIGTERM, unless configured
+pub const DEFAULT_KILL_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long killed processes get to exit after SIGTERM before SIGKILL is sent
+pub fn kill_grace_period(conn: &Connection) -> std::time::Duration {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'kill_grace_period_secs'",
+        [],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| value.parse().ok())
+    .map(std::time::Duration::from_secs)
+    .unwrap_or(DEFAULT_KILL_GRACE_PERIOD)
+}
+
+/// Get the seconds killed processes get to exit before being forced
+#[tauri::command]
+pub async fn get_kill_grace_period(db: State<'_, AgentDb>) -> Result<u64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(kill_grace_period(&conn).as_secs())
+}
+
+/// Set the seconds killed processes get to exit before being forced
+#[tauri::command]
+pub async fn set_kill_grace_period(db: State<'_, AgentDb>, seconds: u64) -> Result<(), String> {
+    if seconds > 300 {
+        return Err("The grace period cannot be longer than 5 minutes".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('kill_grace_period_secs', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![seconds.to_string()],
+    )
+    .map_err(|e| format!("Failed to save kill grace period: {}", e))?;
+
+    Ok(())
+}
+
 /// List all available Claude installations on the system
 #[tauri::command]
 pub async fn list_claude_installations(
@@ -1985,7 +2501,7 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
 
     // Create the agent
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
         params![
             final_name,
             agent_data.icon,
@@ -1995,7 +2511,12 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
             agent_data.sandbox_enabled,
             agent_data.enable_file_read,
             agent_data.enable_file_write,
-            agent_data.enable_network
+            agent_data.enable_network,
+            agent_data.permission_mode.to_column(),
+            agent_data.limits.max_runtime_secs,
+            agent_data.limits.max_cpu_secs,
+            agent_data.limits.max_memory_mb,
+            agent_data.limits.max_processes
         ],
     )
     .map_err(|e| format!("Failed to create agent: {}", e))?;
@@ -2005,7 +2526,7 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, created_at, updated_at FROM agents WHERE id = ?1",
+            "SELECT id, name, icon, system_prompt, default_task, model, sandbox_enabled, enable_file_read, enable_file_write, enable_network, permission_mode, max_runtime_secs, max_cpu_secs, max_memory_mb, max_processes, created_at, updated_at FROM agents WHERE id = ?1",
             params![id],
             |row| {
                 Ok(Agent {
@@ -2019,8 +2540,10 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
                     enable_file_read: row.get(7)?,
                     enable_file_write: row.get(8)?,
                     enable_network: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
+                    permission_mode: PermissionMode::from_column(row.get(10)?),
+                    limits: ResourceLimits::from_row(row, 11)?,
+                    created_at: row.get(15)?,
+                    updated_at: row.get(16)?,
                 })
             },
         )
@@ -1,5 +1,6 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
@@ -11,19 +12,118 @@ use tokio::process::{Child, Command};
 use tokio::sync::Mutex;
 use uuid;
 
-/// Global state to track current Claude process
+use crate::commands::agents::{kill_grace_period, AgentDb};
+use crate::commands::permissions::{
+    expire_pending, PermissionMode, PermissionSource, PermissionState,
+};
+use crate::process::input::StreamInput;
+use crate::process::persistence::{self, ProcessKind, ProcessRecord};
+
+/// A running Claude Code process
+pub struct ClaudeProcess {
+    /// None for a process re-adopted after the app restarted
+    pub child: Option<Child>,
+    pub pid: Option<u32>,
+    /// The process's stdin, for sending further messages while it runs; None
+    /// for a re-adopted process
+    pub input: Option<Arc<StreamInput>>,
+    /// Claude's own session ID, once known from the resumed session or the output
+    pub claude_session_id: Option<String>,
+}
+
+/// Global state to track running Claude processes
+///
+/// Processes are keyed by the session ID generated when they are spawned, and
+/// can also be looked up by Claude's own session ID once it is known.
 pub struct ClaudeProcessState {
-    pub current_process: Arc<Mutex<Option<Child>>>,
+    pub processes: Arc<Mutex<HashMap<String, ClaudeProcess>>>,
 }
 
 impl Default for ClaudeProcessState {
     fn default() -> Self {
         Self {
-            current_process: Arc::new(Mutex::new(None)),
+            processes: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
 
+impl ClaudeProcessState {
+    /// Remove a process by its session ID or Claude's session ID, returning
+    /// it with the session ID it was stored under
+    pub async fn take_process(&self, session_id: &str) -> Option<(String, ClaudeProcess)> {
+        let mut processes = self.processes.lock().await;
+        let key = if processes.contains_key(session_id) {
+            session_id.to_string()
+        } else {
+            processes
+                .iter()
+                .find(|(_, process)| process.claude_session_id.as_deref() == Some(session_id))
+                .map(|(key, _)| key.clone())?
+        };
+        processes.remove(&key).map(|process| (key, process))
+    }
+
+    /// Whether a process is running under this session ID or Claude's session ID
+    pub async fn is_running(&self, session_id: &str) -> bool {
+        self.processes.lock().await.iter().any(|(key, process)| {
+            key == session_id || process.claude_session_id.as_deref() == Some(session_id)
+        })
+    }
+
+    /// Record Claude's session ID for a running process
+    pub async fn set_claude_session_id(&self, session_id: &str, claude_session_id: String) {
+        if let Some(process) = self.processes.lock().await.get_mut(session_id) {
+            process.claude_session_id = Some(claude_session_id);
+        }
+    }
+
+    /// The session IDs a process's events are emitted under
+    pub async fn event_ids(&self, session_id: &str) -> Vec<String> {
+        let processes = self.processes.lock().await;
+        let claude_session_id = processes
+            .get(session_id)
+            .and_then(|process| process.claude_session_id.clone());
+        session_event_ids(session_id, claude_session_id.as_deref())
+    }
+
+    /// The stdin of a process, by its session ID or Claude's session ID, if it
+    /// accepts input
+    pub async fn input(&self, session_id: &str) -> Option<Arc<StreamInput>> {
+        self.processes
+            .lock()
+            .await
+            .iter()
+            .find(|(key, process)| {
+                key.as_str() == session_id
+                    || process.claude_session_id.as_deref() == Some(session_id)
+            })
+            .and_then(|(_, process)| process.input.clone())
+    }
+
+    /// Session IDs of the running processes
+    pub async fn running_sessions(&self) -> Vec<String> {
+        self.processes.lock().await.keys().cloned().collect()
+    }
+}
+
+/// A process's session ID, followed by Claude's session ID when known and different
+fn session_event_ids(session_id: &str, claude_session_id: Option<&str>) -> Vec<String> {
+    let mut ids = vec![session_id.to_string()];
+    if let Some(claude_session_id) = claude_session_id.filter(|id| *id != session_id) {
+        ids.push(claude_session_id.to_string());
+    }
+    ids
+}
+
+/// The `session_id` carried by a line of stream-json output
+fn stream_session_id(line: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()?
+        .get("session_id")?
+        .as_str()
+        .map(String::from)
+}
+
 /// Represents a project in the ~/.claude/projects directory
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
@@ -786,13 +886,17 @@ pub async fn load_session_history(
 }
 
 /// Execute a new interactive Claude Code session with streaming output
+///
+/// Returns the session ID to use with `cancel_claude_execution`. Without a
+/// `permission_mode`, tool use requests are sent to the frontend to approve.
 #[tauri::command]
 pub async fn execute_claude_code(
     app: AppHandle,
     project_path: String,
     prompt: String,
     model: String,
-) -> Result<(), String> {
+    permission_mode: Option<PermissionMode>,
+) -> Result<String, String> {
     log::info!(
         "Starting new Claude Code session in: {} with model: {}",
         project_path,
@@ -810,28 +914,33 @@ pub async fn execute_claude_code(
     };
 
     cmd.arg("-p")
-        .arg(&prompt)
+        .arg("--input-format")
+        .arg("stream-json")
         .arg("--model")
         .arg(&model)
         .arg("--output-format")
         .arg("stream-json")
         .arg("--verbose")
-        .arg("--dangerously-skip-permissions")
+        .args(permission_mode.unwrap_or_default().args())
         .current_dir(&project_path)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
-    spawn_claude_process(app, cmd).await
+    spawn_claude_process(app, cmd, project_path, prompt, None).await
 }
 
 /// Continue an existing Claude Code conversation with streaming output
+///
+/// Returns the session ID to use with `cancel_claude_execution`. Without a
+/// `permission_mode`, tool use requests are sent to the frontend to approve.
 #[tauri::command]
 pub async fn continue_claude_code(
     app: AppHandle,
     project_path: String,
     prompt: String,
     model: String,
-) -> Result<(), String> {
+    permission_mode: Option<PermissionMode>,
+) -> Result<String, String> {
     log::info!(
         "Continuing Claude Code conversation in: {} with model: {}",
         project_path,
@@ -850,21 +959,25 @@ pub async fn continue_claude_code(
 
     cmd.arg("-c") // Continue flag
         .arg("-p")
-        .arg(&prompt)
+        .arg("--input-format")
+        .arg("stream-json")
         .arg("--model")
         .arg(&model)
         .arg("--output-format")
         .arg("stream-json")
         .arg("--verbose")
-        .arg("--dangerously-skip-permissions")
+        .args(permission_mode.unwrap_or_default().args())
         .current_dir(&project_path)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
-    spawn_claude_process(app, cmd).await
+    spawn_claude_process(app, cmd, project_path, prompt, None).await
 }
 
 /// Resume an existing Claude Code session by ID with streaming output
+///
+/// Returns the session ID to use with `cancel_claude_execution`. Without a
+/// `permission_mode`, tool use requests are sent to the frontend to approve.
 #[tauri::command]
 pub async fn resume_claude_code(
     app: AppHandle,
@@ -872,7 +985,8 @@ pub async fn resume_claude_code(
     session_id: String,
     prompt: String,
     model: String,
-) -> Result<(), String> {
+    permission_mode: Option<PermissionMode>,
+) -> Result<String, String> {
     log::info!(
         "Resuming Claude Code session: {} in: {} with model: {}",
         session_id,
@@ -893,66 +1007,215 @@ pub async fn resume_claude_code(
     cmd.arg("--resume")
         .arg(&session_id)
         .arg("-p")
-        .arg(&prompt)
+        .arg("--input-format")
+        .arg("stream-json")
         .arg("--model")
         .arg(&model)
         .arg("--output-format")
         .arg("stream-json")
         .arg("--verbose")
-        .arg("--dangerously-skip-permissions")
+        .args(permission_mode.unwrap_or_default().args())
         .current_dir(&project_path)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
-    spawn_claude_process(app, cmd).await
+    spawn_claude_process(app, cmd, project_path, prompt, Some(session_id)).await
 }
 
-/// Cancel the currently running Claude Code execution
+/// Cancel a running Claude Code execution
+///
+/// `session_id` may be the ID returned when the execution started or Claude's
+/// own session ID. Other running executions are left alone.
 #[tauri::command]
-pub async fn cancel_claude_execution(
-    app: AppHandle,
-    session_id: Option<String>,
-) -> Result<(), String> {
+pub async fn cancel_claude_execution(app: AppHandle, session_id: String) -> Result<(), String> {
     log::info!(
-        "Cancelling Claude Code execution for session: {:?}",
+        "Cancelling Claude Code execution for session: {}",
         session_id
     );
 
+    let grace = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        kill_grace_period(&conn)
+    };
+
+    // The process is taken so it finishes as cancelled rather than completed,
+    // and put back if it cannot be killed
     let claude_state = app.state::<ClaudeProcessState>();
-    let mut current_process = claude_state.current_process.lock().await;
+    let Some((key, mut process)) = claude_state.take_process(&session_id).await else {
+        log::warn!(
+            "No active Claude process to cancel for session {}",
+            session_id
+        );
+        return Ok(());
+    };
 
-    if let Some(mut child) = current_process.take() {
-        // Try to get the PID before killing
-        let pid = child.id();
-        log::info!("Attempting to kill Claude process with PID: {:?}", pid);
+    log::info!(
+        "Attempting to kill Claude process with PID: {:?}",
+        process.pid
+    );
 
-        // Kill the process
-        match child.kill().await {
-            Ok(_) => {
-                log::info!("Successfully killed Claude process");
+    // Kill the process and everything it started
+    let Some(pid) = process.pid else {
+        let error = format!(
+            "Failed to kill Claude process: the process ID of {} is unknown",
+            key
+        );
+        claude_state.processes.lock().await.insert(key, process);
+        return Err(error);
+    };
+    let report = crate::process::group::terminate_tree(pid, grace).await;
+    report.log(&format!("session {}", key));
+    if let Some(child) = process.child.as_mut() {
+        // Reap the child so it does not linger as a zombie
+        let _ = child.try_wait();
+    }
+    let ids = session_event_ids(&key, process.claude_session_id.as_deref());
+    for id in &ids {
+        let _ = app.emit(&format!("claude-processes-reaped:{}", id), &report);
+    }
+    if !report.terminated() {
+        log::error!("Failed to kill Claude process for session {}", key);
+        claude_state.processes.lock().await.insert(key, process);
+        return Err(format!(
+            "Failed to kill Claude process: {} processes survived",
+            report.survivors.len()
+        ));
+    }
+    log::info!("Successfully killed Claude process for session {}", key);
+    forget_process(&app, &key);
+    expire_pending(
+        &app.state::<PermissionState>(),
+        &app.state::<AgentDb>(),
+        &PermissionSource::Session(key.clone()),
+    );
 
-                // If we have a session ID, emit session-specific events
-                if let Some(sid) = session_id {
-                    let _ = app.emit(&format!("claude-cancelled:{}", sid), true);
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    let _ = app.emit(&format!("claude-complete:{}", sid), false);
-                }
+    // Emit session-specific events under every ID the session is known by
+    for id in &ids {
+        let _ = app.emit(&format!("claude-cancelled:{}", id), true);
+    }
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    for id in &ids {
+        let _ = app.emit(&format!("claude-complete:{}", id), false);
+    }
 
-                // Also emit generic events for backward compatibility
-                let _ = app.emit("claude-cancelled", true);
-                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                let _ = app.emit("claude-complete", false);
-                Ok(())
+    // Also emit generic events for backward compatibility
+    let _ = app.emit("claude-cancelled", true);
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let _ = app.emit("claude-complete", false);
+    Ok(())
+}
+
+/// Send a user message to a running Claude Code execution
+///
+/// `session_id` may be the ID returned when the execution started or Claude's
+/// own session ID. The message is handled once the current turn finishes.
+#[tauri::command]
+pub async fn send_claude_input(
+    app: AppHandle,
+    session_id: String,
+    message: String,
+) -> Result<(), String> {
+    log::info!("Sending input to Claude session: {}", session_id);
+
+    let input = app
+        .state::<ClaudeProcessState>()
+        .input(&session_id)
+        .await
+        .ok_or_else(|| format!("No running Claude session accepting input: {}", session_id))?;
+    input.send_user_message(&message).await
+}
+
+/// List the session IDs of running Claude Code executions
+#[tauri::command]
+pub async fn list_running_claude_sessions(app: AppHandle) -> Result<Vec<String>, String> {
+    Ok(app.state::<ClaudeProcessState>().running_sessions().await)
+}
+
+/// Forget the persisted record of a process that has finished
+fn forget_process(app: &AppHandle, session_id: &str) {
+    if let Ok(conn) = app.state::<AgentDb>().0.lock() {
+        if let Err(e) = persistence::remove_process(&conn, ProcessKind::ClaudeSession, session_id) {
+            log::warn!("Failed to forget Claude process {}: {}", session_id, e);
+        }
+    }
+}
+
+/// Re-adopt Claude Code executions whose process outlived the previous run of the app
+///
+/// Live processes are tracked again under their session ID, with their output
+/// followed through the session transcript. They can be cancelled but not sent
+/// input. Records of processes that are gone are dropped.
+pub async fn recover_claude_sessions(app: AppHandle) -> Result<(), String> {
+    let records = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        persistence::load_processes(&conn, ProcessKind::ClaudeSession)?
+    };
+
+    for record in records {
+        let claude_state = app.state::<ClaudeProcessState>();
+        let mut processes = claude_state.processes.lock().await;
+        // Already tracked, having been started since the app launched
+        if processes.contains_key(&record.key) {
+            continue;
+        }
+
+        if let Err(reason) = record.verify_identity() {
+            log::warn!(
+                "Claude session {} did not survive the restart: {}",
+                record.key,
+                reason
+            );
+            forget_process(&app, &record.key);
+            continue;
+        }
+
+        log::info!(
+            "Re-adopted Claude session {} (PID {})",
+            record.key,
+            record.pid
+        );
+        processes.insert(
+            record.key.clone(),
+            ClaudeProcess {
+                child: None,
+                pid: Some(record.pid),
+                input: None,
+                claude_session_id: record.claude_session_id.clone(),
+            },
+        );
+        drop(processes);
+
+        let app_handle = app.clone();
+        tokio::spawn(async move {
+            let ids = session_event_ids(&record.key, record.claude_session_id.as_deref());
+            persistence::tail_session_file(&record, |line| {
+                for id in &ids {
+                    let _ = app_handle.emit(&format!("claude-output:{}", id), line);
+                }
+                let _ = app_handle.emit("claude-output", line);
+            })
+            .await;
+
+            // If the session is gone, it was cancelled and the events were already sent
+            if app_handle
+                .state::<ClaudeProcessState>()
+                .take_process(&record.key)
+                .await
+                .is_none()
+            {
+                return;
             }
-            Err(e) => {
-                log::error!("Failed to kill Claude process: {}", e);
-                Err(format!("Failed to kill Claude process: {}", e))
+            forget_process(&app_handle, &record.key);
+            for id in &ids {
+                let _ = app_handle.emit(&format!("claude-complete:{}", id), true);
             }
-        }
-    } else {
-        log::warn!("No active Claude process to cancel");
-        Ok(())
+            let _ = app_handle.emit("claude-complete", true);
+        });
     }
+
+    Ok(())
 }
 
 /// Helper function to check if sandboxing should be used based on settings
@@ -1153,8 +1416,74 @@ fn get_claude_settings_sync(_app: &AppHandle) -> Result<ClaudeSettings, String>
     Ok(ClaudeSettings { data })
 }
 
+/// Feeds Claude's stream-json output to the checkpoint manager of the session
+/// it belongs to, so automatic checkpoints follow the session's strategy
+/// without the frontend forwarding every message
+pub(crate) struct StreamCheckpointer {
+    project_path: String,
+    manager: Option<Arc<crate::checkpoint::manager::CheckpointManager>>,
+}
+
+impl StreamCheckpointer {
+    pub(crate) fn new(project_path: String) -> Self {
+        Self {
+            project_path,
+            manager: None,
+        }
+    }
+
+    /// Track one output line, emitting `checkpoint-created` when it leads to
+    /// a checkpoint. Lines before the session ID is known are skipped.
+    pub(crate) async fn process_line(&mut self, app: &AppHandle, line: &str) {
+        if self.manager.is_none() {
+            let Some(session_id) = stream_session_id(line) else {
+                return;
+            };
+
+            let project_id = persistence::project_dir_name(&self.project_path);
+            match app
+                .state::<crate::checkpoint::state::CheckpointState>()
+                .get_or_create_manager(session_id, project_id, PathBuf::from(&self.project_path))
+                .await
+            {
+                Ok(manager) => self.manager = Some(manager),
+                Err(e) => {
+                    log::warn!("Failed to get checkpoint manager for stream: {}", e);
+                    return;
+                }
+            }
+        }
+        let Some(manager) = &self.manager else {
+            return;
+        };
+
+        match manager.process_stream_message(line).await {
+            Ok(Some(result)) => {
+                let session_id = manager.session_id();
+                let _ = app.emit(&format!("checkpoint-created:{}", session_id), &result);
+                let _ = app.emit("checkpoint-created", &result);
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to track streamed message: {}", e),
+        }
+    }
+}
+
 /// Helper function to spawn Claude process and handle streaming
-async fn spawn_claude_process(app: AppHandle, mut cmd: Command) -> Result<(), String> {
+///
+/// The command must read `--input-format stream-json`; `prompt` is sent as its
+/// first message and stdin is kept open for more until the last turn ends.
+///
+/// Returns the session ID generated for the process. Other running processes
+/// are left alone. `claude_session_id` is the session being resumed, if any;
+/// otherwise it is learned from the output.
+async fn spawn_claude_process(
+    app: AppHandle,
+    mut cmd: Command,
+    project_path: String,
+    prompt: String,
+    claude_session_id: Option<String>,
+) -> Result<String, String> {
     use tokio::io::{AsyncBufReadExt, BufReader};
 
     // Generate a unique session ID for this Claude Code session
@@ -1167,15 +1496,26 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command) -> Result<(), St
         uuid::Uuid::new_v4().to_string()
     );
 
-    // Spawn the process
+    // Spawn the process in its own process group so the processes it starts
+    // can be terminated with it
+    crate::process::group::isolate(&mut cmd);
     let mut child = cmd
+        .stdin(Stdio::piped())
         .spawn()
         .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
 
-    // Get stdout and stderr
+    // Get stdin, stdout and stderr
+    let stdin = child.stdin.take().ok_or("Failed to get stdin")?;
     let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
 
+    // Send the prompt as the first message
+    let input = Arc::new(StreamInput::new(stdin));
+    if let Err(e) = input.send_user_message(&prompt).await {
+        let _ = child.kill().await;
+        return Err(e);
+    }
+
     // Get the child PID for logging
     let pid = child.id();
     log::info!(
@@ -1188,29 +1528,85 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command) -> Result<(), St
     let stdout_reader = BufReader::new(stdout);
     let stderr_reader = BufReader::new(stderr);
 
-    // Store the child process in the global state (for backward compatibility)
+    // Persist the process so it can be re-adopted if the app restarts while it
+    // runs; without a PID there is nothing to re-adopt
+    let record = pid.map(|pid| {
+        let mut record = ProcessRecord::spawned(
+            ProcessKind::ClaudeSession,
+            session_id.clone(),
+            pid,
+            project_path.clone(),
+            format!("{:?}", cmd.as_std()),
+        );
+        record.claude_session_id = claude_session_id.clone();
+        record
+    });
+
+    // Store the child process in the global state alongside any other sessions
     let claude_state = app.state::<ClaudeProcessState>();
-    {
-        let mut current_process = claude_state.current_process.lock().await;
-        // If there's already a process running, kill it first
-        if let Some(mut existing_child) = current_process.take() {
-            log::warn!("Killing existing Claude process before starting new one");
-            let _ = existing_child.kill().await;
+    claude_state.processes.lock().await.insert(
+        session_id.clone(),
+        ClaudeProcess {
+            child: Some(child),
+            pid,
+            input: Some(Arc::clone(&input)),
+            claude_session_id: claude_session_id.clone(),
+        },
+    );
+    if let (Some(record), Ok(conn)) = (record, app.state::<AgentDb>().0.lock()) {
+        if let Err(e) = persistence::save_process(&conn, &record) {
+            log::warn!("Failed to persist Claude process {}: {}", session_id, e);
         }
-        *current_process = Some(child);
     }
 
     // Spawn tasks to read stdout and stderr
     let app_handle = app.clone();
     let session_id_clone = session_id.clone();
     let stdout_task = tokio::spawn(async move {
+        let mut checkpointer = StreamCheckpointer::new(project_path);
+        let mut claude_session_id = claude_session_id;
         let mut lines = stdout_reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
             log::debug!("Claude stdout: {}", line);
+
+            // Surface tool permission requests instead of forwarding them as output
+            if let Some(request) = app_handle.state::<PermissionState>().register(
+                &PermissionSource::Session(session_id_clone.clone()),
+                &line,
+                &input,
+            ) {
+                for id in session_event_ids(&session_id_clone, claude_session_id.as_deref()) {
+                    let _ = app_handle.emit(&format!("claude-permission-request:{}", id), &request);
+                }
+                let _ = app_handle.emit("claude-permission-request", &request);
+                continue;
+            }
+
+            if claude_session_id.is_none() {
+                if let Some(id) = stream_session_id(&line) {
+                    app_handle
+                        .state::<ClaudeProcessState>()
+                        .set_claude_session_id(&session_id_clone, id.clone())
+                        .await;
+                    if let Ok(conn) = app_handle.state::<AgentDb>().0.lock() {
+                        let _ = persistence::set_claude_session_id(
+                            &conn,
+                            ProcessKind::ClaudeSession,
+                            &session_id_clone,
+                            &id,
+                        );
+                    }
+                    claude_session_id = Some(id);
+                }
+            }
             // Emit the line to the frontend with session isolation
-            let _ = app_handle.emit(&format!("claude-output:{}", session_id_clone), &line);
+            for id in session_event_ids(&session_id_clone, claude_session_id.as_deref()) {
+                let _ = app_handle.emit(&format!("claude-output:{}", id), &line);
+            }
             // Also emit to the generic event for backward compatibility
             let _ = app_handle.emit("claude-output", &line);
+            input.observe_output(&line).await;
+            checkpointer.process_line(&app_handle, &line).await;
         }
     });
 
@@ -1221,7 +1617,13 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command) -> Result<(), St
         while let Ok(Some(line)) = lines.next_line().await {
             log::error!("Claude stderr: {}", line);
             // Emit error lines to the frontend with session isolation
-            let _ = app_handle_stderr.emit(&format!("claude-error:{}", session_id_clone2), &line);
+            let ids = app_handle_stderr
+                .state::<ClaudeProcessState>()
+                .event_ids(&session_id_clone2)
+                .await;
+            for id in ids {
+                let _ = app_handle_stderr.emit(&format!("claude-error:{}", id), &line);
+            }
             // Also emit to the generic event for backward compatibility
             let _ = app_handle_stderr.emit("claude-error", &line);
         }
@@ -1229,41 +1631,49 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command) -> Result<(), St
 
     // Wait for the process to complete
     let app_handle_wait = app.clone();
-    let claude_state_wait = claude_state.current_process.clone();
     let session_id_clone3 = session_id.clone();
     tokio::spawn(async move {
         let _ = stdout_task.await;
         let _ = stderr_task.await;
 
-        // Get the child from the state to wait on it
-        let mut current_process = claude_state_wait.lock().await;
-        if let Some(mut child) = current_process.take() {
-            match child.wait().await {
+        // Take this session's child from the state to wait on it. If it is
+        // gone, the session was cancelled and the events were already sent.
+        let Some((_, mut process)) = app_handle_wait
+            .state::<ClaudeProcessState>()
+            .take_process(&session_id_clone3)
+            .await
+        else {
+            return;
+        };
+        forget_process(&app_handle_wait, &session_id_clone3);
+        expire_pending(
+            &app_handle_wait.state::<PermissionState>(),
+            &app_handle_wait.state::<AgentDb>(),
+            &PermissionSource::Session(session_id_clone3.clone()),
+        );
+        let ids = session_event_ids(&session_id_clone3, process.claude_session_id.as_deref());
+
+        let success = match process.child.as_mut() {
+            Some(child) => match child.wait().await {
                 Ok(status) => {
                     log::info!("Claude process exited with status: {}", status);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    let _ = app_handle_wait.emit(
-                        &format!("claude-complete:{}", session_id_clone3),
-                        status.success(),
-                    );
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", status.success());
+                    status.success()
                 }
                 Err(e) => {
                     log::error!("Failed to wait for Claude process: {}", e);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    let _ = app_handle_wait
-                        .emit(&format!("claude-complete:{}", session_id_clone3), false);
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", false);
+                    false
                 }
-            }
-        }
+            },
+            None => false,
+        };
 
-        // Clear the process from state
-        *current_process = None;
+        // Add a small delay to ensure all messages are processed
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        for id in &ids {
+            let _ = app_handle_wait.emit(&format!("claude-complete:{}", id), success);
+        }
+        // Also emit to the generic event for backward compatibility
+        let _ = app_handle_wait.emit("claude-complete", success);
     });
 
     // Return the session ID to the frontend
@@ -1272,7 +1682,7 @@ async fn spawn_claude_process(app: AppHandle, mut cmd: Command) -> Result<(), St
         session_id.clone(),
     );
 
-    Ok(())
+    Ok(session_id)
 }
 
 /// Lists files and directories in a given path
@@ -1533,17 +1943,28 @@ pub async fn create_checkpoint(
 #[tauri::command]
 pub async fn restore_checkpoint(
     app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    claude_state: tauri::State<'_, ClaudeProcessState>,
     checkpoint_id: String,
     session_id: String,
     project_id: String,
     project_path: String,
+    transcript: Option<crate::checkpoint::TranscriptRestoreMode>,
 ) -> Result<crate::checkpoint::CheckpointResult, String> {
     log::info!(
-        "Restoring checkpoint: {} for session: {}",
+        "Restoring checkpoint: {} for session: {} (transcript: {:?})",
         checkpoint_id,
-        session_id
+        session_id,
+        transcript
     );
 
+    // A running session keeps appending to its transcript
+    if transcript.is_some() && claude_state.is_running(&session_id).await {
+        return Err(format!(
+            "Session {} is running; cancel it before restoring its transcript",
+            session_id
+        ));
+    }
+
     let manager = app
         .get_or_create_manager(
             session_id.clone(),
@@ -1553,29 +1974,78 @@ pub async fn restore_checkpoint(
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
-    let result = manager
+    let mut result = manager
         .restore_checkpoint(&checkpoint_id)
         .await
         .map_err(|e| format!("Failed to restore checkpoint: {}", e))?;
 
-    // Update the session JSONL file with restored messages
-    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
-    let session_path = claude_dir
-        .join("projects")
-        .join(&result.checkpoint.project_id)
-        .join(format!("{}.jsonl", session_id));
+    // Bring the session transcript back to the checkpoint so a resumed
+    // conversation matches the restored files
+    if let Some(mode) = transcript {
+        result.transcript = Some(
+            manager
+                .restore_transcript(&checkpoint_id, mode)
+                .await
+                .map_err(|e| format!("Failed to restore session transcript: {}", e))?,
+        );
+    }
 
-    // The manager has already restored the messages internally,
-    // but we need to update the actual session file
-    let (_, _, messages) = manager
-        .storage
-        .load_checkpoint(&result.checkpoint.project_id, &session_id, &checkpoint_id)
-        .map_err(|e| format!("Failed to load checkpoint data: {}", e))?;
+    Ok(result)
+}
 
-    fs::write(&session_path, messages)
-        .map_err(|e| format!("Failed to update session file: {}", e))?;
+/// Previews what restoring a checkpoint would change, without touching any files
+#[tauri::command]
+pub async fn preview_restore_checkpoint(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    checkpoint_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+) -> Result<crate::checkpoint::RestorePlan, String> {
+    log::info!(
+        "Planning restore of checkpoint: {} for session: {}",
+        checkpoint_id,
+        session_id
+    );
 
-    Ok(result)
+    let manager = app
+        .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    manager
+        .plan_restore(&checkpoint_id)
+        .await
+        .map_err(|e| format!("Failed to plan restore: {}", e))
+}
+
+/// Restores selected files from a checkpoint without moving the timeline
+#[tauri::command]
+pub async fn restore_checkpoint_files(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    checkpoint_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    paths: Vec<String>,
+    force: bool,
+) -> Result<crate::checkpoint::PartialRestoreResult, String> {
+    log::info!(
+        "Restoring {} paths from checkpoint: {} for session: {}",
+        paths.len(),
+        checkpoint_id,
+        session_id
+    );
+
+    let manager = app
+        .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    manager
+        .restore_files(&checkpoint_id, &paths, force)
+        .await
+        .map_err(|e| format!("Failed to restore files: {}", e))
 }
 
 /// Lists all checkpoints for a session
@@ -1650,6 +2120,35 @@ pub async fn fork_from_checkpoint(
         .map_err(|e| format!("Failed to fork checkpoint: {}", e))
 }
 
+/// Merges one timeline branch into another with a three-way file merge
+#[tauri::command]
+pub async fn merge_checkpoints(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    ours_checkpoint_id: String,
+    theirs_checkpoint_id: String,
+    description: Option<String>,
+) -> Result<crate::checkpoint::MergeResult, String> {
+    log::info!(
+        "Merging checkpoint {} into {} for session: {}",
+        theirs_checkpoint_id,
+        ours_checkpoint_id,
+        session_id
+    );
+
+    let manager = app
+        .get_or_create_manager(session_id, project_id, PathBuf::from(project_path))
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    manager
+        .merge_checkpoints(&ours_checkpoint_id, &theirs_checkpoint_id, description)
+        .await
+        .map_err(|e| format!("Failed to merge checkpoints: {}", e))
+}
+
 /// Gets the timeline for a session
 #[tauri::command]
 pub async fn get_session_timeline(
@@ -1681,6 +2180,7 @@ pub async fn update_checkpoint_settings(
     project_path: String,
     auto_checkpoint_enabled: bool,
     checkpoint_strategy: String,
+    strategy_thresholds: Option<crate::checkpoint::StrategyThresholds>,
 ) -> Result<(), String> {
     use crate::checkpoint::CheckpointStrategy;
 
@@ -1691,6 +2191,9 @@ pub async fn update_checkpoint_settings(
         "per_prompt" => CheckpointStrategy::PerPrompt,
         "per_tool_use" => CheckpointStrategy::PerToolUse,
         "smart" => CheckpointStrategy::Smart,
+        "interval" => CheckpointStrategy::Interval,
+        "changed_lines" => CheckpointStrategy::ChangedLines,
+        "bash_pattern" => CheckpointStrategy::BashPattern,
         _ => {
             return Err(format!(
                 "Invalid checkpoint strategy: {}",
@@ -1704,12 +2207,115 @@ pub async fn update_checkpoint_settings(
         .await
         .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
 
+    if let Some(thresholds) = strategy_thresholds {
+        manager
+            .update_strategy_thresholds(thresholds)
+            .await
+            .map_err(|e| format!("Failed to update strategy thresholds: {}", e))?;
+    }
+
     manager
         .update_settings(auto_checkpoint_enabled, strategy)
         .await
         .map_err(|e| format!("Failed to update settings: {}", e))
 }
 
+/// Updates the checkpoint retention policy for a session and applies it
+#[tauri::command]
+pub async fn update_retention_policy(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    policy: crate::checkpoint::RetentionPolicy,
+) -> Result<usize, String> {
+    log::info!("Updating retention policy for session: {}", session_id);
+
+    let manager = app
+        .get_or_create_manager(session_id, project_id, PathBuf::from(&project_path))
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    manager
+        .update_retention_policy(policy)
+        .await
+        .map_err(|e| format!("Failed to update retention policy: {}", e))
+}
+
+/// Sets the tags and milestone flag of a checkpoint
+#[tauri::command]
+pub async fn update_checkpoint_labels(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    checkpoint_id: String,
+    session_id: String,
+    project_id: String,
+    project_path: String,
+    tags: Vec<String>,
+    milestone: bool,
+) -> Result<crate::checkpoint::Checkpoint, String> {
+    log::info!(
+        "Updating labels of checkpoint {} in session: {}",
+        checkpoint_id,
+        session_id
+    );
+
+    let manager = app
+        .get_or_create_manager(session_id, project_id, PathBuf::from(project_path))
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+
+    manager
+        .update_checkpoint_labels(&checkpoint_id, tags, milestone)
+        .await
+        .map_err(|e| format!("Failed to update checkpoint labels: {}", e))
+}
+
+/// Searches all checkpoint timelines of a project
+#[tauri::command]
+pub async fn search_checkpoints(
+    project_id: String,
+    query: crate::checkpoint::search::CheckpointQuery,
+) -> Result<Vec<crate::checkpoint::search::CheckpointReference>, String> {
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    log::info!("Searching checkpoints in project: {}", project_id);
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::new(claude_dir);
+
+    storage
+        .search_checkpoints(&project_id, &query)
+        .map_err(|e| format!("Failed to search checkpoints: {}", e))
+}
+
+/// Gets every checkpoint at which a file changed, with diffs against the previous version
+#[tauri::command]
+pub async fn get_file_history(
+    project_id: String,
+    session_id: Option<String>,
+    file_path: String,
+) -> Result<Vec<crate::checkpoint::history::FileHistoryEntry>, String> {
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    log::info!(
+        "Getting history of {} in project: {}",
+        file_path,
+        project_id
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::for_project(claude_dir, &project_id, None)
+        .map_err(|e| format!("Failed to open checkpoint storage: {}", e))?;
+
+    storage
+        .file_history(
+            &project_id,
+            session_id.as_deref(),
+            std::path::Path::new(&file_path),
+        )
+        .map_err(|e| format!("Failed to get file history: {}", e))
+}
+
 /// Gets diff between two checkpoints
 #[tauri::command]
 pub async fn get_checkpoint_diff(
@@ -1727,74 +2333,169 @@ pub async fn get_checkpoint_diff(
     );
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
-    let storage = CheckpointStorage::new(claude_dir);
+    let storage = CheckpointStorage::for_project(claude_dir, &project_id, None)
+        .map_err(|e| format!("Failed to open checkpoint storage: {}", e))?;
 
-    // Load both checkpoints
-    let (from_checkpoint, from_files, _) = storage
-        .load_checkpoint(&project_id, &session_id, &from_checkpoint_id)
+    // Load the full file state at both checkpoints
+    let (from_checkpoint, from_files) = storage
+        .load_file_states(&project_id, &session_id, &from_checkpoint_id)
         .map_err(|e| format!("Failed to load source checkpoint: {}", e))?;
-    let (to_checkpoint, to_files, _) = storage
-        .load_checkpoint(&project_id, &session_id, &to_checkpoint_id)
+    let (to_checkpoint, to_files) = storage
+        .load_file_states(&project_id, &session_id, &to_checkpoint_id)
         .map_err(|e| format!("Failed to load target checkpoint: {}", e))?;
 
-    // Build file maps
-    let mut from_map: std::collections::HashMap<PathBuf, &crate::checkpoint::FileSnapshot> =
-        std::collections::HashMap::new();
-    for file in &from_files {
-        from_map.insert(file.file_path.clone(), file);
-    }
+    Ok(crate::checkpoint::diff::diff_checkpoints(
+        &from_checkpoint,
+        &from_files,
+        &to_checkpoint,
+        &to_files,
+    ))
+}
 
-    let mut to_map: std::collections::HashMap<PathBuf, &crate::checkpoint::FileSnapshot> =
-        std::collections::HashMap::new();
-    for file in &to_files {
-        to_map.insert(file.file_path.clone(), file);
-    }
+/// Exports a session's checkpoint timeline to a portable archive file
+#[tauri::command]
+pub async fn export_checkpoint_archive(
+    session_id: String,
+    project_id: String,
+    output_path: String,
+) -> Result<crate::checkpoint::archive::ArchiveSummary, String> {
+    use crate::checkpoint::storage::CheckpointStorage;
 
-    // Calculate differences
-    let mut modified_files = Vec::new();
-    let mut added_files = Vec::new();
-    let mut deleted_files = Vec::new();
-
-    // Check for modified and deleted files
-    for (path, from_file) in &from_map {
-        if let Some(to_file) = to_map.get(path) {
-            if from_file.hash != to_file.hash {
-                // File was modified
-                let additions = to_file.content.lines().count();
-                let deletions = from_file.content.lines().count();
-
-                modified_files.push(crate::checkpoint::FileDiff {
-                    path: path.clone(),
-                    additions,
-                    deletions,
-                    diff_content: None, // TODO: Generate actual diff
-                });
-            }
-        } else {
-            // File was deleted
-            deleted_files.push(path.clone());
-        }
+    log::info!(
+        "Exporting checkpoint timeline for session: {} to {}",
+        session_id,
+        output_path
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::for_project(claude_dir, &project_id, None)
+        .map_err(|e| format!("Failed to open checkpoint storage: {}", e))?;
+
+    let (archive, summary) = storage
+        .export_timeline_archive(&project_id, &session_id)
+        .map_err(|e| format!("Failed to export timeline: {}", e))?;
+
+    fs::write(&output_path, archive).map_err(|e| format!("Failed to write archive: {}", e))?;
+
+    Ok(summary)
+}
+
+/// Imports a checkpoint timeline archive into a project
+#[tauri::command]
+pub async fn import_checkpoint_archive(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    archive_path: String,
+    project_id: String,
+    new_session_id: Option<String>,
+) -> Result<crate::checkpoint::archive::ArchiveSummary, String> {
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    log::info!(
+        "Importing checkpoint timeline from {} into project: {}",
+        archive_path,
+        project_id
+    );
+
+    let archive_size = fs::metadata(&archive_path)
+        .map_err(|e| format!("Failed to read archive: {}", e))?
+        .len();
+    if archive_size > crate::checkpoint::archive::MAX_ARCHIVE_SIZE {
+        return Err(format!(
+            "Archive is larger than {} bytes",
+            crate::checkpoint::archive::MAX_ARCHIVE_SIZE
+        ));
     }
+    let archive = fs::read(&archive_path).map_err(|e| format!("Failed to read archive: {}", e))?;
 
-    // Check for added files
-    for (path, _) in &to_map {
-        if !from_map.contains_key(path) {
-            added_files.push(path.clone());
-        }
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::for_project(claude_dir, &project_id, None)
+        .map_err(|e| format!("Failed to open checkpoint storage: {}", e))?;
+
+    let summary = storage
+        .import_timeline_archive(&archive, &project_id, new_session_id.as_deref())
+        .map_err(|e| format!("Failed to import timeline: {}", e))?;
+
+    // Drop any cached manager so the imported timeline is loaded fresh
+    app.remove_manager(&summary.session_id).await;
+
+    Ok(summary)
+}
+
+/// Verifies a session's checkpoint storage, optionally repairing it
+#[tauri::command]
+pub async fn verify_checkpoint_timeline(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    session_id: String,
+    project_id: String,
+    repair: bool,
+) -> Result<crate::checkpoint::TimelineVerifyReport, String> {
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    log::info!(
+        "Verifying checkpoint timeline for session: {} (repair: {})",
+        session_id,
+        repair
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::for_project(claude_dir, &project_id, None)
+        .map_err(|e| format!("Failed to open checkpoint storage: {}", e))?;
+
+    let report = storage
+        .verify_timeline(&project_id, &session_id, repair)
+        .map_err(|e| format!("Failed to verify timeline: {}", e))?;
+
+    if repair {
+        // Drop any cached manager so the repaired timeline is loaded fresh
+        app.remove_manager(&session_id).await;
     }
 
-    // Calculate token delta
-    let token_delta = (to_checkpoint.metadata.total_tokens as i64)
-        - (from_checkpoint.metadata.total_tokens as i64);
+    Ok(report)
+}
 
-    Ok(crate::checkpoint::CheckpointDiff {
-        from_checkpoint_id,
-        to_checkpoint_id,
-        modified_files,
-        added_files,
-        deleted_files,
-        token_delta,
-    })
+/// Moves per-session checkpoint content into the shared per-project stores
+#[tauri::command]
+pub async fn migrate_checkpoint_storage(
+) -> Result<crate::checkpoint::content_store::MigrationReport, String> {
+    use crate::checkpoint::storage::CheckpointStorage;
+
+    log::info!("Migrating checkpoint content to project stores");
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let storage = CheckpointStorage::new(claude_dir);
+
+    storage
+        .migrate_content_stores()
+        .map_err(|e| format!("Failed to migrate checkpoint storage: {}", e))
+}
+
+/// Selects the checkpoint storage backend for a project
+#[tauri::command]
+pub async fn set_checkpoint_backend(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    project_id: String,
+    project_path: String,
+    backend: crate::checkpoint::backend::BackendKind,
+) -> Result<crate::checkpoint::backend::BackendConfig, String> {
+    log::info!(
+        "Setting checkpoint backend for project {} to {:?}",
+        project_id,
+        backend
+    );
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let config = crate::checkpoint::backend::select_backend(
+        &claude_dir,
+        &project_id,
+        backend,
+        &PathBuf::from(project_path),
+    )
+    .map_err(|e| format!("Failed to set checkpoint backend: {}", e))?;
+
+    // Cached managers still hold the previous backend
+    app.remove_project_managers(&project_id).await;
+
+    Ok(config)
 }
 
 /// Tracks a message for checkpointing
@@ -1890,6 +2591,8 @@ pub async fn get_checkpoint_settings(
         "checkpoint_strategy": timeline.checkpoint_strategy,
         "total_checkpoints": timeline.total_checkpoints,
         "current_checkpoint_id": timeline.current_checkpoint_id,
+        "retention_policy": timeline.retention_policy,
+        "strategy_thresholds": timeline.strategy_thresholds,
     }))
 }
 
@@ -1912,10 +2615,17 @@ pub async fn get_checkpoint_state_stats(
 ) -> Result<serde_json::Value, String> {
     let active_count = app.active_count().await;
     let active_sessions = app.list_active_sessions().await;
+    let managers = app.manager_stats().await;
+    let total_message_bytes: usize = managers.iter().map(|m| m.usage.message_bytes).sum();
+    let (idle_timeout, max_managers) = app.limits();
 
     Ok(serde_json::json!({
         "active_managers": active_count,
         "active_sessions": active_sessions,
+        "managers": managers,
+        "total_message_bytes": total_message_bytes,
+        "idle_timeout_secs": idle_timeout.as_secs(),
+        "max_managers": max_managers,
     }))
 }
 
@@ -1,6 +1,7 @@
 pub mod agents;
 pub mod claude;
 pub mod mcp;
+pub mod permissions;
 pub mod sandbox;
 pub mod screenshot;
 pub mod usage;
@@ -0,0 +1,393 @@
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+use crate::process::input::StreamInput;
+
+/// How a Claude process is allowed to use tools
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum PermissionMode {
+    /// Every tool use is approved (`--dangerously-skip-permissions`)
+    Skip,
+    /// Only the listed tools may be used; anything else is denied
+    AllowList { tools: Vec<String> },
+    /// Tool use requests are sent to the frontend to approve or deny
+    #[default]
+    Interactive,
+}
+
+impl PermissionMode {
+    /// Command line arguments selecting this mode
+    ///
+    /// Interactive mode needs the process to read `--input-format stream-json`,
+    /// as its requests are answered over stdin.
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            PermissionMode::Skip => vec!["--dangerously-skip-permissions".to_string()],
+            PermissionMode::AllowList { tools } => {
+                vec!["--allowedTools".to_string(), tools.join(",")]
+            }
+            PermissionMode::Interactive => {
+                vec!["--permission-prompt-tool".to_string(), "stdio".to_string()]
+            }
+        }
+    }
+
+    /// Read a mode stored as JSON in a database column
+    ///
+    /// A missing value is the default mode, and one that cannot be read falls
+    /// back to `Interactive`, so nothing is auto-approved.
+    pub fn from_column(value: Option<String>) -> Self {
+        match value {
+            None => PermissionMode::default(),
+            Some(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Unreadable permission mode {:?}: {}", json, e);
+                PermissionMode::Interactive
+            }),
+        }
+    }
+
+    /// Serialize the mode for a database column
+    pub fn to_column(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// A tool use waiting for the user's decision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionRequest {
+    /// ID to answer the request with
+    pub id: String,
+    /// The Claude Code execution that asked, if any
+    pub session_id: Option<String>,
+    /// The agent run that asked, if any
+    pub run_id: Option<i64>,
+    pub tool_name: String,
+    pub input: JsonValue,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// A recorded decision on a tool use request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionDecision {
+    pub id: i64,
+    pub request_id: String,
+    pub session_id: Option<String>,
+    pub run_id: Option<i64>,
+    pub tool_name: String,
+    pub tool_input: String,
+    /// `allow`, `deny`, or `expired` when the process ended before a decision
+    pub decision: String,
+    pub reason: Option<String>,
+    pub requested_at: String,
+    pub decided_at: String,
+}
+
+/// Where a permission request came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PermissionSource {
+    Session(String),
+    AgentRun(i64),
+}
+
+struct PendingPermission {
+    request: PermissionRequest,
+    /// The ID the process gave the request
+    control_request_id: String,
+    input: Arc<StreamInput>,
+}
+
+/// Tool use requests awaiting a decision, across every running process
+#[derive(Default)]
+pub struct PermissionState {
+    pending: Mutex<HashMap<String, PendingPermission>>,
+}
+
+impl PermissionState {
+    /// Register a line of output if it is a tool permission request, returning
+    /// the request to surface to the frontend
+    ///
+    /// Requests arrive as `control_request` messages with the `can_use_tool`
+    /// subtype when the process runs with `--permission-prompt-tool stdio`.
+    pub fn register(
+        &self,
+        source: &PermissionSource,
+        line: &str,
+        input: &Arc<StreamInput>,
+    ) -> Option<PermissionRequest> {
+        let message: JsonValue = serde_json::from_str(line).ok()?;
+        if message.get("type")?.as_str()? != "control_request" {
+            return None;
+        }
+        let body = message.get("request")?;
+        if body.get("subtype")?.as_str()? != "can_use_tool" {
+            return None;
+        }
+
+        let (session_id, run_id) = match source {
+            PermissionSource::Session(id) => (Some(id.clone()), None),
+            PermissionSource::AgentRun(id) => (None, Some(*id)),
+        };
+        let request = PermissionRequest {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id,
+            run_id,
+            tool_name: body.get("tool_name")?.as_str()?.to_string(),
+            input: body.get("input").cloned().unwrap_or(JsonValue::Null),
+            requested_at: Utc::now(),
+        };
+
+        let pending = PendingPermission {
+            request: request.clone(),
+            control_request_id: message.get("request_id")?.as_str()?.to_string(),
+            input: Arc::clone(input),
+        };
+        self.pending
+            .lock()
+            .ok()?
+            .insert(request.id.clone(), pending);
+        Some(request)
+    }
+
+    /// Requests still waiting for a decision, oldest first
+    pub fn pending(&self) -> Vec<PermissionRequest> {
+        let Ok(pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let mut requests: Vec<_> = pending.values().map(|p| p.request.clone()).collect();
+        requests.sort_by_key(|r| r.requested_at);
+        requests
+    }
+
+    fn take(&self, id: &str) -> Option<PendingPermission> {
+        self.pending.lock().ok()?.remove(id)
+    }
+
+    /// Drop the requests of a process that has ended, returning them
+    fn take_source(&self, source: &PermissionSource) -> Vec<PermissionRequest> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let ids: Vec<String> = pending
+            .values()
+            .filter(|p| match source {
+                PermissionSource::Session(id) => p.request.session_id.as_ref() == Some(id),
+                PermissionSource::AgentRun(id) => p.request.run_id == Some(*id),
+            })
+            .map(|p| p.request.id.clone())
+            .collect();
+        ids.iter()
+            .filter_map(|id| pending.remove(id))
+            .map(|p| p.request)
+            .collect()
+    }
+}
+
+/// Create the audit table for permission decisions
+pub fn init_permission_tables(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS permission_decisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            request_id TEXT NOT NULL,
+            session_id TEXT,
+            run_id INTEGER,
+            tool_name TEXT NOT NULL,
+            tool_input TEXT NOT NULL,
+            decision TEXT NOT NULL,
+            reason TEXT,
+            requested_at TEXT NOT NULL,
+            decided_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn record_decision(
+    db: &AgentDb,
+    request: &PermissionRequest,
+    decision: &str,
+    reason: Option<&str>,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO permission_decisions (request_id, session_id, run_id, tool_name, tool_input, decision, reason, requested_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            request.id,
+            request.session_id,
+            request.run_id,
+            request.tool_name,
+            request.input.to_string(),
+            decision,
+            reason,
+            request.requested_at.to_rfc3339(),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Record the requests of an ended process as expired and forget them
+pub fn expire_pending(permissions: &PermissionState, db: &AgentDb, source: &PermissionSource) {
+    for request in permissions.take_source(source) {
+        if let Err(e) = record_decision(
+            db,
+            &request,
+            "expired",
+            Some("Process ended before a decision"),
+        ) {
+            warn!("Failed to record expired permission request: {}", e);
+        }
+    }
+}
+
+/// Approve or deny a pending tool use request
+#[tauri::command]
+pub async fn respond_to_permission_request(
+    db: State<'_, AgentDb>,
+    permissions: State<'_, PermissionState>,
+    request_id: String,
+    approve: bool,
+    reason: Option<String>,
+) -> Result<(), String> {
+    let pending = permissions
+        .take(&request_id)
+        .ok_or_else(|| format!("No pending permission request: {}", request_id))?;
+    let request = &pending.request;
+    info!(
+        "{} use of {} for request {}",
+        if approve { "Approving" } else { "Denying" },
+        request.tool_name,
+        request_id
+    );
+
+    let response = if approve {
+        json!({ "behavior": "allow", "updatedInput": request.input })
+    } else {
+        json!({
+            "behavior": "deny",
+            "message": reason.as_deref().unwrap_or("The user denied this tool use"),
+        })
+    };
+
+    if let Err(e) = pending
+        .input
+        .send_control_response(&pending.control_request_id, response)
+        .await
+    {
+        record_decision(&db, request, "expired", Some(&e))?;
+        return Err(e);
+    }
+
+    let decision = if approve { "allow" } else { "deny" };
+    record_decision(&db, request, decision, reason.as_deref())
+}
+
+/// List tool use requests waiting for a decision
+#[tauri::command]
+pub async fn list_pending_permission_requests(
+    permissions: State<'_, PermissionState>,
+) -> Result<Vec<PermissionRequest>, String> {
+    Ok(permissions.pending())
+}
+
+/// List recorded permission decisions, newest first, optionally for one
+/// Claude Code execution or agent run
+#[tauri::command]
+pub async fn list_permission_decisions(
+    db: State<'_, AgentDb>,
+    session_id: Option<String>,
+    run_id: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<PermissionDecision>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, request_id, session_id, run_id, tool_name, tool_input, decision, reason, requested_at, decided_at
+             FROM permission_decisions
+             WHERE (?1 IS NULL OR session_id = ?1) AND (?2 IS NULL OR run_id = ?2)
+             ORDER BY id DESC LIMIT ?3",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let decisions = stmt
+        .query_map(params![session_id, run_id, limit.unwrap_or(100)], |row| {
+            Ok(PermissionDecision {
+                id: row.get(0)?,
+                request_id: row.get(1)?,
+                session_id: row.get(2)?,
+                run_id: row.get(3)?,
+                tool_name: row.get(4)?,
+                tool_input: row.get(5)?,
+                decision: row.get(6)?,
+                reason: row.get(7)?,
+                requested_at: row.get(8)?,
+                decided_at: row.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(decisions)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    #[test]
+    fn test_permission_mode_columns() {
+        assert_eq!(
+            PermissionMode::from_column(None),
+            PermissionMode::Interactive
+        );
+        assert_eq!(
+            PermissionMode::from_column(Some("not json".to_string())),
+            PermissionMode::Interactive
+        );
+
+        let mode = PermissionMode::AllowList {
+            tools: vec!["Read".to_string(), "Edit".to_string()],
+        };
+        assert_eq!(PermissionMode::from_column(Some(mode.to_column())), mode);
+        assert_eq!(mode.args(), vec!["--allowedTools", "Read,Edit"]);
+    }
+
+    #[tokio::test]
+    async fn test_register_permission_request() {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let input = Arc::new(StreamInput::new(child.stdin.take().unwrap()));
+        let state = PermissionState::default();
+        let source = PermissionSource::AgentRun(7);
+
+        assert!(state
+            .register(&source, r#"{"type":"assistant"}"#, &input)
+            .is_none());
+        let line = r#"{"type":"control_request","request_id":"req-1","request":{"subtype":"can_use_tool","tool_name":"Bash","input":{"command":"ls"}}}"#;
+        let request = state.register(&source, line, &input).unwrap();
+        assert_eq!(request.tool_name, "Bash");
+        assert_eq!(request.run_id, Some(7));
+        assert_eq!(state.pending().len(), 1);
+
+        let pending = state.take(&request.id).unwrap();
+        assert_eq!(pending.control_request_id, "req-1");
+        assert!(state.take_source(&source).is_empty());
+        let _ = child.kill().await;
+    }
+}
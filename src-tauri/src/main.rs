@@ -12,27 +12,36 @@ use commands::agents::{
     cleanup_finished_processes, create_agent, delete_agent, execute_agent, export_agent,
     export_agent_to_file, fetch_github_agent_content, fetch_github_agents, get_agent,
     get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
-    get_live_session_output, get_session_output, get_session_status, import_agent,
-    import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
-    list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
-    list_running_sessions, set_claude_binary_path, stream_session_output, update_agent, AgentDb,
+    get_kill_grace_period, get_live_session_output, get_session_output, get_session_status,
+    import_agent, import_agent_from_file, import_agent_from_github, init_database,
+    kill_agent_session, list_agent_runs, list_agent_runs_with_metrics, list_agents,
+    list_claude_installations, list_running_sessions, recover_agent_runs, send_agent_input,
+    set_claude_binary_path, set_kill_grace_period, stream_session_output, update_agent, AgentDb,
 };
 use commands::claude::{
     cancel_claude_execution, check_auto_checkpoint, check_claude_version, cleanup_old_checkpoints,
     clear_checkpoint_manager, continue_claude_code, create_checkpoint, execute_claude_code,
-    find_claude_md_files, fork_from_checkpoint, get_checkpoint_diff, get_checkpoint_settings,
-    get_checkpoint_state_stats, get_claude_settings, get_project_sessions,
-    get_recently_modified_files, get_session_timeline, get_system_prompt, list_checkpoints,
-    list_directory_contents, list_projects, load_session_history, open_new_session,
-    read_claude_md_file, restore_checkpoint, resume_claude_code, save_claude_md_file,
-    save_claude_settings, save_system_prompt, search_files, track_checkpoint_message,
-    track_session_messages, update_checkpoint_settings, ClaudeProcessState,
+    export_checkpoint_archive, find_claude_md_files, fork_from_checkpoint, get_checkpoint_diff,
+    get_checkpoint_settings, get_checkpoint_state_stats, get_claude_settings, get_file_history,
+    get_project_sessions, get_recently_modified_files, get_session_timeline, get_system_prompt,
+    import_checkpoint_archive, list_checkpoints, list_directory_contents, list_projects,
+    list_running_claude_sessions, load_session_history, merge_checkpoints,
+    migrate_checkpoint_storage, open_new_session, preview_restore_checkpoint, read_claude_md_file,
+    recover_claude_sessions, restore_checkpoint, restore_checkpoint_files, resume_claude_code,
+    save_claude_md_file, save_claude_settings, save_system_prompt, search_checkpoints,
+    search_files, send_claude_input, set_checkpoint_backend, track_checkpoint_message,
+    track_session_messages, update_checkpoint_labels, update_checkpoint_settings,
+    update_retention_policy, verify_checkpoint_timeline, ClaudeProcessState,
 };
 use commands::mcp::{
     mcp_add, mcp_add_from_claude_desktop, mcp_add_json, mcp_get, mcp_get_server_status, mcp_list,
     mcp_read_project_config, mcp_remove, mcp_reset_project_choices, mcp_save_project_config,
     mcp_serve, mcp_test_connection,
 };
+use commands::permissions::{
+    list_pending_permission_requests, list_permission_decisions, respond_to_permission_request,
+    PermissionState,
+};
 use commands::sandbox::{
     clear_sandbox_violations, create_sandbox_profile, create_sandbox_rule, delete_sandbox_profile,
     delete_sandbox_rule, export_all_sandbox_profiles, export_sandbox_profile,
@@ -87,6 +96,19 @@ fn main() {
                 });
             }
 
+            // Periodically evict checkpoint managers of idle sessions
+            let eviction_state = checkpoint_state.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(5 * 60));
+                loop {
+                    interval.tick().await;
+                    let evicted = eviction_state.evict_idle().await;
+                    if evicted > 0 {
+                        log::info!("Evicted {} idle checkpoint managers", evicted);
+                    }
+                }
+            });
+
             app.manage(checkpoint_state);
 
             // Initialize process registry
@@ -95,6 +117,20 @@ fn main() {
             // Initialize Claude process state
             app.manage(ClaudeProcessState::default());
 
+            // Initialize pending tool permission requests
+            app.manage(PermissionState::default());
+
+            // Re-adopt Claude processes still running from before the app restarted
+            if let Err(e) = recover_agent_runs(&app.handle()) {
+                log::error!("Failed to recover agent runs: {}", e);
+            }
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = recover_claude_sessions(handle).await {
+                    log::error!("Failed to recover Claude sessions: {}", e);
+                }
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -114,15 +150,26 @@ fn main() {
             continue_claude_code,
             resume_claude_code,
             cancel_claude_execution,
+            list_running_claude_sessions,
+            send_claude_input,
+            respond_to_permission_request,
+            list_pending_permission_requests,
+            list_permission_decisions,
             list_directory_contents,
             search_files,
             create_checkpoint,
             restore_checkpoint,
+            restore_checkpoint_files,
+            preview_restore_checkpoint,
             list_checkpoints,
             fork_from_checkpoint,
             get_session_timeline,
             update_checkpoint_settings,
+            update_checkpoint_labels,
+            search_checkpoints,
+            update_retention_policy,
             get_checkpoint_diff,
+            get_file_history,
             track_checkpoint_message,
             track_session_messages,
             check_auto_checkpoint,
@@ -131,6 +178,12 @@ fn main() {
             clear_checkpoint_manager,
             get_checkpoint_state_stats,
             get_recently_modified_files,
+            export_checkpoint_archive,
+            import_checkpoint_archive,
+            verify_checkpoint_timeline,
+            merge_checkpoints,
+            migrate_checkpoint_storage,
+            set_checkpoint_backend,
             list_agents,
             create_agent,
             update_agent,
@@ -147,9 +200,12 @@ fn main() {
             cleanup_finished_processes,
             get_session_output,
             get_live_session_output,
+            send_agent_input,
             stream_session_output,
             get_claude_binary_path,
             set_claude_binary_path,
+            get_kill_grace_period,
+            set_kill_grace_period,
             list_claude_installations,
             export_agent,
             export_agent_to_file,
@@ -0,0 +1,433 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How often termination checks whether the processes have exited
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for processes to go away after SIGKILL
+#[cfg(unix)]
+const KILL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Start a command in a process group of its own, so it and everything it
+/// spawns can be signalled together
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn isolate(cmd: &mut tokio::process::Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+}
+
+/// A process that was part of a terminated process tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeProcess {
+    pub pid: u32,
+    pub command: String,
+}
+
+/// The outcome of terminating a process and its descendants
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminationReport {
+    pub pid: u32,
+    /// Whether processes outlived the grace period and were sent SIGKILL
+    pub forced: bool,
+    /// Descendants that exited
+    pub reaped: Vec<TreeProcess>,
+    /// Processes, including the root, still running after SIGKILL
+    pub survivors: Vec<TreeProcess>,
+}
+
+impl TerminationReport {
+    /// Whether every process in the tree has exited
+    pub fn terminated(&self) -> bool {
+        self.survivors.is_empty()
+    }
+
+    /// Log what was terminated
+    pub fn log(&self, label: &str) {
+        if self.forced {
+            log::warn!(
+                "{} (PID {}) outlived the grace period and was killed",
+                label,
+                self.pid
+            );
+        }
+        for process in &self.reaped {
+            log::info!(
+                "Reaped descendant of {}: PID {} ({})",
+                label,
+                process.pid,
+                process.command
+            );
+        }
+        for process in &self.survivors {
+            log::error!(
+                "Process of {} survived termination: PID {} ({})",
+                label,
+                process.pid,
+                process.command
+            );
+        }
+    }
+}
+
+/// Resources used by the live processes of a process tree
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TreeUsage {
+    pub processes: usize,
+    /// Resident memory
+    pub rss_bytes: u64,
+    /// CPU time, including that of children the processes have waited for
+    pub cpu_secs: f64,
+}
+
+/// A row of the OS process table
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+struct ProcessEntry {
+    pid: u32,
+    ppid: u32,
+    pgid: u32,
+    /// Start time in an OS-specific format, to tell reused PIDs apart
+    start: String,
+    command: String,
+    rss_bytes: u64,
+    cpu_secs: f64,
+}
+
+#[cfg(unix)]
+impl ProcessEntry {
+    fn is_alive(&self, table: &[ProcessEntry]) -> bool {
+        table
+            .iter()
+            .any(|entry| entry.pid == self.pid && entry.start == self.start)
+    }
+
+    fn describe(&self) -> TreeProcess {
+        TreeProcess {
+            pid: self.pid,
+            command: self.command.clone(),
+        }
+    }
+}
+
+/// Running processes; zombies are left out as they have already exited
+#[cfg(target_os = "linux")]
+fn process_table() -> Vec<ProcessEntry> {
+    let Ok(dir) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    dir.filter_map(|entry| {
+        let pid: u32 = entry.ok()?.file_name().to_str()?.parse().ok()?;
+        read_process(pid)
+    })
+    .collect()
+}
+
+/// The process with this PID, or None if there is none or it is a zombie
+#[cfg(target_os = "linux")]
+fn read_process(pid: u32) -> Option<ProcessEntry> {
+    // SAFETY: sysconf has no memory safety requirements
+    let (ticks_per_sec, page_size) = unsafe {
+        (
+            libc::sysconf(libc::_SC_CLK_TCK),
+            libc::sysconf(libc::_SC_PAGESIZE),
+        )
+    };
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (name, rest) = stat.split_once(" (")?.1.rsplit_once(')')?;
+    // Fields are counted from the closing parenthesis of the command name
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    if fields.first() == Some(&"Z") {
+        return None;
+    }
+    let command = std::fs::read(format!("/proc/{}/cmdline", pid))
+        .ok()
+        .map(|raw| {
+            raw.split(|b| *b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|command| !command.is_empty())
+        .unwrap_or_else(|| name.to_string());
+    // utime, stime, cutime and cstime, in clock ticks
+    let ticks: u64 = fields
+        .get(11..15)?
+        .iter()
+        .filter_map(|field| field.parse::<u64>().ok())
+        .sum();
+    let rss_pages: u64 = fields.get(21)?.parse().ok()?;
+    Some(ProcessEntry {
+        pid,
+        ppid: fields.get(1)?.parse().ok()?,
+        pgid: fields.get(2)?.parse().ok()?,
+        start: fields.get(19)?.to_string(),
+        command,
+        rss_bytes: rss_pages * page_size.max(0) as u64,
+        cpu_secs: ticks as f64 / ticks_per_sec.max(1) as f64,
+    })
+}
+
+/// Columns requested from ps for each process
+#[cfg(all(unix, not(target_os = "linux")))]
+const PS_COLUMNS: &str = "pid=,ppid=,pgid=,stat=,rss=,time=,lstart=,command=";
+
+/// Running processes; zombies are left out as they have already exited
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_table() -> Vec<ProcessEntry> {
+    let Ok(output) = std::process::Command::new("ps")
+        .args(["-A", "-o", PS_COLUMNS])
+        .output()
+    else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_ps_line)
+        .collect()
+}
+
+/// The process with this PID, or None if there is none or it is a zombie
+#[cfg(all(unix, not(target_os = "linux")))]
+fn read_process(pid: u32) -> Option<ProcessEntry> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", PS_COLUMNS, "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(parse_ps_line)
+}
+
+/// Parse a line of ps output with the `PS_COLUMNS` columns
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parse_ps_line(line: &str) -> Option<ProcessEntry> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    // lstart is printed as five words, e.g. "Fri Oct 16 10:00:00 2026"
+    if fields.len() < 12 || fields[3].starts_with('Z') {
+        return None;
+    }
+    Some(ProcessEntry {
+        pid: fields[0].parse().ok()?,
+        ppid: fields[1].parse().ok()?,
+        pgid: fields[2].parse().ok()?,
+        start: fields[6..11].join(" "),
+        command: fields[11..].join(" "),
+        rss_bytes: fields[4].parse::<u64>().ok()? * 1024,
+        cpu_secs: parse_cpu_time(fields[5])?,
+    })
+}
+
+/// Start time and command line of a running process, or None if there is no
+/// live process with this PID
+///
+/// The start time is in an OS-specific format, only meaningful for comparison.
+#[cfg(unix)]
+pub fn identify(pid: u32) -> Option<(String, String)> {
+    // The command line of a process that is still being exec'd reads as empty
+    // and its name stands in for it, so wait for exec to finish
+    #[cfg(target_os = "linux")]
+    for _ in 0..100 {
+        match std::fs::read(format!("/proc/{}/cmdline", pid)) {
+            Ok(cmdline) if cmdline.is_empty() => {
+                std::thread::sleep(std::time::Duration::from_millis(1))
+            }
+            _ => break,
+        }
+    }
+    read_process(pid).map(|entry| (entry.start, entry.command))
+}
+
+/// Parse a CPU time printed by ps as `[[dd-]hh:]mm:ss[.ss]`
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parse_cpu_time(time: &str) -> Option<f64> {
+    let (days, clock) = match time.split_once('-') {
+        Some((days, clock)) => (days.parse::<f64>().ok()?, clock),
+        None => (0.0, time),
+    };
+    let mut secs = 0.0;
+    for part in clock.split(':') {
+        secs = secs * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(days * 86400.0 + secs)
+}
+
+/// Live members of the tree rooted at `root`: the root, its descendants and
+/// anything left in its process group after being orphaned
+#[cfg(unix)]
+fn tree_members(root: u32, known: &[ProcessEntry], table: &[ProcessEntry]) -> Vec<ProcessEntry> {
+    let mut members: Vec<ProcessEntry> = table
+        .iter()
+        .filter(|entry| {
+            entry.pgid == root
+                || entry.pid == root
+                || known
+                    .iter()
+                    .any(|k| k.pid == entry.pid && k.start == entry.start)
+        })
+        .cloned()
+        .collect();
+
+    let mut i = 0;
+    while i < members.len() {
+        let parent = members[i].pid;
+        for entry in table {
+            if entry.ppid == parent && !members.iter().any(|m| m.pid == entry.pid) {
+                members.push(entry.clone());
+            }
+        }
+        i += 1;
+    }
+    members
+}
+
+/// Resources used by a process tree, or None once its root has exited
+#[cfg(unix)]
+pub fn tree_usage(pid: u32) -> Option<TreeUsage> {
+    let table = process_table();
+    if !table.iter().any(|entry| entry.pid == pid) {
+        return None;
+    }
+    let members = tree_members(pid, &[], &table);
+    Some(TreeUsage {
+        processes: members.len(),
+        rss_bytes: members.iter().map(|m| m.rss_bytes).sum(),
+        cpu_secs: members.iter().map(|m| m.cpu_secs).sum(),
+    })
+}
+
+/// Process trees cannot be inspected on this platform
+#[cfg(not(unix))]
+pub fn tree_usage(_pid: u32) -> Option<TreeUsage> {
+    None
+}
+
+#[cfg(unix)]
+fn signal(pid: i32, signal: libc::c_int) {
+    // SAFETY: kill(2) has no memory safety requirements
+    unsafe {
+        libc::kill(pid, signal);
+    }
+}
+
+/// Signal the root's process group, if it leads one, and each member
+#[cfg(unix)]
+fn signal_tree(root: u32, members: &[ProcessEntry], sig: libc::c_int) {
+    if members.iter().any(|m| m.pid == root && m.pgid == root) {
+        signal(-(root as i32), sig);
+    }
+    // Members that moved to a group or session of their own are signalled directly
+    for member in members {
+        signal(member.pid as i32, sig);
+    }
+}
+
+/// Terminate a process and every process it spawned
+///
+/// The tree is sent SIGTERM and given `grace` to exit; whatever is left is
+/// sent SIGKILL. Processes spawned while the tree shuts down are included.
+#[cfg(unix)]
+pub async fn terminate_tree(pid: u32, grace: Duration) -> TerminationReport {
+    let mut report = TerminationReport {
+        pid,
+        ..Default::default()
+    };
+    if pid <= 1 || pid == std::process::id() {
+        return report;
+    }
+
+    let mut known = tree_members(pid, &[], &process_table());
+    signal_tree(pid, &known, libc::SIGTERM);
+
+    let deadline = tokio::time::Instant::now() + grace;
+    let mut kill_deadline = None;
+    loop {
+        let table = process_table();
+        let live = tree_members(pid, &known, &table);
+        for member in &live {
+            if !known
+                .iter()
+                .any(|k| k.pid == member.pid && k.start == member.start)
+            {
+                known.push(member.clone());
+            }
+        }
+        if live.is_empty() {
+            break;
+        }
+
+        let now = tokio::time::Instant::now();
+        match kill_deadline {
+            None if now >= deadline => {
+                report.forced = true;
+                signal_tree(pid, &live, libc::SIGKILL);
+                kill_deadline = Some(now + KILL_TIMEOUT);
+            }
+            Some(kill_deadline) if now >= kill_deadline => break,
+            Some(_) => signal_tree(pid, &live, libc::SIGKILL),
+            None => {}
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    let table = process_table();
+    for process in &known {
+        if process.is_alive(&table) {
+            report.survivors.push(process.describe());
+        } else if process.pid != pid {
+            report.reaped.push(process.describe());
+        }
+    }
+    report
+}
+
+/// Terminate a process and every process it spawned
+///
+/// Windows offers no grace period for a tree kill, so the tree is killed at once.
+#[cfg(not(unix))]
+pub async fn terminate_tree(pid: u32, _grace: Duration) -> TerminationReport {
+    let mut report = TerminationReport {
+        pid,
+        forced: true,
+        ..Default::default()
+    };
+    let killed = tokio::process::Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &pid.to_string()])
+        .output()
+        .await
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+    if !killed {
+        report.survivors.push(TreeProcess {
+            pid,
+            command: String::new(),
+        });
+    }
+    report
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_terminate_tree_kills_descendants() {
+        // The shell and its children ignore SIGTERM, so SIGKILL is needed
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", "trap '' TERM; sleep 30 & sleep 30 & wait"]);
+        isolate(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let pid = child.id().unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let report = terminate_tree(pid, Duration::from_millis(300)).await;
+        assert!(report.forced);
+        assert!(report.terminated(), "{:?}", report.survivors);
+        assert_eq!(report.reaped.len(), 2);
+        assert!(report.reaped.iter().all(|p| p.command.contains("sleep")));
+        assert!(!child.wait().await.unwrap().success());
+    }
+}
@@ -0,0 +1,143 @@
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+use tokio::sync::Mutex;
+
+/// Build a stream-json user message, one line of Claude's `--input-format stream-json`
+pub fn user_message(text: &str) -> String {
+    json!({
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": [{ "type": "text", "text": text }],
+        },
+    })
+    .to_string()
+}
+
+/// Stdin of a Claude process started with `--input-format stream-json`
+///
+/// Each user message starts a turn, which ends with a `result` message on
+/// stdout. Stdin stays open while turns are outstanding so more messages can
+/// be sent mid-run, and is closed once the last one finishes so the process
+/// exits as it would with a `-p <prompt>` invocation.
+pub struct StreamInput {
+    stdin: Mutex<Option<ChildStdin>>,
+    pending_turns: AtomicUsize,
+}
+
+impl StreamInput {
+    pub fn new(stdin: ChildStdin) -> Self {
+        Self {
+            stdin: Mutex::new(Some(stdin)),
+            pending_turns: AtomicUsize::new(0),
+        }
+    }
+
+    /// Send a user message to the process
+    pub async fn send_user_message(&self, text: &str) -> Result<(), String> {
+        self.write_line(&user_message(text), true).await
+    }
+
+    /// Answer a `control_request` the process sent on stdout
+    pub async fn send_control_response(
+        &self,
+        request_id: &str,
+        response: serde_json::Value,
+    ) -> Result<(), String> {
+        let line = json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": request_id,
+                "response": response,
+            },
+        })
+        .to_string();
+        self.write_line(&line, false).await
+    }
+
+    async fn write_line(&self, message: &str, starts_turn: bool) -> Result<(), String> {
+        let mut stdin = self.stdin.lock().await;
+        let Some(pipe) = stdin.as_mut() else {
+            return Err("Session is no longer accepting input".to_string());
+        };
+
+        let written = async {
+            pipe.write_all(message.as_bytes()).await?;
+            pipe.write_all(b"\n").await?;
+            pipe.flush().await
+        }
+        .await;
+        if let Err(e) = written {
+            // The process has gone away; nothing more can be sent
+            *stdin = None;
+            return Err(format!("Failed to send input: {}", e));
+        }
+
+        if starts_turn {
+            self.pending_turns.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    /// Note a line of the process's output, closing stdin when the result of
+    /// the last outstanding turn arrives
+    pub async fn observe_output(&self, line: &str) {
+        let is_result = serde_json::from_str::<serde_json::Value>(line)
+            .ok()
+            .and_then(|msg| msg.get("type")?.as_str().map(|t| t == "result"))
+            .unwrap_or(false);
+        if !is_result {
+            return;
+        }
+
+        // Hold the stdin lock so a message sent concurrently is either counted
+        // before this check or rejected after the close
+        let mut stdin = self.stdin.lock().await;
+        let remaining = self
+            .pending_turns
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .map(|n| n - 1)
+            .unwrap_or(0);
+        if remaining == 0 {
+            *stdin = None;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::process::Command;
+
+    #[tokio::test]
+    async fn test_stdin_closes_after_last_turn() {
+        let mut child = Command::new("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let input = StreamInput::new(child.stdin.take().unwrap());
+        let mut output = BufReader::new(child.stdout.take().unwrap()).lines();
+
+        input.send_user_message("first").await.unwrap();
+        input.send_user_message("second").await.unwrap();
+        let echoed: serde_json::Value =
+            serde_json::from_str(&output.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(echoed["message"]["content"][0]["text"], "first");
+
+        let result = r#"{"type":"result","subtype":"success"}"#;
+        input.observe_output(result).await;
+        input.observe_output(result).await;
+        assert!(input.send_user_message("third").await.is_err());
+
+        // cat exits once its stdin is closed
+        assert!(output.next_line().await.unwrap().is_some());
+        assert!(output.next_line().await.unwrap().is_none());
+        assert!(child.wait().await.unwrap().success());
+    }
+}
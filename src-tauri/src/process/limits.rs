@@ -0,0 +1,336 @@
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::group::tree_usage;
+
+/// How often a running process tree is checked against its limits
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// CPU seconds a process gets past RLIMIT_CPU's SIGXCPU before it is sent SIGKILL
+#[cfg(unix)]
+const CPU_HARD_LIMIT_MARGIN: u64 = 5;
+
+/// Limits on the resources an agent's process tree may use; None is unlimited
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Wall-clock seconds the run may take
+    #[serde(default)]
+    pub max_runtime_secs: Option<u64>,
+    /// CPU seconds the process tree may use
+    #[serde(default)]
+    pub max_cpu_secs: Option<u64>,
+    /// Resident memory of the process tree, in megabytes
+    #[serde(default)]
+    pub max_memory_mb: Option<u64>,
+    /// Processes in the tree, including the agent's own
+    #[serde(default)]
+    pub max_processes: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Read limits from the four consecutive columns starting at `first`
+    pub fn from_row(row: &Row, first: usize) -> rusqlite::Result<Self> {
+        Ok(Self {
+            max_runtime_secs: row.get(first)?,
+            max_cpu_secs: row.get(first + 1)?,
+            max_memory_mb: row.get(first + 2)?,
+            max_processes: row.get(first + 3)?,
+        })
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A limit a process tree went over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Runtime(u64),
+    Cpu(u64),
+    Memory(u64),
+    Processes(u64),
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Runtime(secs) => {
+                write!(f, "Exceeded the wall-clock limit of {} seconds", secs)
+            }
+            LimitExceeded::Cpu(secs) => {
+                write!(f, "Exceeded the CPU time limit of {} seconds", secs)
+            }
+            LimitExceeded::Memory(mb) => write!(f, "Exceeded the memory limit of {} MB", mb),
+            LimitExceeded::Processes(count) => {
+                write!(f, "Exceeded the limit of {} processes", count)
+            }
+        }
+    }
+}
+
+/// Have the kernel enforce the per-process limits when `cmd` is spawned
+///
+/// RLIMIT_CPU bounds the CPU time of each process; the total of the tree is
+/// checked by [`watch`].
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub fn apply_rlimits(cmd: &mut tokio::process::Command, limits: &ResourceLimits) {
+    #[cfg(unix)]
+    if let Some(cpu_secs) = limits.max_cpu_secs {
+        let limit = libc::rlimit {
+            rlim_cur: cpu_secs as libc::rlim_t,
+            rlim_max: (cpu_secs + CPU_HARD_LIMIT_MARGIN) as libc::rlim_t,
+        };
+        // SAFETY: the closure only calls setrlimit, which is async-signal-safe
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::setrlimit(libc::RLIMIT_CPU, &limit) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+    }
+}
+
+/// A cgroup v2 group holding a process tree, enforcing its memory and process
+/// limits; it is removed when dropped
+pub struct Cgroup {
+    path: PathBuf,
+    /// The group's `cgroup.procs`, kept open so a spawned process can join
+    /// without allocating
+    procs: std::fs::File,
+}
+
+impl Cgroup {
+    /// Create a cgroup enforcing `limits`
+    ///
+    /// The cgroup is created next to the app's own, which needs cgroup v2 with
+    /// the memory and pids controllers delegated to the app. Returns None when
+    /// there is nothing for a cgroup to enforce.
+    #[cfg(target_os = "linux")]
+    pub fn create(name: &str, limits: &ResourceLimits) -> Result<Option<Self>, String> {
+        if limits.max_memory_mb.is_none() && limits.max_processes.is_none() {
+            return Ok(None);
+        }
+
+        let own = std::fs::read_to_string("/proc/self/cgroup").map_err(|e| e.to_string())?;
+        let own = own
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .ok_or("cgroup v2 is not in use")?;
+        let parent = PathBuf::from("/sys/fs/cgroup")
+            .join(own.trim_start_matches('/'))
+            .parent()
+            .map(PathBuf::from)
+            .ok_or("The app is in the root cgroup")?;
+
+        let controllers = std::fs::read_to_string(parent.join("cgroup.subtree_control"))
+            .map_err(|e| e.to_string())?;
+        let delegated = |controller: &str| controllers.split_whitespace().any(|c| c == controller);
+        if limits.max_memory_mb.is_some() && !delegated("memory") {
+            return Err("The memory controller is not delegated to the app".to_string());
+        }
+        if limits.max_processes.is_some() && !delegated("pids") {
+            return Err("The pids controller is not delegated to the app".to_string());
+        }
+
+        let path = parent.join(format!("claudia-{}", name));
+        // A group left behind by a previous run of the app is reused
+        std::fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+        let procs = std::fs::OpenOptions::new()
+            .write(true)
+            .open(path.join("cgroup.procs"))
+            .map_err(|e| format!("Failed to open cgroup.procs: {}", e))?;
+        let cgroup = Self { path, procs };
+
+        let write = |file: &str, value: String| {
+            std::fs::write(cgroup.path.join(file), value)
+                .map_err(|e| format!("Failed to write {}: {}", file, e))
+        };
+        if let Some(mb) = limits.max_memory_mb {
+            write("memory.max", (mb * 1024 * 1024).to_string())?;
+            // Without swap, the limit is on resident memory; not every kernel allows it
+            let _ = write("memory.swap.max", "0".to_string());
+        }
+        if let Some(count) = limits.max_processes {
+            write("pids.max", count.to_string())?;
+        }
+
+        Ok(Some(cgroup))
+    }
+
+    /// Cgroups are only available on Linux
+    #[cfg(not(target_os = "linux"))]
+    pub fn create(_name: &str, _limits: &ResourceLimits) -> Result<Option<Self>, String> {
+        Ok(None)
+    }
+
+    /// Have `cmd` join the group when spawned, before it runs anything, so
+    /// every process it starts is contained too
+    #[cfg_attr(not(unix), allow(unused_variables))]
+    pub fn apply(&self, cmd: &mut tokio::process::Command) {
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsRawFd;
+
+            let fd = self.procs.as_raw_fd();
+            // SAFETY: the closure only calls write(2), which is async-signal-safe.
+            // The descriptor is open in the child as the parent holds it until
+            // the spawn returns, and is closed on exec.
+            unsafe {
+                cmd.pre_exec(move || {
+                    // Writing 0 moves the writing process
+                    if libc::write(fd, b"0".as_ptr().cast(), 1) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+    }
+
+    /// Move a running process into the group
+    ///
+    /// Processes it has already started stay in their own groups.
+    pub fn add(&self, pid: u32) -> Result<(), String> {
+        use std::io::Write;
+
+        (&self.procs)
+            .write_all(pid.to_string().as_bytes())
+            .map_err(|e| format!("Failed to move process {} into the cgroup: {}", pid, e))
+    }
+
+    /// Whether the kernel killed a process of the group for going over its memory limit
+    pub fn memory_exceeded(&self) -> bool {
+        std::fs::read_to_string(self.path.join("memory.events"))
+            .ok()
+            .and_then(|events| {
+                events
+                    .lines()
+                    .find_map(|line| line.strip_prefix("oom_kill "))
+                    .and_then(|count| count.trim().parse::<u64>().ok())
+            })
+            .is_some_and(|count| count > 0)
+    }
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        // Only succeeds once every process has left the group
+        if let Err(e) = std::fs::remove_dir(&self.path) {
+            log::warn!("Failed to remove cgroup {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Check a process tree against its limits until the root exits, returning
+/// the first limit it goes over
+///
+/// Memory and process counts are checked here as well so the limits hold
+/// where no cgroup could be set up. Nothing is checked on platforms whose
+/// process trees cannot be inspected.
+pub async fn watch(pid: u32, limits: &ResourceLimits, started: Instant) -> Option<LimitExceeded> {
+    loop {
+        let usage = tree_usage(pid)?;
+
+        if let Some(secs) = limits.max_runtime_secs {
+            if started.elapsed() >= Duration::from_secs(secs) {
+                return Some(LimitExceeded::Runtime(secs));
+            }
+        }
+        if let Some(secs) = limits.max_cpu_secs {
+            if usage.cpu_secs >= secs as f64 {
+                return Some(LimitExceeded::Cpu(secs));
+            }
+        }
+        if let Some(mb) = limits.max_memory_mb {
+            if usage.rss_bytes > mb * 1024 * 1024 {
+                return Some(LimitExceeded::Memory(mb));
+            }
+        }
+        if let Some(count) = limits.max_processes {
+            if usage.processes as u64 > count {
+                return Some(LimitExceeded::Processes(count));
+            }
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::process::group::{isolate, terminate_tree};
+
+    #[tokio::test]
+    async fn test_watch_reports_exceeded_limit() {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", "sleep 30 & sleep 30 & wait"]);
+        isolate(&mut cmd);
+        let limits = ResourceLimits {
+            max_cpu_secs: Some(60),
+            max_processes: Some(2),
+            ..Default::default()
+        };
+        apply_rlimits(&mut cmd, &limits);
+        let mut child = cmd.spawn().unwrap();
+        let pid = child.id().unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let exceeded = watch(pid, &limits, Instant::now()).await;
+        assert_eq!(exceeded, Some(LimitExceeded::Processes(2)));
+        assert_eq!(
+            exceeded.unwrap().to_string(),
+            "Exceeded the limit of 2 processes"
+        );
+
+        assert!(terminate_tree(pid, Duration::ZERO).await.terminated());
+        child.wait().await.unwrap();
+        // The root has exited, so there is nothing left to watch
+        assert_eq!(watch(pid, &limits, Instant::now()).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_spawned_process_is_limited() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // The kernel stops a process that goes over its CPU time
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", "while :; do :; done"]);
+        isolate(&mut cmd);
+        let limits = ResourceLimits {
+            max_cpu_secs: Some(1),
+            ..Default::default()
+        };
+        apply_rlimits(&mut cmd, &limits);
+        let mut child = cmd.spawn().unwrap();
+        let status = tokio::time::timeout(Duration::from_secs(10), child.wait())
+            .await
+            .expect("the CPU limit was not enforced")
+            .unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGXCPU));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs cgroup v2 with the pids controller delegated to the test"]
+    async fn test_spawned_process_joins_cgroup() {
+        // A process joins its cgroup before it runs anything
+        let limits = ResourceLimits {
+            max_processes: Some(1),
+            ..Default::default()
+        };
+        let name = format!("test-{}", std::process::id());
+        let cgroup = Cgroup::create(&name, &limits).unwrap().unwrap();
+        let mut cmd = tokio::process::Command::new("cat");
+        cmd.arg("/proc/self/cgroup");
+        cgroup.apply(&mut cmd);
+        let output = cmd.output().await.unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains(&format!("claudia-{}", name)));
+    }
+}
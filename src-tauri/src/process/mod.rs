@@ -1,3 +1,7 @@
+pub mod group;
+pub mod input;
+pub mod limits;
+pub mod persistence;
 pub mod registry;
 
 pub use registry::*;
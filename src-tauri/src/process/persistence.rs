@@ -0,0 +1,409 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// What a persisted process was started for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessKind {
+    /// An agent run, keyed by its run ID
+    AgentRun,
+    /// An interactive Claude Code execution, keyed by its generated session ID
+    ClaudeSession,
+}
+
+impl ProcessKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ProcessKind::AgentRun => "agent_run",
+            ProcessKind::ClaudeSession => "claude_session",
+        }
+    }
+}
+
+/// A running Claude process, persisted so it can be found again after the
+/// app restarts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessRecord {
+    pub kind: ProcessKind,
+    pub key: String,
+    pub pid: u32,
+    /// When the process was spawned (RFC 3339)
+    pub started_at: String,
+    /// The OS's record of when the process started, to tell it apart from a
+    /// later process that reuses the PID
+    pub process_start: Option<String>,
+    pub command_line: String,
+    pub project_path: String,
+    /// Claude's session ID, once known
+    pub claude_session_id: Option<String>,
+}
+
+impl ProcessRecord {
+    /// Describe a process that has just been spawned
+    ///
+    /// `fallback_command` is recorded when the OS does not report the
+    /// process's command line.
+    pub fn spawned(
+        kind: ProcessKind,
+        key: String,
+        pid: u32,
+        project_path: String,
+        fallback_command: String,
+    ) -> Self {
+        let identity = ProcessIdentity::of(pid);
+        Self {
+            kind,
+            key,
+            pid,
+            started_at: chrono::Utc::now().to_rfc3339(),
+            process_start: identity.as_ref().map(|i| i.start.clone()),
+            command_line: identity
+                .and_then(|i| i.command_line)
+                .unwrap_or(fallback_command),
+            project_path,
+            claude_session_id: None,
+        }
+    }
+
+    /// Check that the PID still belongs to the recorded process, returning
+    /// why not otherwise
+    pub fn verify_identity(&self) -> Result<(), String> {
+        let Some(identity) = ProcessIdentity::of(self.pid) else {
+            return Err(format!("Process {} is no longer running", self.pid));
+        };
+        let Some(recorded_start) = &self.process_start else {
+            return Err(format!(
+                "The identity of process {} was not recorded and cannot be verified",
+                self.pid
+            ));
+        };
+        if *recorded_start != identity.start {
+            return Err(format!(
+                "PID {} now belongs to a different process",
+                self.pid
+            ));
+        }
+        if identity
+            .command_line
+            .is_some_and(|command_line| command_line != self.command_line)
+        {
+            return Err(format!("PID {} is running a different command", self.pid));
+        }
+        Ok(())
+    }
+
+    /// Path of the session transcript Claude writes, once the session ID is known
+    pub fn session_file(&self) -> Option<PathBuf> {
+        let session_id = self.claude_session_id.as_ref()?;
+        let projects_dir = dirs::home_dir()?.join(".claude").join("projects");
+        Some(find_session_file(
+            &projects_dir,
+            &self.project_path,
+            session_id,
+        ))
+    }
+}
+
+/// Name of the directory under `~/.claude/projects` Claude keeps a project's
+/// sessions in, with every character but ASCII letters and digits replaced by `-`
+pub fn project_dir_name(project_path: &str) -> String {
+    project_path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Find the transcript of `session_id` among Claude's project directories
+///
+/// The project's own directory is checked first, then every other one in case
+/// Claude encoded the path differently. If the transcript is not written yet,
+/// its path in the project's own directory is returned.
+fn find_session_file(projects_dir: &Path, project_path: &str, session_id: &str) -> PathBuf {
+    let file_name = format!("{}.jsonl", session_id);
+    let expected = projects_dir
+        .join(project_dir_name(project_path))
+        .join(&file_name);
+    if expected.exists() {
+        return expected;
+    }
+
+    std::fs::read_dir(projects_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path().join(&file_name))
+        .find(|path| path.is_file())
+        .unwrap_or(expected)
+}
+
+/// What the OS reports about a running process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessIdentity {
+    /// Start time in an OS-specific format, only meaningful for comparison
+    pub start: String,
+    pub command_line: Option<String>,
+}
+
+impl ProcessIdentity {
+    /// Look up a running process, returning None if there is none with this PID
+    #[cfg(unix)]
+    pub fn of(pid: u32) -> Option<Self> {
+        let (start, command_line) = super::group::identify(pid)?;
+        Some(Self {
+            start,
+            command_line: Some(command_line),
+        })
+    }
+
+    /// Process identities cannot be verified on this platform
+    #[cfg(not(unix))]
+    pub fn of(_pid: u32) -> Option<Self> {
+        None
+    }
+}
+
+/// Create the table of running processes
+pub fn init_process_table(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS running_processes (
+            kind TEXT NOT NULL,
+            key TEXT NOT NULL,
+            pid INTEGER NOT NULL,
+            started_at TEXT NOT NULL,
+            process_start TEXT,
+            command_line TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            claude_session_id TEXT,
+            PRIMARY KEY (kind, key)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Record a running process, replacing any earlier record with the same key
+pub fn save_process(conn: &Connection, record: &ProcessRecord) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO running_processes (kind, key, pid, started_at, process_start, command_line, project_path, claude_session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            record.kind.as_str(),
+            record.key,
+            record.pid,
+            record.started_at,
+            record.process_start,
+            record.command_line,
+            record.project_path,
+            record.claude_session_id,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Record Claude's session ID for a running process
+pub fn set_claude_session_id(
+    conn: &Connection,
+    kind: ProcessKind,
+    key: &str,
+    claude_session_id: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "UPDATE running_processes SET claude_session_id = ?1 WHERE kind = ?2 AND key = ?3",
+        params![claude_session_id, kind.as_str(), key],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Forget a process that has finished
+pub fn remove_process(conn: &Connection, kind: ProcessKind, key: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM running_processes WHERE kind = ?1 AND key = ?2",
+        params![kind.as_str(), key],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The recorded processes of one kind
+pub fn load_processes(conn: &Connection, kind: ProcessKind) -> Result<Vec<ProcessRecord>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT key, pid, started_at, process_start, command_line, project_path, claude_session_id
+             FROM running_processes WHERE kind = ?1 ORDER BY started_at",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let records = stmt
+        .query_map(params![kind.as_str()], |row| {
+            Ok(ProcessRecord {
+                kind,
+                key: row.get(0)?,
+                pid: row.get(1)?,
+                started_at: row.get(2)?,
+                process_start: row.get(3)?,
+                command_line: row.get(4)?,
+                project_path: row.get(5)?,
+                claude_session_id: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(records)
+}
+
+/// Look up one recorded process
+pub fn load_process(
+    conn: &Connection,
+    kind: ProcessKind,
+    key: &str,
+) -> Result<Option<ProcessRecord>, String> {
+    conn.query_row(
+        "SELECT pid, started_at, process_start, command_line, project_path, claude_session_id
+         FROM running_processes WHERE kind = ?1 AND key = ?2",
+        params![kind.as_str(), key],
+        |row| {
+            Ok(ProcessRecord {
+                kind,
+                key: key.to_string(),
+                pid: row.get(0)?,
+                started_at: row.get(1)?,
+                process_start: row.get(2)?,
+                command_line: row.get(3)?,
+                project_path: row.get(4)?,
+                claude_session_id: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Follow a re-adopted process's session transcript, passing each line
+/// appended after the call to `on_line`, until the process exits
+pub async fn tail_session_file(record: &ProcessRecord, mut on_line: impl FnMut(&str)) {
+    let session_file = record.session_file();
+    let mut offset = match &session_file {
+        Some(path) => tokio::fs::metadata(path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0),
+        None => 0,
+    };
+    let mut partial = String::new();
+
+    loop {
+        let running = record.verify_identity().is_ok();
+
+        if let Some(path) = &session_file {
+            if let Ok(mut file) = tokio::fs::File::open(path).await {
+                let mut appended = Vec::new();
+                if file.seek(std::io::SeekFrom::Start(offset)).await.is_ok()
+                    && file.read_to_end(&mut appended).await.is_ok()
+                {
+                    offset += appended.len() as u64;
+                    partial.push_str(&String::from_utf8_lossy(&appended));
+                    while let Some(end) = partial.find('\n') {
+                        let line: String = partial.drain(..=end).collect();
+                        let line = line.trim_end();
+                        if !line.is_empty() {
+                            on_line(line);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !running {
+            return;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_session_file() {
+        let projects = tempfile::tempdir().unwrap();
+        let project_path = "/home/me/my_project.v2";
+        assert_eq!(project_dir_name(project_path), "-home-me-my-project-v2");
+
+        // Before Claude writes the transcript, it is expected in the project's directory
+        let expected = projects
+            .path()
+            .join("-home-me-my-project-v2")
+            .join("abc.jsonl");
+        assert_eq!(
+            find_session_file(projects.path(), project_path, "abc"),
+            expected
+        );
+
+        // A transcript in a differently named directory is found by session ID
+        let elsewhere = projects.path().join("-home-me-my_project-v2");
+        std::fs::create_dir_all(&elsewhere).unwrap();
+        std::fs::write(elsewhere.join("abc.jsonl"), "{}\n").unwrap();
+        assert_eq!(
+            find_session_file(projects.path(), project_path, "abc"),
+            elsewhere.join("abc.jsonl")
+        );
+
+        // The project's own directory wins when both exist
+        std::fs::create_dir_all(expected.parent().unwrap()).unwrap();
+        std::fs::write(&expected, "{}\n").unwrap();
+        assert_eq!(
+            find_session_file(projects.path(), project_path, "abc"),
+            expected
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_process_record_identity() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_process_table(&conn).unwrap();
+
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        let mut record = ProcessRecord::spawned(
+            ProcessKind::AgentRun,
+            "1".to_string(),
+            pid,
+            "/tmp/project".to_string(),
+            "sleep 30".to_string(),
+        );
+        assert_eq!(record.command_line, "sleep 30");
+        save_process(&conn, &record).unwrap();
+        set_claude_session_id(&conn, ProcessKind::AgentRun, "1", "abc").unwrap();
+        record.claude_session_id = Some("abc".to_string());
+
+        let loaded = load_processes(&conn, ProcessKind::AgentRun).unwrap();
+        assert_eq!(loaded, vec![record.clone()]);
+        assert!(load_processes(&conn, ProcessKind::ClaudeSession)
+            .unwrap()
+            .is_empty());
+        assert!(record.verify_identity().is_ok());
+
+        // A process with a different start time is not the recorded one
+        let mut reused = record.clone();
+        reused.process_start = Some("0".to_string());
+        assert!(reused.verify_identity().is_err());
+
+        child.kill().await.unwrap();
+        child.wait().await.unwrap();
+        assert!(record.verify_identity().is_err());
+
+        remove_process(&conn, ProcessKind::AgentRun, "1").unwrap();
+        assert!(load_process(&conn, ProcessKind::AgentRun, "1")
+            .unwrap()
+            .is_none());
+    }
+}
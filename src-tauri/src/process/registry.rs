@@ -2,8 +2,12 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::process::Child;
 
+use super::group::{terminate_tree, TerminationReport};
+use super::input::StreamInput;
+
 /// Information about a running agent process
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
@@ -22,6 +26,8 @@ pub struct ProcessInfo {
 pub struct ProcessHandle {
     pub info: ProcessInfo,
     pub child: Arc<Mutex<Option<Child>>>,
+    /// The process's stdin; None for processes adopted after a restart
+    pub input: Option<Arc<StreamInput>>,
     pub live_output: Arc<Mutex<String>>,
 }
 
@@ -48,6 +54,7 @@ impl ProcessRegistry {
         task: String,
         model: String,
         child: Child,
+        input: Arc<StreamInput>,
     ) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
@@ -65,6 +72,7 @@ impl ProcessRegistry {
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(Some(child))),
+            input: Some(input),
             live_output: Arc::new(Mutex::new(String::new())),
         };
 
@@ -72,6 +80,25 @@ impl ProcessRegistry {
         Ok(())
     }
 
+    /// Register a process that outlived a previous run of the app
+    ///
+    /// There is no child handle or stdin for such a process, so it can only be
+    /// killed by PID and does not accept input.
+    pub fn adopt_process(&self, info: ProcessInfo) -> Result<(), String> {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let run_id = info.run_id;
+        processes.insert(
+            run_id,
+            ProcessHandle {
+                info,
+                child: Arc::new(Mutex::new(None)),
+                input: None,
+                live_output: Arc::new(Mutex::new(String::new())),
+            },
+        );
+        Ok(())
+    }
+
     /// Unregister a process (called when it completes)
     #[allow(dead_code)]
     pub fn unregister_process(&self, run_id: i64) -> Result<(), String> {
@@ -97,9 +124,16 @@ impl ProcessRegistry {
         Ok(processes.get(&run_id).map(|handle| handle.info.clone()))
     }
 
-    /// Kill a running process with proper cleanup
-    pub async fn kill_process(&self, run_id: i64) -> Result<bool, String> {
-        use log::{error, info, warn};
+    /// Kill a running process and everything it spawned
+    ///
+    /// The process tree is sent SIGTERM and given `grace` to exit before being
+    /// sent SIGKILL. Returns None if the process is not registered.
+    pub async fn kill_process(
+        &self,
+        run_id: i64,
+        grace: Duration,
+    ) -> Result<Option<TerminationReport>, String> {
+        use log::{info, warn};
 
         // First check if the process exists and get its PID
         let (pid, child_arc) = {
@@ -107,171 +141,51 @@ impl ProcessRegistry {
             if let Some(handle) = processes.get(&run_id) {
                 (handle.info.pid, handle.child.clone())
             } else {
-                return Ok(false); // Process not found
+                return Ok(None); // Process not found
             }
         };
 
-        info!(
-            "Attempting graceful shutdown of process {} (PID: {})",
-            run_id, pid
-        );
+        info!("Terminating process tree of run {} (PID: {})", run_id, pid);
+        let report = terminate_tree(pid, grace).await;
+        report.log(&format!("run {}", run_id));
 
-        // Send kill signal to the process
-        let kill_sent = {
+        // Reap the child so it does not linger as a zombie
+        {
             let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
             if let Some(child) = child_guard.as_mut() {
-                match child.start_kill() {
-                    Ok(_) => {
-                        info!("Successfully sent kill signal to process {}", run_id);
-                        true
-                    }
-                    Err(e) => {
-                        error!("Failed to send kill signal to process {}: {}", run_id, e);
-                        return Err(format!("Failed to kill process: {}", e));
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        info!("Process {} exited with status: {:?}", run_id, status);
+                        *child_guard = None;
                     }
+                    Ok(None) => warn!("Process {} is still running after being killed", run_id),
+                    Err(e) => warn!("Error checking process status: {}", e),
                 }
-            } else {
-                false // Process already killed
             }
-        };
-
-        if !kill_sent {
-            return Ok(false);
         }
 
-        // Wait for the process to exit (with timeout)
-        let wait_result = tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
-            loop {
-                // Check if process has exited
-                let status = {
-                    let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
-                    if let Some(child) = child_guard.as_mut() {
-                        match child.try_wait() {
-                            Ok(Some(status)) => {
-                                info!("Process {} exited with status: {:?}", run_id, status);
-                                *child_guard = None; // Clear the child handle
-                                Some(Ok::<(), String>(()))
-                            }
-                            Ok(None) => {
-                                // Still running
-                                None
-                            }
-                            Err(e) => {
-                                error!("Error checking process status: {}", e);
-                                Some(Err(e.to_string()))
-                            }
-                        }
-                    } else {
-                        // Process already gone
-                        Some(Ok(()))
-                    }
-                };
-
-                match status {
-                    Some(result) => return result,
-                    None => {
-                        // Still running, wait a bit
-                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    }
-                }
-            }
-        })
-        .await;
-
-        match wait_result {
-            Ok(Ok(_)) => {
-                info!("Process {} exited gracefully", run_id);
-            }
-            Ok(Err(e)) => {
-                error!("Error waiting for process {}: {}", run_id, e);
-            }
-            Err(_) => {
-                warn!("Process {} didn't exit within 5 seconds after kill", run_id);
-                // Force clear the handle
-                if let Ok(mut child_guard) = child_arc.lock() {
-                    *child_guard = None;
-                }
-            }
+        if report.terminated() {
+            self.unregister_process(run_id)?;
         }
-
-        // Remove from registry after killing
-        self.unregister_process(run_id)?;
-
-        Ok(true)
+        Ok(Some(report))
     }
 
-    /// Kill a process by PID using system commands (fallback method)
-    pub fn kill_process_by_pid(&self, run_id: i64, pid: u32) -> Result<bool, String> {
-        use log::{error, info, warn};
-
-        info!("Attempting to kill process {} by PID {}", run_id, pid);
-
-        let kill_result = if cfg!(target_os = "windows") {
-            std::process::Command::new("taskkill")
-                .args(["/F", "/PID", &pid.to_string()])
-                .output()
-        } else {
-            // First try SIGTERM
-            let term_result = std::process::Command::new("kill")
-                .args(["-TERM", &pid.to_string()])
-                .output();
-
-            match &term_result {
-                Ok(output) if output.status.success() => {
-                    info!("Sent SIGTERM to PID {}", pid);
-                    // Give it 2 seconds to exit gracefully
-                    std::thread::sleep(std::time::Duration::from_secs(2));
-
-                    // Check if still running
-                    let check_result = std::process::Command::new("kill")
-                        .args(["-0", &pid.to_string()])
-                        .output();
-
-                    if let Ok(output) = check_result {
-                        if output.status.success() {
-                            // Still running, send SIGKILL
-                            warn!(
-                                "Process {} still running after SIGTERM, sending SIGKILL",
-                                pid
-                            );
-                            std::process::Command::new("kill")
-                                .args(["-KILL", &pid.to_string()])
-                                .output()
-                        } else {
-                            term_result
-                        }
-                    } else {
-                        term_result
-                    }
-                }
-                _ => {
-                    // SIGTERM failed, try SIGKILL directly
-                    warn!("SIGTERM failed for PID {}, trying SIGKILL", pid);
-                    std::process::Command::new("kill")
-                        .args(["-KILL", &pid.to_string()])
-                        .output()
-                }
-            }
-        };
-
-        match kill_result {
-            Ok(output) => {
-                if output.status.success() {
-                    info!("Successfully killed process with PID {}", pid);
-                    // Remove from registry
-                    self.unregister_process(run_id)?;
-                    Ok(true)
-                } else {
-                    let error_msg = String::from_utf8_lossy(&output.stderr);
-                    warn!("Failed to kill PID {}: {}", pid, error_msg);
-                    Ok(false)
-                }
-            }
-            Err(e) => {
-                error!("Failed to execute kill command for PID {}: {}", pid, e);
-                Err(format!("Failed to execute kill command: {}", e))
-            }
+    /// Kill a process tree by PID (fallback method for processes that are not registered)
+    pub async fn kill_process_by_pid(
+        &self,
+        run_id: i64,
+        pid: u32,
+        grace: Duration,
+    ) -> Result<TerminationReport, String> {
+        log::info!("Attempting to kill process {} by PID {}", run_id, pid);
+
+        let report = terminate_tree(pid, grace).await;
+        report.log(&format!("run {}", run_id));
+        if report.terminated() {
+            // Remove from registry
+            self.unregister_process(run_id)?;
         }
+        Ok(report)
     }
 
     /// Check if a process is still running by trying to get its status
@@ -309,6 +223,23 @@ impl ProcessRegistry {
         }
     }
 
+    /// Send a user message to a running process's stdin
+    pub async fn send_input(&self, run_id: i64, message: &str) -> Result<(), String> {
+        let input = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => handle.input.clone().ok_or_else(|| {
+                    format!(
+                        "Run {} was adopted after a restart and does not accept input",
+                        run_id
+                    )
+                })?,
+                None => return Err(format!("No running process for run {}", run_id)),
+            }
+        };
+        input.send_user_message(message).await
+    }
+
     /// Append to live output for a process
     pub fn append_live_output(&self, run_id: i64, output: &str) -> Result<(), String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
@@ -170,6 +170,7 @@ export const AgentRunsList: React.FC<AgentRunsListProps> = ({
                           "outline"
                         }
                         className="text-xs"
+                        title={run.status_reason}
                       >
                         {run.status === "completed" ? "Completed" :
                          run.status === "running" ? "Running" :
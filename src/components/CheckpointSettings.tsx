@@ -54,6 +54,9 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
     { value: "per_prompt", label: "After Each Prompt" },
     { value: "per_tool_use", label: "After Tool Use" },
     { value: "smart", label: "Smart (Recommended)" },
+    { value: "interval", label: "Every Few Minutes of Activity" },
+    { value: "changed_lines", label: "After Many Changed Lines" },
+    { value: "bash_pattern", label: "Before Risky Bash Commands" },
   ];
 
   useEffect(() => {
@@ -213,6 +216,9 @@ export const CheckpointSettings: React.FC<CheckpointSettingsProps> = ({
             {checkpointStrategy === "per_prompt" && "A checkpoint will be created after each user prompt"}
             {checkpointStrategy === "per_tool_use" && "A checkpoint will be created after each tool use"}
             {checkpointStrategy === "smart" && "Checkpoints will be created after destructive operations"}
+            {checkpointStrategy === "interval" && "A checkpoint will be created after each interval of session activity"}
+            {checkpointStrategy === "changed_lines" && "A checkpoint will be created once edits have changed enough lines"}
+            {checkpointStrategy === "bash_pattern" && "A checkpoint will be created before Bash commands matching the configured patterns"}
           </p>
         </div>
 
@@ -15,7 +15,7 @@ import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 import { Popover } from "@/components/ui/popover";
-import { api, type Session } from "@/lib/api";
+import { api, type PermissionMode, type Session } from "@/lib/api";
 import { cn } from "@/lib/utils";
 import { open } from "@tauri-apps/plugin-dialog";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
@@ -91,6 +91,7 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
   const parentRef = useRef<HTMLDivElement>(null);
   const unlistenRefs = useRef<UnlistenFn[]>([]);
   const hasActiveSessionRef = useRef(false);
+  const executionIdRef = useRef<string | null>(null);
   const floatingPromptRef = useRef<FloatingPromptInputRef>(null);
 
   // Get effective session info (from prop or extracted) - use useMemo to ensure it updates
@@ -282,11 +283,39 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
       unlistenRefs.current.forEach(unlisten => unlisten());
       unlistenRefs.current = [];
       
-      // Set up event listeners before executing
-      console.log('[ClaudeCodeSession] Setting up event listeners...');
+      // Add the user message immediately to the UI
+      const userMessage: ClaudeStreamMessage = {
+        type: "user",
+        message: {
+          content: [
+            {
+              type: "text",
+              text: prompt
+            }
+          ]
+        }
+      };
+      setMessages(prev => [...prev, userMessage]);
+
+      // This view cannot answer permission requests yet, so it explicitly
+      // opts in to approving every tool use
+      const permissionMode: PermissionMode = { mode: "skip" };
+
+      // Execute the appropriate command
+      let runId: string;
+      if (effectiveSession && !isFirstPrompt) {
+        console.log('[ClaudeCodeSession] Resuming session:', effectiveSession.id);
+        runId = await api.resumeClaudeCode(projectPath, effectiveSession.id, prompt, model, permissionMode);
+      } else {
+        console.log('[ClaudeCodeSession] Starting new session');
+        setIsFirstPrompt(false);
+        runId = await api.executeClaudeCode(projectPath, prompt, model, permissionMode);
+      }
+      executionIdRef.current = runId;
       
-      // If we already have a Claude session ID, use isolated listeners
-      const eventSuffix = claudeSessionId ? `:${claudeSessionId}` : '';
+      // Listen only to this run's events so output from other sessions is ignored
+      console.log('[ClaudeCodeSession] Setting up event listeners for run:', runId);
+      const eventSuffix = `:${runId}`;
       
       const outputUnlisten = await listen<string>(`claude-output${eventSuffix}`, async (event) => {
         try {
@@ -363,30 +392,6 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
       });
 
       unlistenRefs.current = [outputUnlisten, errorUnlisten, completeUnlisten];
-      
-      // Add the user message immediately to the UI (after setting up listeners)
-      const userMessage: ClaudeStreamMessage = {
-        type: "user",
-        message: {
-          content: [
-            {
-              type: "text",
-              text: prompt
-            }
-          ]
-        }
-      };
-      setMessages(prev => [...prev, userMessage]);
-
-      // Execute the appropriate command
-      if (effectiveSession && !isFirstPrompt) {
-        console.log('[ClaudeCodeSession] Resuming session:', effectiveSession.id);
-        await api.resumeClaudeCode(projectPath, effectiveSession.id, prompt, model);
-      } else {
-        console.log('[ClaudeCodeSession] Starting new session');
-        setIsFirstPrompt(false);
-        await api.executeClaudeCode(projectPath, prompt, model);
-      }
     } catch (err) {
       console.error("Failed to send prompt:", err);
       setError("Failed to send prompt");
@@ -486,8 +491,11 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
     try {
       setIsCancelling(true);
       
-      // Cancel the Claude execution with session ID if available
-      await api.cancelClaudeExecution(claudeSessionId || undefined);
+      // Cancel this session's execution, leaving other sessions running
+      const runId = executionIdRef.current || claudeSessionId;
+      if (runId) {
+        await api.cancelClaudeExecution(runId);
+      }
       
       // Clean up listeners
       unlistenRefs.current.forEach(unlisten => unlisten());
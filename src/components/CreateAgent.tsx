@@ -97,7 +97,10 @@ export const CreateAgent: React.FC<CreateAgentProps> = ({
           sandboxEnabled,
           enableFileRead,
           enableFileWrite,
-          enableNetwork
+          enableNetwork,
+          // Agent runs cannot answer permission requests in the UI yet, so
+          // new agents explicitly approve every tool use
+          { mode: "skip" }
         );
       }
       
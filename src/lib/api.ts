@@ -167,6 +167,57 @@ export interface ImportResult {
   new_name?: string;
 }
 
+/**
+ * How a Claude process is allowed to use tools
+ */
+export type PermissionMode =
+  | { mode: "skip" }
+  | { mode: "allow_list"; tools: string[] }
+  | { mode: "interactive" };
+
+/**
+ * Limits on the resources an agent's runs may use; a missing or null limit is unlimited
+ */
+export interface ResourceLimits {
+  /** Wall-clock seconds a run may take */
+  max_runtime_secs?: number | null;
+  /** CPU seconds the run's process tree may use */
+  max_cpu_secs?: number | null;
+  /** Resident memory of the run's process tree, in megabytes */
+  max_memory_mb?: number | null;
+  /** Processes in the run's process tree */
+  max_processes?: number | null;
+}
+
+/**
+ * A tool use waiting for approval, emitted as `claude-permission-request[:id]`
+ * or `agent-permission-request[:runId]`
+ */
+export interface PermissionRequest {
+  id: string;
+  sessionId?: string;
+  runId?: number;
+  toolName: string;
+  input: any;
+  requestedAt: string;
+}
+
+/**
+ * A recorded decision on a tool use request
+ */
+export interface PermissionDecision {
+  id: number;
+  requestId: string;
+  sessionId?: string;
+  runId?: number;
+  toolName: string;
+  toolInput: string;
+  decision: "allow" | "deny" | "expired";
+  reason?: string;
+  requestedAt: string;
+  decidedAt: string;
+}
+
 // Agent API types
 export interface Agent {
   id?: number;
@@ -179,6 +230,11 @@ export interface Agent {
   enable_file_read: boolean;
   enable_file_write: boolean;
   enable_network: boolean;
+  permission_mode?: PermissionMode;
+  max_runtime_secs?: number | null;
+  max_cpu_secs?: number | null;
+  max_memory_mb?: number | null;
+  max_processes?: number | null;
   created_at: string;
   updated_at: string;
 }
@@ -196,6 +252,11 @@ export interface AgentExport {
     enable_file_read: boolean;
     enable_file_write: boolean;
     enable_network: boolean;
+    permission_mode?: PermissionMode;
+    max_runtime_secs?: number | null;
+    max_cpu_secs?: number | null;
+    max_memory_mb?: number | null;
+    max_processes?: number | null;
   };
 }
 
@@ -221,6 +282,29 @@ export interface AgentRun {
   process_started_at?: string;
   created_at: string;
   completed_at?: string;
+  status_reason?: string; // Why the run ended with its status, e.g. why it failed
+}
+
+/**
+ * A process that was part of a killed process tree
+ */
+export interface TreeProcess {
+  pid: number;
+  command: string;
+}
+
+/**
+ * Outcome of killing a process and its descendants, sent with the
+ * `agent-processes-reaped:<runId>` and `claude-processes-reaped:<sessionId>` events
+ */
+export interface TerminationReport {
+  pid: number;
+  /** Whether processes outlived the grace period and were sent SIGKILL */
+  forced: boolean;
+  /** Descendants that exited */
+  reaped: TreeProcess[];
+  /** Processes still running after SIGKILL */
+  survivors: TreeProcess[];
 }
 
 export interface AgentRunMetrics {
@@ -244,6 +328,7 @@ export interface AgentRunWithMetrics {
   process_started_at?: string;
   created_at: string;
   completed_at?: string;
+  status_reason?: string; // Why the run ended with its status, e.g. why it failed
   metrics?: AgentRunMetrics;
   output?: string; // Real-time JSONL content
 }
@@ -311,6 +396,11 @@ export interface Checkpoint {
   timestamp: string;
   description?: string;
   parentCheckpointId?: string;
+  /** Further parents of a merge checkpoint */
+  mergeParentIds?: string[];
+  tags?: string[];
+  /** Pinned milestone, never removed by the retention policy */
+  milestone?: boolean;
   metadata: CheckpointMetadata;
 }
 
@@ -331,7 +421,8 @@ export interface CheckpointMetadata {
 export interface FileSnapshot {
   checkpointId: string;
   filePath: string;
-  content: string;
+  /** Raw bytes of the file */
+  content: number[];
   hash: string;
   isDeleted: boolean;
   permissions?: number;
@@ -357,12 +448,46 @@ export interface SessionTimeline {
   autoCheckpointEnabled: boolean;
   checkpointStrategy: CheckpointStrategy;
   totalCheckpoints: number;
+  retentionPolicy: RetentionPolicy;
+  strategyThresholds: StrategyThresholds;
+}
+
+/**
+ * Rules for pruning old checkpoints. Described checkpoints, fork points and
+ * the current checkpoint are always kept.
+ */
+export interface RetentionPolicy {
+  /** Keep every checkpoint from the last N hours; older ones are thinned */
+  keepAllHours?: number;
+  /** Keep the newest checkpoint of each day older than keepAllHours */
+  keepDaily: boolean;
+  /** Cap on the on-disk size of all timelines of the project, in bytes */
+  maxProjectBytes?: number;
 }
 
 /**
  * Strategy for automatic checkpoint creation
  */
-export type CheckpointStrategy = 'manual' | 'per_prompt' | 'per_tool_use' | 'smart';
+export type CheckpointStrategy =
+  | 'manual'
+  | 'per_prompt'
+  | 'per_tool_use'
+  | 'smart'
+  | 'interval'
+  | 'changed_lines'
+  | 'bash_pattern';
+
+/**
+ * Thresholds for the interval, changed-lines and Bash-pattern strategies
+ */
+export interface StrategyThresholds {
+  /** Minutes of activity between checkpoints */
+  intervalMinutes: number;
+  /** Lines added or removed by edits since the last checkpoint */
+  changedLines: number;
+  /** Regular expressions matched against Bash commands */
+  bashPatterns: string[];
+}
 
 /**
  * Result of a checkpoint operation
@@ -371,6 +496,113 @@ export interface CheckpointResult {
   checkpoint: Checkpoint;
   filesProcessed: number;
   warnings: string[];
+  /** Session transcript rewritten by a restore, if requested */
+  transcript?: TranscriptRestore;
+}
+
+/**
+ * How a restore brings the session transcript back to a checkpoint:
+ * truncate the session's own transcript, or write a copy to a new session
+ */
+export type TranscriptRestoreMode = "in_place" | "new_session";
+
+/**
+ * A session transcript written from a checkpoint's conversation
+ */
+export interface TranscriptRestore {
+  /** Session to resume to continue from the checkpoint */
+  sessionId: string;
+  messageCount: number;
+}
+
+/**
+ * What restoring a checkpoint would do, without touching the working tree
+ */
+export interface RestorePlan {
+  checkpointId: string;
+  writes: PlannedFileChange[];
+  deletions: PlannedFileChange[];
+  permissionChanges: PlannedPermissionChange[];
+  removedDirs: string[];
+  warnings: string[];
+}
+
+/**
+ * A file write or deletion planned by a restore
+ */
+export interface PlannedFileChange {
+  path: string;
+  size: number;
+  exists: boolean;
+  unchanged: boolean;
+  /** Modified outside Claude since it was last tracked */
+  wouldLoseLocalChanges: boolean;
+}
+
+/**
+ * A permission change planned by a restore
+ */
+export interface PlannedPermissionChange {
+  path: string;
+  from: number;
+  to: number;
+}
+
+/**
+ * Criteria for searching a project's checkpoints; every given criterion must match
+ */
+export interface CheckpointQuery {
+  tags?: string[];
+  /** Text to find in the prompt that led to the checkpoint */
+  text?: string;
+  /** Path, directory or glob of a file snapshotted by the checkpoint */
+  path?: string;
+  since?: string;
+  until?: string;
+  milestonesOnly?: boolean;
+}
+
+/**
+ * A checkpoint found by a search
+ */
+export interface CheckpointReference {
+  sessionId: string;
+  checkpoint: Checkpoint;
+  matchedFiles: string[];
+}
+
+/**
+ * How a checkpoint changed a file
+ */
+export type FileChangeKind = 'added' | 'modified' | 'deleted';
+
+/**
+ * A checkpoint at which a file's content changed
+ */
+export interface FileHistoryEntry {
+  sessionId: string;
+  checkpointId: string;
+  timestamp: string;
+  /** Prompt that led to the checkpoint */
+  userPrompt: string;
+  change: FileChangeKind;
+  /** Checkpoint holding the version this one replaced */
+  previousCheckpointId?: string;
+  /** Diff against the previous version */
+  diff: FileDiff;
+}
+
+/**
+ * Result of restoring selected files from a checkpoint
+ */
+export interface PartialRestoreResult {
+  checkpointId: string;
+  restoredFiles: string[];
+  deletedFiles: string[];
+  /** Files changed on disk since their last snapshot; skipped unless forced */
+  conflicts: string[];
+  unmatchedPatterns: string[];
+  warnings: string[];
 }
 
 /**
@@ -382,6 +614,7 @@ export interface CheckpointDiff {
   modifiedFiles: FileDiff[];
   addedFiles: string[];
   deletedFiles: string[];
+  renamedFiles: FileRename[];
   tokenDelta: number;
 }
 
@@ -392,9 +625,111 @@ export interface FileDiff {
   path: string;
   additions: number;
   deletions: number;
+  isBinary: boolean;
   diffContent?: string;
 }
 
+/**
+ * A file detected as renamed between two checkpoints
+ */
+export interface FileRename {
+  fromPath: string;
+  toPath: string;
+}
+
+/**
+ * Summary of an exported or imported checkpoint timeline archive
+ */
+export interface ArchiveSummary {
+  projectId: string;
+  sessionId: string;
+  checkpoints: number;
+  files: number;
+  totalBytes: number;
+}
+
+/**
+ * Result of moving per-session checkpoint content into project-wide stores
+ */
+export interface MigrationReport {
+  sessionsMigrated: number;
+  blobsMoved: number;
+  duplicatesRemoved: number;
+  bytesFreed: number;
+}
+
+/**
+ * Why a file could not be merged automatically
+ */
+export type MergeConflictKind = "content" | "delete_modify" | "binary";
+
+/**
+ * A file changed differently on both merged branches
+ */
+export interface MergeConflict {
+  path: string;
+  kind: MergeConflictKind;
+  /** Merged content with conflict markers, for text conflicts */
+  content?: string | null;
+}
+
+/**
+ * Result of merging two timeline branches
+ */
+export interface MergeResult {
+  oursCheckpointId: string;
+  theirsCheckpointId: string;
+  baseCheckpointId?: string | null;
+  /** The merge checkpoint, created only when there are no conflicts */
+  checkpoint?: Checkpoint | null;
+  mergedFiles: string[];
+  conflicts: MergeConflict[];
+}
+
+/**
+ * Where a project's checkpoint content is stored
+ */
+export type CheckpointBackendKind = "file" | "git";
+
+/**
+ * Checkpoint storage backend selected for a project
+ */
+export interface CheckpointBackendConfig {
+  backend: CheckpointBackendKind;
+  /** Repository holding checkpoint commits, for the git backend */
+  repoPath?: string | null;
+}
+
+/**
+ * Result of verifying (and optionally repairing) a checkpoint timeline
+ */
+export interface TimelineVerifyReport {
+  sessionId: string;
+  checkpointsChecked: number;
+  blobsChecked: number;
+  issues: TimelineIssue[];
+  orphanCheckpoints: string[];
+  repaired: boolean;
+  prunedCheckpoints: string[];
+}
+
+/**
+ * A single problem found while verifying a checkpoint timeline
+ */
+export interface TimelineIssue {
+  checkpointId?: string;
+  kind:
+    | "missing_metadata"
+    | "missing_messages"
+    | "missing_reference"
+    | "invalid_reference"
+    | "missing_content"
+    | "corrupt_content"
+    | "count_mismatch";
+  path?: string;
+  detail: string;
+}
+
 /**
  * Represents an MCP server configuration
  */
@@ -706,6 +1041,8 @@ export const api = {
    * @param enable_file_read - Optional file read permission
    * @param enable_file_write - Optional file write permission
    * @param enable_network - Optional network permission
+   * @param permission_mode - Optional tool permission mode (defaults to interactive approval)
+   * @param limits - Optional resource limits for the agent's runs (defaults to none)
    * @returns Promise resolving to the created agent
    */
   async createAgent(
@@ -717,7 +1054,9 @@ export const api = {
     sandbox_enabled?: boolean,
     enable_file_read?: boolean,
     enable_file_write?: boolean,
-    enable_network?: boolean
+    enable_network?: boolean,
+    permission_mode?: PermissionMode,
+    limits?: ResourceLimits
   ): Promise<Agent> {
     try {
       return await invoke<Agent>('create_agent', { 
@@ -729,7 +1068,9 @@ export const api = {
         sandboxEnabled: sandbox_enabled,
         enableFileRead: enable_file_read,
         enableFileWrite: enable_file_write,
-        enableNetwork: enable_network
+        enableNetwork: enable_network,
+        permissionMode: permission_mode,
+        limits
       });
     } catch (error) {
       console.error("Failed to create agent:", error);
@@ -749,6 +1090,8 @@ export const api = {
    * @param enable_file_read - Optional file read permission
    * @param enable_file_write - Optional file write permission
    * @param enable_network - Optional network permission
+   * @param permission_mode - Optional tool permission mode
+   * @param limits - Optional resource limits, replacing all current limits when given
    * @returns Promise resolving to the updated agent
    */
   async updateAgent(
@@ -761,7 +1104,9 @@ export const api = {
     sandbox_enabled?: boolean,
     enable_file_read?: boolean,
     enable_file_write?: boolean,
-    enable_network?: boolean
+    enable_network?: boolean,
+    permission_mode?: PermissionMode,
+    limits?: ResourceLimits
   ): Promise<Agent> {
     try {
       return await invoke<Agent>('update_agent', { 
@@ -774,7 +1119,9 @@ export const api = {
         sandboxEnabled: sandbox_enabled,
         enableFileRead: enable_file_read,
         enableFileWrite: enable_file_write,
-        enableNetwork: enable_network
+        enableNetwork: enable_network,
+        permissionMode: permission_mode,
+        limits
       });
     } catch (error) {
       console.error("Failed to update agent:", error);
@@ -981,6 +1328,20 @@ export const api = {
     }
   },
 
+  /**
+   * Sends a user message to a running agent session
+   * @param runId - The run ID to send the message to
+   * @param message - The message text; it is handled once the current turn finishes
+   */
+  async sendAgentInput(runId: number, message: string): Promise<void> {
+    try {
+      return await invoke<void>('send_agent_input', { runId, message });
+    } catch (error) {
+      console.error("Failed to send agent input:", error);
+      throw new Error(`Failed to send agent input: ${error instanceof Error ? error.message : 'Unknown error'}`);
+    }
+  },
+
   /**
    * Get live output directly from process stdout buffer
    * @param runId - The run ID to get live output for
@@ -1018,33 +1379,97 @@ export const api = {
 
   /**
    * Executes a new interactive Claude Code session with streaming output
+   * @param permissionMode - How tool use is approved; requests are sent for approval if omitted
+   * @returns The ID of the started execution, which can be passed to cancelClaudeExecution
    */
-  async executeClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return invoke("execute_claude_code", { projectPath, prompt, model });
+  async executeClaudeCode(projectPath: string, prompt: string, model: string, permissionMode?: PermissionMode): Promise<string> {
+    return invoke<string>("execute_claude_code", { projectPath, prompt, model, permissionMode });
   },
 
   /**
    * Continues an existing Claude Code conversation with streaming output
+   * @param permissionMode - How tool use is approved; requests are sent for approval if omitted
+   * @returns The ID of the started execution, which can be passed to cancelClaudeExecution
    */
-  async continueClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return invoke("continue_claude_code", { projectPath, prompt, model });
+  async continueClaudeCode(projectPath: string, prompt: string, model: string, permissionMode?: PermissionMode): Promise<string> {
+    return invoke<string>("continue_claude_code", { projectPath, prompt, model, permissionMode });
   },
 
   /**
    * Resumes an existing Claude Code session by ID with streaming output
+   * @param permissionMode - How tool use is approved; requests are sent for approval if omitted
+   * @returns The ID of the started execution, which can be passed to cancelClaudeExecution
    */
-  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string): Promise<void> {
-    return invoke("resume_claude_code", { projectPath, sessionId, prompt, model });
+  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string, permissionMode?: PermissionMode): Promise<string> {
+    return invoke<string>("resume_claude_code", { projectPath, sessionId, prompt, model, permissionMode });
   },
 
   /**
-   * Cancels the currently running Claude Code execution
-   * @param sessionId - Optional session ID to cancel a specific session
+   * Cancels a running Claude Code execution
+   * @param sessionId - The execution ID or Claude session ID to cancel; other executions keep running
    */
-  async cancelClaudeExecution(sessionId?: string): Promise<void> {
+  async cancelClaudeExecution(sessionId: string): Promise<void> {
     return invoke("cancel_claude_execution", { sessionId });
   },
 
+  /**
+   * Sends a user message to a running Claude Code execution
+   * @param sessionId - The execution ID or Claude session ID
+   * @param message - The message text; it is handled once the current turn finishes
+   */
+  async sendClaudeInput(sessionId: string, message: string): Promise<void> {
+    return invoke("send_claude_input", { sessionId, message });
+  },
+
+  /**
+   * Approves or denies a pending tool use request
+   * @param requestId - The ID of the permission request
+   * @param approve - Whether to allow the tool use
+   * @param reason - Optional reason, shown to Claude when denying
+   */
+  async respondToPermissionRequest(requestId: string, approve: boolean, reason?: string): Promise<void> {
+    try {
+      return await invoke<void>("respond_to_permission_request", { requestId, approve, reason });
+    } catch (error) {
+      console.error("Failed to respond to permission request:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists tool use requests waiting for a decision
+   */
+  async listPendingPermissionRequests(): Promise<PermissionRequest[]> {
+    try {
+      return await invoke<PermissionRequest[]>("list_pending_permission_requests");
+    } catch (error) {
+      console.error("Failed to list pending permission requests:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists recorded permission decisions, newest first
+   * @param sessionId - Optional Claude Code execution to filter by
+   * @param runId - Optional agent run to filter by
+   * @param limit - Maximum number of decisions (defaults to 100)
+   */
+  async listPermissionDecisions(sessionId?: string, runId?: number, limit?: number): Promise<PermissionDecision[]> {
+    try {
+      return await invoke<PermissionDecision[]>("list_permission_decisions", { sessionId, runId, limit });
+    } catch (error) {
+      console.error("Failed to list permission decisions:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists the IDs of running Claude Code executions
+   */
+  async listRunningClaudeSessions(): Promise<string[]> {
+    return invoke<string[]>("list_running_claude_sessions");
+  },
+
   /**
    * Lists files and directories in a given path
    */
@@ -1476,15 +1901,35 @@ export const api = {
   },
 
   /**
-   * Restores a session to a specific checkpoint
+   * Restores a session to a specific checkpoint, optionally rewriting its
+   * transcript so a resumed conversation continues from the checkpoint
    */
   async restoreCheckpoint(
     checkpointId: string,
     sessionId: string,
     projectId: string,
-    projectPath: string
+    projectPath: string,
+    transcript?: TranscriptRestoreMode
   ): Promise<CheckpointResult> {
     return invoke("restore_checkpoint", {
+      checkpointId,
+      sessionId,
+      projectId,
+      projectPath,
+      transcript
+    });
+  },
+
+  /**
+   * Previews what restoring a checkpoint would change, without touching any files
+   */
+  async previewRestoreCheckpoint(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string
+  ): Promise<RestorePlan> {
+    return invoke("preview_restore_checkpoint", {
       checkpointId,
       sessionId,
       projectId,
@@ -1492,6 +1937,27 @@ export const api = {
     });
   },
 
+  /**
+   * Restores only the files matching the given paths or globs from a checkpoint
+   */
+  async restoreCheckpointFiles(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    paths: string[],
+    force: boolean = false
+  ): Promise<PartialRestoreResult> {
+    return invoke("restore_checkpoint_files", {
+      checkpointId,
+      sessionId,
+      projectId,
+      projectPath,
+      paths,
+      force
+    });
+  },
+
   /**
    * Lists all checkpoints for a session
    */
@@ -1528,6 +1994,33 @@ export const api = {
     });
   },
 
+  /**
+   * Merges the branch ending at theirsCheckpointId into the one ending at
+   * oursCheckpointId. Conflicts are returned instead of creating a checkpoint.
+   */
+  async mergeCheckpoints(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    oursCheckpointId: string,
+    theirsCheckpointId: string,
+    description?: string
+  ): Promise<MergeResult> {
+    try {
+      return await invoke<MergeResult>("merge_checkpoints", {
+        sessionId,
+        projectId,
+        projectPath,
+        oursCheckpointId,
+        theirsCheckpointId,
+        description
+      });
+    } catch (error) {
+      console.error("Failed to merge checkpoints:", error);
+      throw error;
+    }
+  },
+
   /**
    * Gets the timeline for a session
    */
@@ -1551,17 +2044,102 @@ export const api = {
     projectId: string,
     projectPath: string,
     autoCheckpointEnabled: boolean,
-    checkpointStrategy: CheckpointStrategy
+    checkpointStrategy: CheckpointStrategy,
+    strategyThresholds?: StrategyThresholds
   ): Promise<void> {
     return invoke("update_checkpoint_settings", {
       sessionId,
       projectId,
       projectPath,
       autoCheckpointEnabled,
-      checkpointStrategy
+      checkpointStrategy,
+      strategyThresholds
     });
   },
 
+  /**
+   * Updates the checkpoint retention policy for a session and applies it.
+   * Returns the number of checkpoints removed.
+   */
+  async updateRetentionPolicy(
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    policy: RetentionPolicy
+  ): Promise<number> {
+    return invoke("update_retention_policy", {
+      sessionId,
+      projectId,
+      projectPath,
+      policy
+    });
+  },
+
+  /**
+   * Sets the tags and milestone flag of a checkpoint
+   */
+  async updateCheckpointLabels(
+    checkpointId: string,
+    sessionId: string,
+    projectId: string,
+    projectPath: string,
+    tags: string[],
+    milestone: boolean
+  ): Promise<Checkpoint> {
+    try {
+      return await invoke<Checkpoint>("update_checkpoint_labels", {
+        checkpointId,
+        sessionId,
+        projectId,
+        projectPath,
+        tags,
+        milestone
+      });
+    } catch (error) {
+      console.error("Failed to update checkpoint labels:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Searches the checkpoints of every session of a project, newest first
+   */
+  async searchCheckpoints(
+    projectId: string,
+    query: CheckpointQuery
+  ): Promise<CheckpointReference[]> {
+    try {
+      return await invoke<CheckpointReference[]>("search_checkpoints", {
+        projectId,
+        query
+      });
+    } catch (error) {
+      console.error("Failed to search checkpoints:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets every checkpoint at which a file changed, newest first. Covers the
+   * whole project unless a session is given.
+   */
+  async getFileHistory(
+    projectId: string,
+    filePath: string,
+    sessionId?: string
+  ): Promise<FileHistoryEntry[]> {
+    try {
+      return await invoke<FileHistoryEntry[]>("get_file_history", {
+        projectId,
+        sessionId,
+        filePath
+      });
+    } catch (error) {
+      console.error("Failed to get file history:", error);
+      throw error;
+    }
+  },
+
   /**
    * Gets diff between two checkpoints
    */
@@ -1584,6 +2162,99 @@ export const api = {
     }
   },
 
+  /**
+   * Exports a session's checkpoint timeline to an archive file
+   */
+  async exportCheckpointArchive(
+    sessionId: string,
+    projectId: string,
+    outputPath: string
+  ): Promise<ArchiveSummary> {
+    try {
+      return await invoke<ArchiveSummary>("export_checkpoint_archive", {
+        sessionId,
+        projectId,
+        outputPath
+      });
+    } catch (error) {
+      console.error("Failed to export checkpoint archive:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Imports a checkpoint timeline archive into a project
+   */
+  async importCheckpointArchive(
+    archivePath: string,
+    projectId: string,
+    newSessionId?: string
+  ): Promise<ArchiveSummary> {
+    try {
+      return await invoke<ArchiveSummary>("import_checkpoint_archive", {
+        archivePath,
+        projectId,
+        newSessionId
+      });
+    } catch (error) {
+      console.error("Failed to import checkpoint archive:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Verifies a session's checkpoint storage, optionally pruning broken checkpoints
+   */
+  async verifyCheckpointTimeline(
+    sessionId: string,
+    projectId: string,
+    repair: boolean = false
+  ): Promise<TimelineVerifyReport> {
+    try {
+      return await invoke<TimelineVerifyReport>("verify_checkpoint_timeline", {
+        sessionId,
+        projectId,
+        repair
+      });
+    } catch (error) {
+      console.error("Failed to verify checkpoint timeline:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Moves per-session checkpoint content into shared per-project stores
+   */
+  async migrateCheckpointStorage(): Promise<MigrationReport> {
+    try {
+      return await invoke<MigrationReport>("migrate_checkpoint_storage");
+    } catch (error) {
+      console.error("Failed to migrate checkpoint storage:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Selects the checkpoint storage backend for a project. Only allowed before
+   * the project's first checkpoint.
+   */
+  async setCheckpointBackend(
+    projectId: string,
+    projectPath: string,
+    backend: CheckpointBackendKind
+  ): Promise<CheckpointBackendConfig> {
+    try {
+      return await invoke<CheckpointBackendConfig>("set_checkpoint_backend", {
+        projectId,
+        projectPath,
+        backend
+      });
+    } catch (error) {
+      console.error("Failed to set checkpoint backend:", error);
+      throw error;
+    }
+  },
+
   /**
    * Tracks a message for checkpointing
    */
@@ -1662,6 +2333,8 @@ export const api = {
     checkpoint_strategy: CheckpointStrategy;
     total_checkpoints: number;
     current_checkpoint_id?: string;
+    retention_policy: RetentionPolicy;
+    strategy_thresholds: StrategyThresholds;
   }> {
     try {
       return await invoke("get_checkpoint_settings", {
@@ -1888,6 +2561,33 @@ export const api = {
     }
   },
 
+  /**
+   * Gets the seconds killed processes get to exit before SIGKILL is sent
+   * @returns Promise resolving to the grace period in seconds
+   */
+  async getKillGracePeriod(): Promise<number> {
+    try {
+      return await invoke<number>("get_kill_grace_period");
+    } catch (error) {
+      console.error("Failed to get kill grace period:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets the seconds killed processes get to exit before SIGKILL is sent
+   * @param seconds - The grace period, at most 300 seconds
+   * @returns Promise resolving when the setting is saved
+   */
+  async setKillGracePeriod(seconds: number): Promise<void> {
+    try {
+      return await invoke<void>("set_kill_grace_period", { seconds });
+    } catch (error) {
+      console.error("Failed to set kill grace period:", error);
+      throw error;
+    }
+  },
+
   /**
    * Captures a screenshot of a specific region in the window
    * @param url - The URL to capture